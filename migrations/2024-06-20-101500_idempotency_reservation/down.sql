@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM idempotency WHERE request IS NULL;
+ALTER TABLE idempotency ALTER COLUMN request SET NOT NULL;
+ALTER TYPE http_request RENAME ATTRIBUTE response_body TO reponse_body;
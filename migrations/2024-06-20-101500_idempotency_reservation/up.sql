@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TYPE http_request RENAME ATTRIBUTE reponse_body TO response_body;
+ALTER TABLE idempotency ALTER COLUMN request DROP NOT NULL;
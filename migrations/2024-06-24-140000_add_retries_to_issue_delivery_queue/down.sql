@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE issue_delivery_queue
+	DROP COLUMN attempts,
+	DROP COLUMN last_error,
+	DROP COLUMN next_attempt_at;
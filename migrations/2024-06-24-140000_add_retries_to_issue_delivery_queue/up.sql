@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE issue_delivery_queue
+	ADD COLUMN attempts SMALLINT NOT NULL DEFAULT 0,
+	ADD COLUMN last_error TEXT NULL,
+	ADD COLUMN next_attempt_at timestamptz NOT NULL DEFAULT now();
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE subscriptions
+    ALTER COLUMN status TYPE TEXT
+    USING (
+        CASE status
+            WHEN 'pending' THEN 'pending_confirmation'
+            ELSE status::TEXT
+        END
+    );
+DROP TYPE subscription_status;
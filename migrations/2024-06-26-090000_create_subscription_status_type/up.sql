@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TYPE subscription_status AS ENUM (
+    'pending',
+    'confirmed',
+    'unsubscribed',
+    'bounced',
+    'complained'
+);
+ALTER TABLE subscriptions
+    ALTER COLUMN status TYPE subscription_status
+    USING (
+        CASE status
+            WHEN 'pending_confirmation' THEN 'pending'
+            ELSE status
+        END
+    )::subscription_status;
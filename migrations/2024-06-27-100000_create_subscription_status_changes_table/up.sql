@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE subscription_status_changes (
+	id uuid NOT NULL PRIMARY KEY,
+	subscriber_id uuid NOT NULL
+		REFERENCES subscriptions (id) ON DELETE CASCADE,
+	from_status subscription_status NOT NULL,
+	to_status subscription_status NOT NULL,
+	changed_at timestamptz NOT NULL
+);
+CREATE INDEX subscription_status_changes_subscriber_id_idx
+	ON subscription_status_changes (subscriber_id, changed_at);
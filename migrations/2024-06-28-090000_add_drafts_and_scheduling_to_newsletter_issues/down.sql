@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX newsletter_issues_scheduled_idx;
+DELETE FROM newsletter_issues WHERE published_at IS NULL;
+ALTER TABLE newsletter_issues
+	DROP COLUMN author_id,
+	DROP COLUMN status,
+	DROP COLUMN created_at,
+	DROP COLUMN scheduled_for,
+	DROP COLUMN recipients_count,
+	DROP COLUMN delivered_count,
+	ALTER COLUMN published_at SET NOT NULL;
+DROP TYPE newsletter_issue_status;
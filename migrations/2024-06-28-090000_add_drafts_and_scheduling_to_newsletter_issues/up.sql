@@ -0,0 +1,30 @@
+-- Your SQL goes here
+CREATE TYPE newsletter_issue_status AS ENUM (
+	'draft',
+	'scheduled',
+	'sending',
+	'sent'
+);
+ALTER TABLE newsletter_issues
+	ADD COLUMN author_id uuid
+		REFERENCES users (user_id) ON DELETE SET NULL,
+	ADD COLUMN status newsletter_issue_status NOT NULL DEFAULT 'sent',
+	ADD COLUMN created_at timestamptz,
+	ADD COLUMN scheduled_for timestamptz,
+	ADD COLUMN recipients_count INTEGER NOT NULL DEFAULT 0,
+	ADD COLUMN delivered_count INTEGER NOT NULL DEFAULT 0,
+	ALTER COLUMN published_at DROP NOT NULL;
+-- Issues published so far went out right away.
+UPDATE newsletter_issues SET created_at = published_at;
+UPDATE newsletter_issues SET status = 'sending'
+	WHERE EXISTS (
+		SELECT 1 FROM issue_delivery_queue
+		WHERE issue_delivery_queue.newsletter_issue_id
+			= newsletter_issues.newsletter_issue_id
+	);
+ALTER TABLE newsletter_issues
+	ALTER COLUMN created_at SET NOT NULL,
+	ALTER COLUMN status DROP DEFAULT;
+CREATE INDEX newsletter_issues_scheduled_idx
+	ON newsletter_issues (scheduled_for)
+	WHERE status = 'scheduled';
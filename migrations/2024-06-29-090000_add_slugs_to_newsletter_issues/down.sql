@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX newsletter_issues_published_at_idx;
+ALTER TABLE newsletter_issues DROP COLUMN slug;
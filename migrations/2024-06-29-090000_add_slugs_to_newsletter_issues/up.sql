@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE newsletter_issues ADD COLUMN slug TEXT UNIQUE;
+-- Issues published so far get a slug that cannot clash with each other.
+UPDATE newsletter_issues
+	SET slug = COALESCE(
+		NULLIF(
+			trim(BOTH '-' FROM
+				lower(regexp_replace(title, '[^a-zA-Z0-9]+', '-', 'g'))),
+			''
+		),
+		'issue'
+	) || '-' || right(replace(newsletter_issue_id::text, '-', ''), 8)
+	WHERE published_at IS NOT NULL;
+CREATE INDEX newsletter_issues_published_at_idx
+	ON newsletter_issues (published_at DESC)
+	WHERE published_at IS NOT NULL;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT;
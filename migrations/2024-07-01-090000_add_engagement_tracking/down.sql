@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE issue_clicks;
+DROP TABLE issue_opens;
+DROP TABLE issue_links;
+ALTER TABLE newsletter_issues DROP COLUMN tracking_enabled;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+ALTER TABLE newsletter_issues
+	ADD COLUMN tracking_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE TABLE issue_links (
+	id uuid NOT NULL PRIMARY KEY,
+	newsletter_issue_id uuid NOT NULL
+		REFERENCES newsletter_issues (newsletter_issue_id) ON DELETE CASCADE,
+	url TEXT NOT NULL,
+	UNIQUE (newsletter_issue_id, url)
+);
+CREATE TABLE issue_opens (
+	id uuid NOT NULL PRIMARY KEY,
+	newsletter_issue_id uuid NOT NULL
+		REFERENCES newsletter_issues (newsletter_issue_id) ON DELETE CASCADE,
+	subscriber_id uuid NOT NULL
+		REFERENCES subscriptions (id) ON DELETE CASCADE,
+	opened_at timestamptz NOT NULL
+);
+CREATE INDEX issue_opens_newsletter_issue_id_idx
+	ON issue_opens (newsletter_issue_id);
+CREATE TABLE issue_clicks (
+	id uuid NOT NULL PRIMARY KEY,
+	link_id uuid NOT NULL
+		REFERENCES issue_links (id) ON DELETE CASCADE,
+	subscriber_id uuid NOT NULL
+		REFERENCES subscriptions (id) ON DELETE CASCADE,
+	clicked_at timestamptz NOT NULL
+);
+CREATE INDEX issue_clicks_link_id_idx ON issue_clicks (link_id);
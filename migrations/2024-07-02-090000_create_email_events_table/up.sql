@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE email_events (
+	id uuid NOT NULL PRIMARY KEY,
+	subscriber_id uuid
+		REFERENCES subscriptions (id) ON DELETE SET NULL,
+	email TEXT NOT NULL,
+	kind TEXT NOT NULL,
+	description TEXT NOT NULL,
+	status_changed BOOLEAN NOT NULL,
+	occurred_at timestamptz NOT NULL,
+	received_at timestamptz NOT NULL
+);
+CREATE INDEX email_events_received_at_idx ON email_events (received_at);
+CREATE INDEX email_events_subscriber_id_idx
+	ON email_events (subscriber_id, occurred_at);
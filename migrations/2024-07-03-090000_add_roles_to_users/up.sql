@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TYPE user_role AS ENUM (
+    'owner',
+    'editor',
+    'viewer'
+);
+-- Everyone who could log in so far had full access.
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'owner';
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'viewer';
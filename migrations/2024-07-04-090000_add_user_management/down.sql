@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_invitations;
+ALTER TABLE idempotency
+	DROP CONSTRAINT idempotency_user_id_fkey,
+	ADD CONSTRAINT idempotency_user_id_fkey
+		FOREIGN KEY (user_id) REFERENCES users (user_id);
+ALTER TABLE users DROP COLUMN disabled_at;
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN disabled_at timestamptz;
+
+-- Deleting a user also drops their saved idempotent responses.
+ALTER TABLE idempotency
+	DROP CONSTRAINT idempotency_user_id_fkey,
+	ADD CONSTRAINT idempotency_user_id_fkey
+		FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE;
+
+CREATE TABLE user_invitations (
+	token TEXT NOT NULL PRIMARY KEY,
+	email TEXT NOT NULL,
+	role user_role NOT NULL,
+	invited_by uuid
+		REFERENCES users (user_id) ON DELETE SET NULL,
+	created_at timestamptz NOT NULL,
+	expires_at timestamptz NOT NULL
+);
+
+-- The seeded account's password is public. Unless it was changed, remove the
+-- account: the first-run setup creates the first owner instead.
+DELETE FROM users
+	WHERE user_id = 'c9f4598c-e76c-46d4-811d-620478288ee7'
+	AND password_hash = '$argon2id$v=19$m=15000,t=2,p=1$fvhmK//LNM9SR3yOrcM9sw$c5v2kqRGsZ8hBfrOwsnfb/ttinhbu2MLgtEO10T5nzo';
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- The TOTP secret is encrypted by the application. The last accepted step
+-- keeps a code from being used twice.
+ALTER TABLE users
+	ADD COLUMN totp_secret TEXT,
+	ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE recovery_codes (
+	id uuid NOT NULL PRIMARY KEY,
+	user_id uuid NOT NULL
+		REFERENCES users (user_id) ON DELETE CASCADE,
+	code_hash TEXT NOT NULL,
+	used_at timestamptz
+);
+CREATE INDEX recovery_codes_user_id_idx ON recovery_codes (user_id);
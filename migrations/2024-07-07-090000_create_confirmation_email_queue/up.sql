@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Confirmation emails waiting for the delivery worker, such as the ones
+-- requested by a subscriber import.
+CREATE TABLE confirmation_email_queue (
+	subscription_token TEXT NOT NULL PRIMARY KEY
+		REFERENCES subscription_tokens (subscription_token) ON DELETE CASCADE,
+	attempts SMALLINT NOT NULL DEFAULT 0,
+	next_attempt_at timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- The tokens cannot be recovered from their hashes.
+DELETE FROM user_invitations;
+ALTER TABLE user_invitations RENAME COLUMN token_hash TO token;
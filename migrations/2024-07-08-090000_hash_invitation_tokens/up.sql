@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Invitations keep only a hash of their token, as password resets do. The
+-- pending ones are hashed the way `InvitationToken::hash` does it.
+ALTER TABLE user_invitations RENAME COLUMN token TO token_hash;
+UPDATE user_invitations
+	SET token_hash = rtrim(
+		translate(encode(sha256(convert_to(token_hash, 'UTF8')), 'base64'), '+/', '-_'),
+		'='
+	);
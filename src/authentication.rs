@@ -16,8 +16,14 @@ use argon2::{
 use secrecy::{ExposeSecret, Secret};
 use uuid::Uuid;
 
+mod csrf;
+mod login_throttle;
 mod middleware;
+mod two_factor;
+pub use csrf::*;
+pub use login_throttle::*;
 pub use middleware::*;
+pub use two_factor::*;
 
 #[derive(thiserror::Error, Debug)]
 pub enum AuthError {
@@ -99,18 +105,26 @@ pub async fn change_password(
     password: Password,
     connection: &mut DatabaseConnection,
 ) -> Result<(), anyhow::Error> {
-    let password_hash =
-        spawn_blocking_with_tracing(move || compute_password_hash(&password))
-            .await?
-            .context("Failed to hash password.")?;
+    let password_hash = hash_password(password).await?;
     change_password_query(connection, user_id, password_hash)
         .await
         .context("Failed to change password in the database.")?;
     Ok(())
 }
 
+/// Hashes a password the way `validate_credentials` expects it stored.
+pub async fn hash_password(
+    password: Password,
+) -> Result<Secret<String>, anyhow::Error> {
+    spawn_blocking_with_tracing(move || {
+        compute_password_hash(password.as_ref())
+    })
+    .await?
+    .context("Failed to hash password.")
+}
+
 fn compute_password_hash(
-    password: &Password,
+    password: &Secret<String>,
 ) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
     let password_hash = Argon2::new(
@@ -118,7 +132,7 @@ fn compute_password_hash(
         argon2::Version::V0x13,
         Params::new(15000, 2, 1, None).unwrap(),
     )
-    .hash_password(password.as_ref().expose_secret().as_bytes(), &salt)?
+    .hash_password(password.expose_secret().as_bytes(), &salt)?
     .to_string();
     Ok(Secret::new(password_hash))
 }
@@ -0,0 +1,160 @@
+use anyhow::Context;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{FromRef, FromRequest, Multipart, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use axum_extra::extract::cookie::Key;
+
+use crate::{flash::Flash, session_state::TypedSession};
+
+/// Form field, or header for scripts, carrying the session's CSRF token.
+const CSRF_FIELD: &str = "csrf_token";
+const CSRF_HEADER: &str = "x-csrf-token";
+/// Matches the body limit axum applies to the form extractors.
+const MAX_FORM_BYTES: usize = 2 * 1024 * 1024;
+
+/// Where `require_csrf_token` sends requests it rejects, unless they
+/// came from one of our pages.
+#[derive(Clone)]
+pub struct CsrfProtection {
+    key: Key,
+    redirect_to: &'static str,
+    body_limit: usize,
+}
+
+impl CsrfProtection {
+    pub fn new(key: Key, redirect_to: &'static str) -> Self {
+        Self {
+            key,
+            redirect_to,
+            body_limit: MAX_FORM_BYTES,
+        }
+    }
+
+    /// Reads bodies up to `limit` looking for the token, for routes that
+    /// accept uploads larger than a form.
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit.max(MAX_FORM_BYTES);
+        self
+    }
+}
+
+impl FromRef<CsrfProtection> for Key {
+    fn from_ref(state: &CsrfProtection) -> Self {
+        state.key.clone()
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CsrfForm {
+    csrf_token: String,
+}
+
+/// Rejects state-changing requests that do not post back the CSRF token
+/// of their session, redirecting them with a flash message.
+#[tracing::instrument(
+    name = "Middleware CSRF Checking",
+    skip(protection, session, flash, request, next)
+)]
+pub async fn require_csrf_token(
+    State(protection): State<CsrfProtection>,
+    session: TypedSession,
+    flash: Flash,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method().is_safe() {
+        return next.run(request).await;
+    }
+    let redirect_to = referring_page(&request)
+        .unwrap_or_else(|| protection.redirect_to.to_string());
+    let (request, candidate) =
+        match read_csrf_token(request, protection.body_limit).await {
+            Ok(read) => read,
+            Err(e) => {
+                tracing::error!("{} Reason {:?}", e, e);
+                return (
+                    flash.error(e.to_string()),
+                    Redirect::to(&redirect_to),
+                )
+                    .into_response();
+            }
+        };
+    let verified = match candidate {
+        Some(candidate) => session.verify_csrf_token(&candidate).await,
+        None => Ok(false),
+    };
+    let flash = match verified {
+        Ok(true) => return next.run(request).await,
+        Ok(false) => flash.warning("Your form has expired, please try again."),
+        Err(e) => {
+            tracing::error!("Could not check the CSRF token. Reason {:?}", e);
+            flash.error("Could not check the CSRF token")
+        }
+    };
+    (flash, Redirect::to(&redirect_to)).into_response()
+}
+
+/// The path of the page the form was posted from, when it is ours.
+fn referring_page(request: &Request) -> Option<String> {
+    let host = request.headers().get(header::HOST)?.to_str().ok()?;
+    let referer = request.headers().get(header::REFERER)?.to_str().ok()?;
+    let (_, url) = referer.split_once("://")?;
+    let path = url.strip_prefix(host)?;
+    // `//host` would be followed as a link to another site.
+    (path.starts_with('/') && !path.starts_with("//")).then(|| path.to_string())
+}
+
+/// Finds the token in the header or the form, giving back the request
+/// with its body intact for the handler.
+async fn read_csrf_token(
+    request: Request,
+    body_limit: usize,
+) -> Result<(Request, Option<String>), anyhow::Error> {
+    if let Some(token) = request.headers().get(CSRF_HEADER) {
+        let token = token.to_str().ok().map(str::to_string);
+        return Ok((request, token));
+    }
+    let Some(content_type) = request.headers().get(header::CONTENT_TYPE) else {
+        return Ok((request, None));
+    };
+    let content_type = content_type.clone();
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, body_limit)
+        .await
+        .context("Could not read the form")?;
+    let form = Request::builder()
+        .method(parts.method.clone())
+        .header(header::CONTENT_TYPE, content_type.clone())
+        .body(Body::from(bytes.clone()))
+        .context("Could not read the form")?;
+    let token = if is_multipart(&content_type) {
+        multipart_token(form).await
+    } else {
+        Form::<CsrfForm>::from_request(form, &())
+            .await
+            .ok()
+            .map(|Form(form)| form.csrf_token)
+    };
+    Ok((Request::from_parts(parts, Body::from(bytes)), token))
+}
+
+fn is_multipart(content_type: &HeaderValue) -> bool {
+    content_type
+        .to_str()
+        .is_ok_and(|value| value.starts_with("multipart/form-data"))
+}
+
+async fn multipart_token(form: Request) -> Option<String> {
+    let mut multipart = Multipart::from_request(form, &()).await.ok()?;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() == Some(CSRF_FIELD) {
+            return field.text().await.ok();
+        }
+    }
+    None
+}
@@ -0,0 +1,191 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use tower_sessions_redis_store::fred::{prelude::*, types::Expiration};
+use uuid::Uuid;
+
+use crate::configuration::LoginThrottleSettings;
+
+/// What to do with a login attempt before checking the credentials.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoginAttempt {
+    Allowed,
+    Delayed(Duration),
+    Locked { retry_after: Duration },
+}
+
+impl LoginAttempt {
+    fn for_failures(settings: &LoginThrottleSettings, failures: u64) -> Self {
+        if failures >= settings.slowdown_after {
+            Self::Delayed(Duration::from_millis(settings.slowdown_delay_ms))
+        } else {
+            Self::Allowed
+        }
+    }
+}
+
+/// Counts failed logins per username and per client IP in Redis.
+///
+/// Each failure is a member of a sorted set scored by its timestamp, so
+/// the window slides instead of resetting on a fixed boundary.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    redis: RedisPool,
+    settings: LoginThrottleSettings,
+}
+
+impl LoginThrottle {
+    pub fn new(redis: RedisPool, settings: LoginThrottleSettings) -> Self {
+        Self { redis, settings }
+    }
+
+    fn user_key(username: &str) -> String {
+        format!("login_failures:user:{}", username)
+    }
+
+    fn ip_key(ip: IpAddr) -> String {
+        format!("login_failures:ip:{}", ip)
+    }
+
+    fn lock_key(key: &str) -> String {
+        format!("{}:locked", key)
+    }
+
+    #[tracing::instrument(name = "Check login throttle", skip(self))]
+    pub async fn check(
+        &self,
+        username: &str,
+        ip: IpAddr,
+    ) -> Result<LoginAttempt, RedisError> {
+        let user_key = Self::user_key(username);
+        for key in [&user_key, &Self::ip_key(ip)] {
+            let ttl: i64 = self.redis.ttl(Self::lock_key(key)).await?;
+            if ttl > 0 {
+                return Ok(LoginAttempt::Locked {
+                    retry_after: Duration::from_secs(ttl as u64),
+                });
+            }
+        }
+        let failures = self.recent_failures(&user_key).await?;
+        Ok(LoginAttempt::for_failures(&self.settings, failures))
+    }
+
+    /// Records a failed attempt, locking the username or the IP once it
+    /// goes over its limit.
+    #[tracing::instrument(name = "Record failed login", skip(self))]
+    pub async fn record_failure(
+        &self,
+        username: &str,
+        ip: IpAddr,
+    ) -> Result<(), RedisError> {
+        let user_key = Self::user_key(username);
+        let failures = self.add_failure(&user_key).await?;
+        if failures >= self.settings.lockout_after {
+            self.lock(&user_key).await?;
+            tracing::warn!(
+                username,
+                %ip,
+                failures,
+                lockout_seconds = self.settings.lockout_seconds,
+                "Locked out username after repeated failed logins"
+            );
+        }
+        let ip_key = Self::ip_key(ip);
+        let failures = self.add_failure(&ip_key).await?;
+        if failures >= self.settings.ip_lockout_after {
+            self.lock(&ip_key).await?;
+            tracing::warn!(
+                %ip,
+                failures,
+                lockout_seconds = self.settings.lockout_seconds,
+                "Locked out client IP after repeated failed logins"
+            );
+        }
+        Ok(())
+    }
+
+    /// Forgets the failures of a username once it has logged in.
+    pub async fn record_success(
+        &self,
+        username: &str,
+    ) -> Result<(), RedisError> {
+        let _: () = self.redis.del(Self::user_key(username)).await?;
+        Ok(())
+    }
+
+    async fn recent_failures(&self, key: &str) -> Result<u64, RedisError> {
+        let cutoff = chrono::Utc::now().timestamp_millis()
+            - self.settings.window_seconds * 1000;
+        let _: () =
+            self.redis.zremrangebyscore(key, 0.0, cutoff as f64).await?;
+        self.redis.zcard(key).await
+    }
+
+    async fn add_failure(&self, key: &str) -> Result<u64, RedisError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let _: () = self
+            .redis
+            .zadd(
+                key,
+                None,
+                None,
+                false,
+                false,
+                (now as f64, Uuid::now_v7().to_string()),
+            )
+            .await?;
+        let _: () =
+            self.redis.expire(key, self.settings.window_seconds).await?;
+        self.recent_failures(key).await
+    }
+
+    async fn lock(&self, key: &str) -> Result<(), RedisError> {
+        let _: () = self
+            .redis
+            .set(
+                Self::lock_key(key),
+                1,
+                Some(Expiration::EX(self.settings.lockout_seconds)),
+                None,
+                false,
+            )
+            .await?;
+        // The count starts over once the lock expires.
+        let _: () = self.redis.del(key).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> LoginThrottleSettings {
+        LoginThrottleSettings {
+            window_seconds: 900,
+            slowdown_after: 3,
+            slowdown_delay_ms: 1000,
+            lockout_after: 10,
+            ip_lockout_after: 50,
+            lockout_seconds: 900,
+        }
+    }
+
+    #[test]
+    fn the_first_failures_are_not_slowed_down() {
+        for failures in 0..3 {
+            assert_eq!(
+                LoginAttempt::for_failures(&settings(), failures),
+                LoginAttempt::Allowed
+            );
+        }
+    }
+
+    #[test]
+    fn attempts_are_slowed_down_past_the_threshold() {
+        assert_eq!(
+            LoginAttempt::for_failures(&settings(), 3),
+            LoginAttempt::Delayed(Duration::from_secs(1))
+        );
+    }
+}
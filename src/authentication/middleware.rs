@@ -1,20 +1,25 @@
 use std::{fmt::Display, ops::Deref};
 
-use crate::session_state::TypedSession;
+use crate::{
+    database::queries::get_user_role, domain::UserRole,
+    session_state::TypedSession, startup::ApplicationState, TEMPLATES,
+};
 use anyhow::Context;
 use axum::{
-    extract::Request,
+    extract::{Request, State},
     http::StatusCode,
     middleware::Next,
-    response::{IntoResponse, Redirect, Response},
+    response::{Html, IntoResponse, Redirect, Response},
+    Extension,
 };
 use uuid::Uuid;
 
 #[tracing::instrument(
     name = "Middleware Credential Checking",
-    skip(session, request, next)
+    skip(app_state, session, request, next)
 )]
 pub async fn check_credentials(
+    State(app_state): State<ApplicationState>,
     session: TypedSession,
     mut request: Request,
     next: Next,
@@ -32,10 +37,58 @@ pub async fn check_credentials(
         }
     };
 
+    // The role is read on every request so that changes apply right away.
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .map_err(|err| {
+                tracing::error!("Could not get database pool {:?}", err);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            })?;
+    let role = match get_user_role(&mut connection, id).await {
+        Ok(Some(role)) => role,
+        Ok(None) => {
+            let _ = session.logout().await;
+            return Ok(Redirect::to("/login").into_response());
+        }
+        Err(err) => {
+            tracing::error!("Could not get the user role {:?}", err);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
     request.extensions_mut().insert(UserId(id));
+    request.extensions_mut().insert(role);
     Ok(next.run(request).await)
 }
 
+/// Only lets through users whose role includes `required`, answering
+/// everyone else with a 403 page.
+///
+/// Layered after `check_credentials`, which provides the user's role.
+#[tracing::instrument(name = "Middleware Role Checking", skip(request, next))]
+pub async fn require_role(
+    State(required): State<UserRole>,
+    Extension(role): Extension<UserRole>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if role.includes(required) {
+        return next.run(request).await;
+    }
+    tracing::warn!("Access denied to a user lacking the {} role.", required);
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("role", &role);
+    tera_context.insert("required", &required);
+    match TEMPLATES.render("pages/forbidden.html", &tera_context) {
+        Ok(body) => (StatusCode::FORBIDDEN, Html(body)).into_response(),
+        Err(err) => {
+            tracing::error!("Could not render forbidden page {:?}", err);
+            StatusCode::FORBIDDEN.into_response()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct UserId(Uuid);
 
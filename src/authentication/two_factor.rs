@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Context};
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use super::compute_password_hash;
+use crate::{
+    domain::TotpSecret, models::RecoveryCodes,
+    telemetry::spawn_blocking_with_tracing,
+};
+
+/// Recovery codes handed out when two-factor authentication is enabled.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Encrypts a TOTP secret for storage on the user's row.
+///
+/// The key is derived from the application's `hmac_secret`, and the user id
+/// is authenticated along with the secret so that it cannot be moved to
+/// another account.
+pub fn encrypt_totp_secret(
+    secret: &TotpSecret,
+    user_id: Uuid,
+    hmac_secret: &Secret<String>,
+) -> Result<String, anyhow::Error> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("Could not generate a nonce."))?;
+    let mut sealed = secret.as_bytes().to_vec();
+    cipher(hmac_secret)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(user_id.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("Could not encrypt the TOTP secret."))?;
+    let mut stored = nonce.to_vec();
+    stored.extend(sealed);
+    Ok(URL_SAFE_NO_PAD.encode(stored))
+}
+
+pub fn decrypt_totp_secret(
+    stored: &str,
+    user_id: Uuid,
+    hmac_secret: &Secret<String>,
+) -> Result<TotpSecret, anyhow::Error> {
+    let stored = URL_SAFE_NO_PAD
+        .decode(stored)
+        .context("The stored TOTP secret is not valid base64.")?;
+    if stored.len() < NONCE_LEN {
+        return Err(anyhow!("The stored TOTP secret is too short."));
+    }
+    let (nonce, sealed) = stored.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| anyhow!("The stored TOTP nonce is invalid."))?;
+    let mut sealed = sealed.to_vec();
+    let secret = cipher(hmac_secret)
+        .open_in_place(nonce, Aad::from(user_id.as_bytes()), &mut sealed)
+        .map_err(|_| anyhow!("Could not decrypt the TOTP secret."))?;
+    Ok(TotpSecret::from_bytes(secret.to_vec()))
+}
+
+fn cipher(hmac_secret: &Secret<String>) -> LessSafeKey {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(hmac_secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any size");
+    mac.update(b"totp-secret-encryption");
+    let key = mac.finalize().into_bytes();
+    LessSafeKey::new(
+        UnboundKey::new(&aead::AES_256_GCM, &key)
+            .expect("SHA-256 digests are valid AES-256 keys"),
+    )
+}
+
+/// Codes shaped like `abcde-fghij`, to be shown to the user only once.
+pub fn generate_recovery_codes() -> Vec<Secret<String>> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String =
+                std::iter::repeat_with(|| rng.sample(Alphanumeric))
+                    .map(|c| char::from(c).to_ascii_lowercase())
+                    .take(10)
+                    .collect();
+            Secret::new(format!("{}-{}", &code[..5], &code[5..]))
+        })
+        .collect()
+}
+
+/// Hashes recovery codes with the same argon2 parameters as passwords.
+pub async fn hash_recovery_codes(
+    user_id: Uuid,
+    codes: Vec<Secret<String>>,
+) -> Result<Vec<RecoveryCodes>, anyhow::Error> {
+    spawn_blocking_with_tracing(move || {
+        codes
+            .iter()
+            .map(|code| {
+                let hash = compute_password_hash(code)?;
+                Ok(RecoveryCodes::new(user_id, hash.expose_secret()))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()
+    })
+    .await?
+    .context("Failed to hash recovery codes.")
+}
+
+/// Finds which of the stored codes `candidate` is, if any.
+pub async fn find_recovery_code(
+    candidate: Secret<String>,
+    stored: Vec<RecoveryCodes>,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    spawn_blocking_with_tracing(move || {
+        let candidate = candidate.expose_secret().trim().to_ascii_lowercase();
+        for code in stored {
+            let hash = PasswordHash::new(&code.code_hash)
+                .context("Failed to parse hash in PHC format.")?;
+            if Argon2::default()
+                .verify_password(candidate.as_bytes(), &hash)
+                .is_ok()
+            {
+                return Ok(Some(code.id));
+            }
+        }
+        Ok(None)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    fn hmac_secret() -> Secret<String> {
+        Secret::new("a-long-and-secure-hmac-secret".into())
+    }
+
+    #[test]
+    fn encrypted_secrets_round_trip() {
+        let secret = TotpSecret::generate();
+        let user_id = Uuid::now_v7();
+        let stored =
+            encrypt_totp_secret(&secret, user_id, &hmac_secret()).unwrap();
+        assert!(!stored.contains(&secret.to_base32()));
+        let decrypted =
+            decrypt_totp_secret(&stored, user_id, &hmac_secret()).unwrap();
+        assert_eq!(decrypted.as_bytes(), secret.as_bytes());
+    }
+
+    #[test]
+    fn encrypted_secrets_are_bound_to_their_user_and_key() {
+        let secret = TotpSecret::generate();
+        let user_id = Uuid::now_v7();
+        let stored =
+            encrypt_totp_secret(&secret, user_id, &hmac_secret()).unwrap();
+        assert_ok!(decrypt_totp_secret(&stored, user_id, &hmac_secret()));
+        assert_err!(decrypt_totp_secret(
+            &stored,
+            Uuid::now_v7(),
+            &hmac_secret()
+        ));
+        assert_err!(decrypt_totp_secret(
+            &stored,
+            user_id,
+            &Secret::new("another-secret".into())
+        ));
+    }
+
+    #[test]
+    fn recovery_codes_are_distinct() {
+        let codes = generate_recovery_codes();
+        let unique: std::collections::HashSet<_> = codes
+            .iter()
+            .map(|code| code.expose_secret().clone())
+            .collect();
+        assert_eq!(unique.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|code| code.expose_secret().len() == 11));
+    }
+}
@@ -0,0 +1,132 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{
+        rejection::ExtensionRejection, ConnectInfo, FromRef, FromRequestParts,
+    },
+    http::request::Parts,
+};
+use ipnet::IpNet;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Proxies, such as the load balancer in front of the app, whose
+/// `X-Forwarded-For` header is believed.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Arc<[IpNet]>);
+
+impl TrustedProxies {
+    pub fn new(proxies: Vec<IpNet>) -> Self {
+        Self(proxies.into())
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|proxy| proxy.contains(&ip))
+    }
+
+    /// Every proxy appends the address it got the request from, so the
+    /// client is the last address that is not one of our proxies. Anything
+    /// before it could have been written by the client itself.
+    fn client_ip<'a>(
+        &self,
+        peer: IpAddr,
+        forwarded_for: impl DoubleEndedIterator<Item = &'a str>,
+    ) -> IpAddr {
+        let mut client = peer;
+        for hop in forwarded_for.rev() {
+            if !self.contains(client) {
+                break;
+            }
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+        }
+        client
+    }
+}
+
+/// The address of the client, past the trusted proxies.
+///
+/// Behind a proxy every connection comes from the proxy, so the connection's
+/// peer only identifies the client when it is not one of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    TrustedProxies: FromRef<S>,
+{
+    type Rejection = ExtensionRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) =
+            ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await?;
+        let forwarded_for: Vec<&str> = parts
+            .headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        let proxies = TrustedProxies::from_ref(state);
+        Ok(Self(
+            proxies.client_ip(peer.ip(), forwarded_for.into_iter()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROXY: &str = "10.0.0.2";
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()])
+    }
+
+    fn client_ip(proxies: &TrustedProxies, peer: &str, header: &str) -> String {
+        proxies
+            .client_ip(peer.parse().unwrap(), header.split(','))
+            .to_string()
+    }
+
+    #[test]
+    fn untrusted_peers_are_the_client() {
+        let ip = client_ip(&proxies(), "203.0.113.7", "198.51.100.1");
+        assert_eq!(ip, "203.0.113.7");
+    }
+
+    #[test]
+    fn trusted_proxies_forward_the_client() {
+        let ip = client_ip(&proxies(), PROXY, "198.51.100.1");
+        assert_eq!(ip, "198.51.100.1");
+    }
+
+    #[test]
+    fn addresses_written_by_the_client_are_ignored() {
+        let ip =
+            client_ip(&proxies(), PROXY, "127.0.0.1, 198.51.100.1, 10.0.0.3");
+        assert_eq!(ip, "198.51.100.1");
+    }
+
+    #[test]
+    fn nothing_is_believed_without_trusted_proxies() {
+        let ip = client_ip(&TrustedProxies::default(), PROXY, "198.51.100.1");
+        assert_eq!(ip, PROXY);
+    }
+
+    #[test]
+    fn malformed_headers_leave_the_last_known_address() {
+        assert_eq!(client_ip(&proxies(), PROXY, "not an ip"), PROXY);
+        assert_eq!(client_ip(&proxies(), PROXY, ""), PROXY);
+    }
+}
@@ -1,8 +1,14 @@
+use anyhow::Context;
+use ipnet::IpNet;
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
 
 use crate::domain::{InvalidEmail, SubscriberEmail};
+use crate::email_client::{
+    EmailClient, EmailTransport, FileSinkTransport, PostmarkTransport, SmtpTls,
+    SmtpTransport,
+};
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
@@ -13,10 +19,42 @@ pub struct Settings {
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
-    pub base_url: String,
     pub sender_email: String,
-    pub api_token: Secret<String>,
     pub timeout_ms: u64,
+    pub transport: EmailTransportSettings,
+    pub webhook: WebhookSettings,
+}
+
+/// How the email provider authenticates its bounce and complaint webhooks.
+#[derive(serde::Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WebhookSettings {
+    /// Sent in the `X-Webhook-Secret` header.
+    SharedSecret { secret: Secret<String> },
+    BasicAuth {
+        username: String,
+        password: Secret<String>,
+    },
+}
+
+#[derive(serde::Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EmailTransportSettings {
+    Postmark {
+        base_url: String,
+        api_token: Secret<String>,
+    },
+    Smtp {
+        host: String,
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        port: u16,
+        tls: SmtpTls,
+        username: Option<String>,
+        password: Option<Secret<String>>,
+    },
+    FileSink {
+        directory: String,
+    },
 }
 
 impl EmailClientSettings {
@@ -27,6 +65,48 @@ impl EmailClientSettings {
     pub fn timeout(&self) -> std::time::Duration {
         std::time::Duration::from_millis(self.timeout_ms)
     }
+
+    pub fn client(&self) -> Result<EmailClient, anyhow::Error> {
+        let sender = self.sender().context("Sender email invalid")?;
+        let transport: Box<dyn EmailTransport> = match &self.transport {
+            EmailTransportSettings::Postmark {
+                base_url,
+                api_token,
+            } => Box::new(PostmarkTransport::new(
+                base_url,
+                api_token.clone(),
+                self.timeout(),
+            )),
+            EmailTransportSettings::Smtp {
+                host,
+                port,
+                tls,
+                username,
+                password,
+            } => {
+                let credentials = match (username, password) {
+                    (Some(username), Some(password)) => {
+                        Some((username.clone(), password.clone()))
+                    }
+                    (None, None) => None,
+                    _ => anyhow::bail!(
+                        "SMTP username and password must be set together."
+                    ),
+                };
+                Box::new(SmtpTransport::new(
+                    host,
+                    *port,
+                    *tls,
+                    credentials,
+                    self.timeout(),
+                )?)
+            }
+            EmailTransportSettings::FileSink { directory } => {
+                Box::new(FileSinkTransport::new(directory))
+            }
+        };
+        Ok(EmailClient::new(sender, transport))
+    }
 }
 
 #[derive(Clone, serde::Deserialize)]
@@ -37,6 +117,93 @@ pub struct ApplicationSettings {
     pub base_url: String,
     pub hmac_secret: Secret<String>,
     pub redis_uri: Secret<String>,
+    /// Runs the delivery worker and the issue scheduler next to the
+    /// server. Tests turn them off and drive the queue themselves.
+    pub background_workers: bool,
+    /// Largest subscriber import upload accepted, in bytes.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub import_body_limit_bytes: usize,
+    /// Proxies in front of the app, whose `X-Forwarded-For` header gives
+    /// the client IP used by the login throttle and the rate limits.
+    pub trusted_proxies: Vec<IpNet>,
+    pub login_throttle: LoginThrottleSettings,
+    pub rate_limits: RateLimitSettings,
+}
+
+/// Limits on failed logins, counted over a sliding window.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct LoginThrottleSettings {
+    pub window_seconds: i64,
+    /// Failures for one username before each attempt is slowed down.
+    pub slowdown_after: u64,
+    pub slowdown_delay_ms: u64,
+    /// Failures for one username before it is locked.
+    pub lockout_after: u64,
+    /// Failures from one client IP, across usernames, before it is locked.
+    pub ip_lockout_after: u64,
+    pub lockout_seconds: i64,
+}
+
+/// Per client IP limits on the public endpoints.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct RateLimitSettings {
+    pub backend: RateLimitBackend,
+    pub subscriptions: RateLimit,
+    pub subscription_confirm: RateLimit,
+    pub login: RateLimit,
+}
+
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitBackend {
+    /// Buckets live in each instance, so limits are per instance.
+    Memory,
+    /// Buckets are shared through `redis_uri`.
+    Redis,
+}
+
+/// A token bucket holding up to `burst` requests, refilled at
+/// `per_minute`.
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(try_from = "RateLimitValues")]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+/// `RateLimit` as written in the configuration, before it is checked.
+#[derive(serde::Deserialize)]
+struct RateLimitValues {
+    burst: u32,
+    per_minute: u32,
+}
+
+impl TryFrom<RateLimitValues> for RateLimit {
+    type Error = String;
+    fn try_from(values: RateLimitValues) -> Result<Self, Self::Error> {
+        // A bucket that never refills would divide by zero, and one that
+        // holds no token would reject every request.
+        if values.per_minute == 0 {
+            return Err("Rate limits must allow at least one request per \
+                minute."
+                .into());
+        }
+        if values.burst == 0 {
+            return Err("Rate limits must allow a burst of at least one \
+                request."
+                .into());
+        }
+        Ok(Self {
+            burst: values.burst,
+            per_minute: values.per_minute,
+        })
+    }
+}
+
+impl RateLimit {
+    pub fn per_second(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -158,3 +325,26 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimit;
+    use claims::{assert_err, assert_ok};
+
+    fn rate_limit(json: serde_json::Value) -> Result<RateLimit, String> {
+        serde_json::from_value(json).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn rate_limits_need_a_refill_and_a_burst() {
+        assert_ok!(rate_limit(
+            serde_json::json!({ "burst": 1, "per_minute": 1 })
+        ));
+        let e = rate_limit(serde_json::json!({ "burst": 5, "per_minute": 0 }))
+            .unwrap_err();
+        assert!(e.contains("per minute"));
+        assert_err!(rate_limit(
+            serde_json::json!({ "burst": 0, "per_minute": 5 })
+        ));
+    }
+}
@@ -1,9 +1,25 @@
+mod confirmation_queue_queries;
+mod email_event_queries;
 mod insert_subscriber;
+mod invitation_queries;
+mod issue_queries;
 mod newsletter_queries;
+mod password_reset_queries;
+mod subscriber_queries;
 mod token_queries;
+mod tracking_queries;
+mod two_factor_queries;
 mod user_queries;
 
+pub use confirmation_queue_queries::*;
+pub use email_event_queries::*;
 pub use insert_subscriber::*;
+pub use invitation_queries::*;
+pub use issue_queries::*;
 pub use newsletter_queries::*;
+pub use password_reset_queries::*;
+pub use subscriber_queries::*;
 pub use token_queries::*;
+pub use tracking_queries::*;
+pub use two_factor_queries::*;
 pub use user_queries::*;
@@ -0,0 +1,107 @@
+use crate::{
+    database::DatabaseConnection,
+    domain::SubscriptionToken,
+    models::ConfirmationEmailQueue,
+    schema::{confirmation_email_queue, subscription_tokens, subscriptions},
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+/// A queued confirmation email, with the address it goes to.
+pub struct PendingConfirmation {
+    pub task: ConfirmationEmailQueue,
+    pub email: String,
+}
+
+#[tracing::instrument(
+    name = "Enqueue confirmation email",
+    skip(connection, token)
+)]
+pub async fn enqueue_confirmation_email(
+    connection: &mut DatabaseConnection,
+    token: &SubscriptionToken,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(confirmation_email_queue::table)
+        .values(confirmation_email_queue::subscription_token.eq(token.as_ref()))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// Locks up to `max_size` due confirmation emails, skipping the ones other
+/// workers hold.
+///
+/// Only the queue rows are locked, so subscribers can confirm meanwhile.
+#[tracing::instrument(name = "Dequeue confirmation emails", skip(connection))]
+pub async fn dequeue_confirmation_emails(
+    connection: &mut DatabaseConnection,
+    max_size: i64,
+) -> Result<Vec<PendingConfirmation>, diesel::result::Error> {
+    let tasks = confirmation_email_queue::table
+        .filter(confirmation_email_queue::next_attempt_at.le(Utc::now()))
+        .order(confirmation_email_queue::next_attempt_at)
+        .limit(max_size)
+        .select(ConfirmationEmailQueue::as_select())
+        .for_update()
+        .skip_locked()
+        .load(connection)
+        .await?;
+    let tokens: Vec<&str> = tasks
+        .iter()
+        .map(|task| task.subscription_token.as_str())
+        .collect();
+    let mut emails: std::collections::HashMap<String, String> =
+        subscription_tokens::table
+            .inner_join(subscriptions::table)
+            .filter(subscription_tokens::subscription_token.eq_any(&tokens))
+            .select((
+                subscription_tokens::subscription_token,
+                subscriptions::email,
+            ))
+            .load::<(String, String)>(connection)
+            .await?
+            .into_iter()
+            .collect();
+    Ok(tasks
+        .into_iter()
+        .filter_map(|task| {
+            let email = emails.remove(&task.subscription_token)?;
+            Some(PendingConfirmation { task, email })
+        })
+        .collect())
+}
+
+#[tracing::instrument(
+    name = "Delete confirmation email",
+    skip(connection, token)
+)]
+pub async fn delete_confirmation_email(
+    connection: &mut DatabaseConnection,
+    token: &str,
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(confirmation_email_queue::table.find(token))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Reschedule confirmation email",
+    skip(connection, token)
+)]
+pub async fn reschedule_confirmation_email(
+    connection: &mut DatabaseConnection,
+    token: &str,
+    attempts: i16,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<(), diesel::result::Error> {
+    diesel::update(confirmation_email_queue::table.find(token))
+        .set((
+            confirmation_email_queue::attempts.eq(attempts),
+            confirmation_email_queue::next_attempt_at.eq(next_attempt_at),
+        ))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use crate::database::DatabaseConnection;
+use crate::domain::EmailEventKind;
+use crate::models::EmailEvents;
+use crate::schema::email_events;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use super::{
+    change_subscriber_status, get_subscriber_by_email, StatusChangeError,
+};
+
+/// Logs a bounce or complaint and moves the subscriber it is about out of
+/// the mailing list when the event calls for it.
+///
+/// Events for unknown addresses, or subscribers whose status cannot change
+/// anymore, are logged as they are.
+#[tracing::instrument(name = "Record email event", skip(connection))]
+pub async fn record_email_event(
+    connection: &mut DatabaseConnection,
+    email: &str,
+    kind: EmailEventKind,
+    description: &str,
+    occurred_at: DateTime<Utc>,
+) -> Result<EmailEvents, StatusChangeError> {
+    connection
+        .transaction::<_, StatusChangeError, _>(|conn| {
+            async move {
+                let subscriber = get_subscriber_by_email(conn, email).await?;
+                let mut status_changed = false;
+                if let (Some(subscriber), Some(next)) =
+                    (&subscriber, kind.subscription_status())
+                {
+                    match change_subscriber_status(conn, &subscriber.id, next)
+                        .await
+                    {
+                        Ok(()) => status_changed = subscriber.status != next,
+                        Err(StatusChangeError::InvalidTransition(e)) => {
+                            tracing::info!("Not applying the event: {}", e)
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                let event = EmailEvents::new(
+                    subscriber.map(|subscriber| subscriber.id),
+                    email,
+                    kind,
+                    description,
+                    status_changed,
+                    occurred_at,
+                );
+                diesel::insert_into(email_events::table)
+                    .values(&event)
+                    .execute(conn)
+                    .await?;
+                Ok(event)
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// The latest bounces and complaints, newest first.
+#[tracing::instrument(name = "Get email events", skip(connection))]
+pub async fn get_email_events(
+    connection: &mut DatabaseConnection,
+    limit: i64,
+) -> Result<Vec<EmailEvents>, diesel::result::Error> {
+    email_events::table
+        .order(email_events::received_at.desc())
+        .limit(limit)
+        .select(EmailEvents::as_select())
+        .load(connection)
+        .await
+}
+
+#[tracing::instrument(name = "Get subscriber email events", skip(connection))]
+pub async fn get_subscriber_email_events(
+    connection: &mut DatabaseConnection,
+    subscriber_id: &Uuid,
+) -> Result<Vec<EmailEvents>, diesel::result::Error> {
+    email_events::table
+        .filter(email_events::subscriber_id.eq(subscriber_id))
+        .order(email_events::occurred_at)
+        .select(EmailEvents::as_select())
+        .load(connection)
+        .await
+}
@@ -0,0 +1,116 @@
+use crate::database::DatabaseConnection;
+use crate::models::{UserInvitations, Users};
+use crate::schema::{user_invitations, users};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+
+#[tracing::instrument(
+    name = "Store user invitation",
+    skip(connection, invitation),
+    fields(email = %invitation.email)
+)]
+pub async fn store_invitation(
+    connection: &mut DatabaseConnection,
+    invitation: &UserInvitations,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(user_invitations::table)
+        .values(invitation)
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// Invitations that can still be accepted, newest first.
+#[tracing::instrument(name = "Get pending invitations", skip(connection))]
+pub async fn get_pending_invitations(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<UserInvitations>, diesel::result::Error> {
+    user_invitations::table
+        .filter(user_invitations::expires_at.gt(Utc::now()))
+        .order(user_invitations::created_at.desc())
+        .select(UserInvitations::as_select())
+        .load(connection)
+        .await
+}
+
+/// `None` when the invitation does not exist or has expired.
+#[tracing::instrument(name = "Get user invitation", skip(connection))]
+pub async fn get_invitation(
+    connection: &mut DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<UserInvitations>, diesel::result::Error> {
+    user_invitations::table
+        .find(token_hash)
+        .filter(user_invitations::expires_at.gt(Utc::now()))
+        .select(UserInvitations::as_select())
+        .first(connection)
+        .await
+        .optional()
+}
+
+#[tracing::instrument(name = "Revoke user invitation", skip(connection))]
+pub async fn revoke_invitation(
+    connection: &mut DatabaseConnection,
+    token_hash: &str,
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(user_invitations::table.find(token_hash))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// Uses up the invitation to create `user`, with the invited role and email.
+#[tracing::instrument(
+    name = "Accept user invitation",
+    skip(connection, user),
+    fields(username = %user.username)
+)]
+pub async fn accept_invitation(
+    connection: &mut DatabaseConnection,
+    token_hash: &str,
+    mut user: Users,
+) -> Result<(), AcceptInvitationError> {
+    connection
+        .transaction::<_, AcceptInvitationError, _>(|conn| {
+            async move {
+                let invitation: UserInvitations = diesel::delete(
+                    user_invitations::table
+                        .find(token_hash)
+                        .filter(user_invitations::expires_at.gt(Utc::now())),
+                )
+                .returning(UserInvitations::as_returning())
+                .get_result(conn)
+                .await
+                .optional()?
+                .ok_or(AcceptInvitationError::UnknownInvitation)?;
+                user.role = invitation.role;
+                diesel::insert_into(users::table)
+                    .values((&user, users::email.eq(&invitation.email)))
+                    .execute(conn)
+                    .await
+                    .map_err(|e| match e {
+                        Error::DatabaseError(
+                            DatabaseErrorKind::UniqueViolation,
+                            _,
+                        ) => AcceptInvitationError::AccountTaken,
+                        e => e.into(),
+                    })?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AcceptInvitationError {
+    #[error("This invitation is invalid or has expired.")]
+    UnknownInvitation,
+    #[error("This username or email is already taken.")]
+    AccountTaken,
+    #[error("A database error has ocurred when accepting an invitation")]
+    DatabaseError(#[from] diesel::result::Error),
+}
@@ -0,0 +1,561 @@
+use crate::database::DatabaseConnection;
+use crate::domain::{
+    InvalidIssueTransition, IssueContent, IssueSlug, NewsletterIssueStatus,
+    SubscriptionStatus,
+};
+use crate::models::{
+    IssueDeliveryDeadLetters, IssueDeliveryQueue, NewsletterIssues,
+};
+use crate::schema::{
+    issue_delivery_dead_letters, issue_delivery_queue, newsletter_issues,
+    subscriptions, users,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::sql_types;
+use diesel::SelectableHelper;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Stores a new draft written by `author_id`.
+#[tracing::instrument(
+    name = "Insert newsletter issue",
+    skip(connection, title, content)
+)]
+pub async fn insert_newsletter_issue(
+    connection: &mut DatabaseConnection,
+    author_id: Uuid,
+    title: &str,
+    content: &IssueContent,
+    tracking_enabled: bool,
+) -> Result<Uuid, diesel::result::Error> {
+    let issue =
+        NewsletterIssues::new(author_id, title, content, tracking_enabled);
+    diesel::insert_into(newsletter_issues::table)
+        .values(&issue)
+        .returning(newsletter_issues::newsletter_issue_id)
+        .get_result(connection)
+        .await
+}
+
+/// Locks the issue until the surrounding transaction ends.
+async fn lock_issue_status(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+) -> Result<NewsletterIssueStatus, IssueChangeError> {
+    newsletter_issues::table
+        .find(issue_id)
+        .select(newsletter_issues::status)
+        .for_update()
+        .first(connection)
+        .await
+        .optional()?
+        .ok_or(IssueChangeError::UnknownIssue)
+}
+
+#[tracing::instrument(
+    name = "Update newsletter issue",
+    skip(connection, title, content)
+)]
+pub async fn update_issue_content(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    title: &str,
+    content: &IssueContent,
+    tracking_enabled: bool,
+) -> Result<(), IssueChangeError> {
+    connection
+        .transaction::<_, IssueChangeError, _>(|conn| {
+            async move {
+                let status = lock_issue_status(conn, issue_id).await?;
+                if !status.is_editable() {
+                    return Err(IssueChangeError::NotEditable(status));
+                }
+                diesel::update(newsletter_issues::table.find(issue_id))
+                    .set((
+                        newsletter_issues::title.eq(title),
+                        newsletter_issues::text_content.eq(content.text()),
+                        newsletter_issues::html_content.eq(content.html()),
+                        newsletter_issues::markdown_content
+                            .eq(content.markdown()),
+                        newsletter_issues::tracking_enabled
+                            .eq(tracking_enabled),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Schedules the issue for `at`, or turns it back into a draft when `at` is
+/// `None`.
+#[tracing::instrument(name = "Schedule newsletter issue", skip(connection))]
+pub async fn schedule_issue(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    at: Option<DateTime<Utc>>,
+) -> Result<(), IssueChangeError> {
+    connection
+        .transaction::<_, IssueChangeError, _>(|conn| {
+            async move {
+                let next = match at {
+                    Some(_) => NewsletterIssueStatus::Scheduled,
+                    None => NewsletterIssueStatus::Draft,
+                };
+                let next = lock_issue_status(conn, issue_id)
+                    .await?
+                    .transition_to(next)?;
+                diesel::update(newsletter_issues::table.find(issue_id))
+                    .set((
+                        newsletter_issues::status.eq(next),
+                        newsletter_issues::scheduled_for.eq(at),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Queues the issue for every confirmed subscriber and gives it the slug it
+/// is archived under.
+///
+/// Returns how many deliveries were queued.
+#[tracing::instrument(name = "Publish newsletter issue", skip(connection))]
+pub async fn publish_issue(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+) -> Result<usize, IssueChangeError> {
+    connection
+        .transaction::<_, IssueChangeError, _>(|conn| {
+            async move {
+                lock_issue_status(conn, issue_id)
+                    .await?
+                    .transition_to(NewsletterIssueStatus::Sending)?;
+                assign_slug(conn, issue_id).await?;
+                let recipients = enqueue_delivery_tasks(conn, issue_id).await?;
+                // Nobody to wait for when there are no recipients.
+                let status = if recipients == 0 {
+                    NewsletterIssueStatus::Sent
+                } else {
+                    NewsletterIssueStatus::Sending
+                };
+                diesel::update(newsletter_issues::table.find(issue_id))
+                    .set((
+                        newsletter_issues::status.eq(status),
+                        newsletter_issues::published_at.eq(Utc::now()),
+                        newsletter_issues::recipients_count
+                            .eq(recipients as i32),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(recipients)
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Gives the issue the slug of its title, or the disambiguated one when
+/// another issue already holds it.
+///
+/// The unique constraint decides, so publishing two issues with the same
+/// title at once cannot hand them the same slug.
+async fn assign_slug(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    let title: String = newsletter_issues::table
+        .find(issue_id)
+        .select(newsletter_issues::title)
+        .first(connection)
+        .await?;
+    let slug = IssueSlug::from_title(&title);
+    match set_slug(connection, issue_id, &slug).await {
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            _,
+        )) => {
+            set_slug(connection, issue_id, &slug.disambiguate(issue_id)).await
+        }
+        outcome => outcome,
+    }
+}
+
+/// Runs in a savepoint, so a taken slug leaves the publication usable.
+async fn set_slug(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    slug: &IssueSlug,
+) -> Result<(), diesel::result::Error> {
+    connection
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                diesel::update(newsletter_issues::table.find(issue_id))
+                    .set(newsletter_issues::slug.eq(slug.as_ref()))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Locks the scheduled issues that are due, skipping the ones other
+/// schedulers hold.
+#[tracing::instrument(name = "Get due scheduled issues", skip(connection))]
+pub async fn get_due_scheduled_issues(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<Uuid>, diesel::result::Error> {
+    newsletter_issues::table
+        .filter(newsletter_issues::status.eq(NewsletterIssueStatus::Scheduled))
+        .filter(newsletter_issues::scheduled_for.le(Utc::now()))
+        .order(newsletter_issues::scheduled_for)
+        .select(newsletter_issues::newsletter_issue_id)
+        .for_update()
+        .skip_locked()
+        .load(connection)
+        .await
+}
+
+#[tracing::instrument(name = "Record issue deliveries", skip(connection))]
+pub async fn record_deliveries(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    delivered: i32,
+) -> Result<(), diesel::result::Error> {
+    diesel::update(newsletter_issues::table.find(issue_id))
+        .set(
+            newsletter_issues::delivered_count
+                .eq(newsletter_issues::delivered_count + delivered),
+        )
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// Marks the issue as sent once nothing is left in its delivery queue.
+#[tracing::instrument(name = "Finish newsletter issue", skip(connection))]
+pub async fn finish_issue_if_done(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    let remaining_tasks = issue_delivery_queue::table
+        .filter(issue_delivery_queue::newsletter_issue_id.eq(issue_id));
+    diesel::update(
+        newsletter_issues::table
+            .find(issue_id)
+            .filter(
+                newsletter_issues::status.eq(NewsletterIssueStatus::Sending),
+            )
+            .filter(diesel::dsl::not(diesel::dsl::exists(remaining_tasks))),
+    )
+    .set(newsletter_issues::status.eq(NewsletterIssueStatus::Sent))
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum IssueChangeError {
+    #[error("There is no such newsletter issue.")]
+    UnknownIssue,
+    #[error("The issue is {0} and can no longer be edited.")]
+    NotEditable(NewsletterIssueStatus),
+    #[error(transparent)]
+    InvalidTransition(#[from] InvalidIssueTransition),
+    #[error("A database error has ocurred when changing a newsletter issue")]
+    DatabaseError(#[from] diesel::result::Error),
+}
+
+#[tracing::instrument(name = "Enqueue delivery tasks", skip(connection))]
+pub async fn enqueue_delivery_tasks(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+) -> Result<usize, diesel::result::Error> {
+    let confirmed_emails = subscriptions::table
+        .filter(subscriptions::status.eq(SubscriptionStatus::Confirmed))
+        .select((issue_id.into_sql::<sql_types::Uuid>(), subscriptions::email));
+    diesel::insert_into(issue_delivery_queue::table)
+        .values(confirmed_emails)
+        .into_columns((
+            issue_delivery_queue::newsletter_issue_id,
+            issue_delivery_queue::subscriber_email,
+        ))
+        .execute(connection)
+        .await
+}
+
+/// Locks the next task that is due, skipping the ones other workers hold.
+///
+/// The lock lasts until the surrounding transaction ends.
+#[tracing::instrument(name = "Dequeue delivery task", skip(connection))]
+pub async fn dequeue_task(
+    connection: &mut DatabaseConnection,
+) -> Result<Option<IssueDeliveryQueue>, diesel::result::Error> {
+    issue_delivery_queue::table
+        .filter(issue_delivery_queue::next_attempt_at.le(Utc::now()))
+        .order(issue_delivery_queue::next_attempt_at)
+        .select(IssueDeliveryQueue::as_select())
+        .for_update()
+        .skip_locked()
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Locks up to `max_size` due tasks of the issue with the oldest due task,
+/// so they can be delivered with a single batch call.
+#[tracing::instrument(name = "Dequeue delivery batch", skip(connection))]
+pub async fn dequeue_batch(
+    connection: &mut DatabaseConnection,
+    max_size: i64,
+) -> Result<Vec<IssueDeliveryQueue>, diesel::result::Error> {
+    let issue_id = match dequeue_task(connection).await? {
+        Some(task) => task.newsletter_issue_id,
+        None => return Ok(Vec::new()),
+    };
+    issue_delivery_queue::table
+        .filter(issue_delivery_queue::newsletter_issue_id.eq(issue_id))
+        .filter(issue_delivery_queue::next_attempt_at.le(Utc::now()))
+        .order(issue_delivery_queue::next_attempt_at)
+        .limit(max_size)
+        .select(IssueDeliveryQueue::as_select())
+        .for_update()
+        .skip_locked()
+        .load(connection)
+        .await
+}
+
+#[tracing::instrument(name = "Delete delivery task", skip(connection, email))]
+pub async fn delete_task(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    email: &str,
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(issue_delivery_queue::table.find((issue_id, email)))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Reschedule delivery task",
+    skip(connection, email, error)
+)]
+pub async fn reschedule_task(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    email: &str,
+    attempts: i16,
+    error: &str,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<(), diesel::result::Error> {
+    diesel::update(issue_delivery_queue::table.find((issue_id, email)))
+        .set((
+            issue_delivery_queue::attempts.eq(attempts),
+            issue_delivery_queue::last_error.eq(error),
+            issue_delivery_queue::next_attempt_at.eq(next_attempt_at),
+        ))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Move delivery task to dead letters",
+    skip(connection, email, error)
+)]
+pub async fn dead_letter_task(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    email: &str,
+    attempts: i16,
+    error: &str,
+) -> Result<(), diesel::result::Error> {
+    let dead_letter = IssueDeliveryDeadLetters {
+        newsletter_issue_id: issue_id,
+        subscriber_email: email.to_string(),
+        attempts,
+        last_error: error.to_string(),
+        failed_at: Utc::now(),
+    };
+    diesel::insert_into(issue_delivery_dead_letters::table)
+        .values(&dead_letter)
+        .on_conflict((
+            issue_delivery_dead_letters::newsletter_issue_id,
+            issue_delivery_dead_letters::subscriber_email,
+        ))
+        .do_update()
+        .set((
+            issue_delivery_dead_letters::attempts.eq(attempts),
+            issue_delivery_dead_letters::last_error.eq(error),
+            issue_delivery_dead_letters::failed_at.eq(dead_letter.failed_at),
+        ))
+        .execute(connection)
+        .await?;
+    delete_task(connection, issue_id, email).await
+}
+
+pub struct FailedDelivery {
+    pub issue_title: String,
+    pub dead_letter: IssueDeliveryDeadLetters,
+}
+
+#[tracing::instrument(name = "Get failed deliveries", skip(connection))]
+pub async fn get_dead_letters(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<FailedDelivery>, diesel::result::Error> {
+    let rows: Vec<(String, IssueDeliveryDeadLetters)> =
+        issue_delivery_dead_letters::table
+            .inner_join(newsletter_issues::table)
+            .order(issue_delivery_dead_letters::failed_at.desc())
+            .select((
+                newsletter_issues::title,
+                IssueDeliveryDeadLetters::as_select(),
+            ))
+            .load(connection)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(issue_title, dead_letter)| FailedDelivery {
+            issue_title,
+            dead_letter,
+        })
+        .collect())
+}
+
+/// Moves a dead letter back into the delivery queue with a fresh retry budget.
+///
+/// Returns `false` when there was no such dead letter.
+#[tracing::instrument(
+    name = "Requeue failed delivery",
+    skip(connection, email)
+)]
+pub async fn requeue_dead_letter(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    email: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted = diesel::delete(
+        issue_delivery_dead_letters::table.find((issue_id, email)),
+    )
+    .execute(connection)
+    .await?;
+    if deleted == 0 {
+        return Ok(false);
+    }
+    diesel::insert_into(issue_delivery_queue::table)
+        .values((
+            issue_delivery_queue::newsletter_issue_id.eq(issue_id),
+            issue_delivery_queue::subscriber_email.eq(email),
+        ))
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .await?;
+    Ok(true)
+}
+
+#[tracing::instrument(name = "Get newsletter issue", skip(connection))]
+pub async fn get_issue(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+) -> Result<NewsletterIssues, diesel::result::Error> {
+    newsletter_issues::table
+        .find(issue_id)
+        .select(NewsletterIssues::as_select())
+        .first(connection)
+        .await
+}
+
+/// The latest published issues, newest first.
+#[tracing::instrument(name = "Get published issues", skip(connection))]
+pub async fn get_published_issues(
+    connection: &mut DatabaseConnection,
+    limit: Option<i64>,
+) -> Result<Vec<NewsletterIssues>, diesel::result::Error> {
+    let mut query = newsletter_issues::table
+        .filter(newsletter_issues::published_at.is_not_null())
+        .order(newsletter_issues::published_at.desc())
+        .select(NewsletterIssues::as_select())
+        .into_boxed();
+    if let Some(limit) = limit {
+        query = query.limit(limit);
+    }
+    query.load(connection).await
+}
+
+#[tracing::instrument(name = "Get published issue", skip(connection))]
+pub async fn get_published_issue(
+    connection: &mut DatabaseConnection,
+    slug: &str,
+) -> Result<NewsletterIssues, diesel::result::Error> {
+    newsletter_issues::table
+        .filter(newsletter_issues::slug.eq(slug))
+        .filter(newsletter_issues::published_at.is_not_null())
+        .select(NewsletterIssues::as_select())
+        .first(connection)
+        .await
+}
+
+/// An issue as listed in the history page.
+pub struct IssueSummary {
+    pub issue: NewsletterIssues,
+    pub author: Option<String>,
+    pub pending: i64,
+    pub failed: i64,
+}
+
+#[tracing::instrument(name = "Get newsletter issue history", skip(connection))]
+pub async fn get_issue_history(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<IssueSummary>, diesel::result::Error> {
+    let issues: Vec<(NewsletterIssues, Option<String>)> =
+        newsletter_issues::table
+            .left_join(users::table)
+            .order(newsletter_issues::created_at.desc())
+            .select((NewsletterIssues::as_select(), users::username.nullable()))
+            .load(connection)
+            .await?;
+    let pending: HashMap<Uuid, i64> = issue_delivery_queue::table
+        .group_by(issue_delivery_queue::newsletter_issue_id)
+        .select((
+            issue_delivery_queue::newsletter_issue_id,
+            diesel::dsl::count_star(),
+        ))
+        .load::<(Uuid, i64)>(connection)
+        .await?
+        .into_iter()
+        .collect();
+    let failed: HashMap<Uuid, i64> = issue_delivery_dead_letters::table
+        .group_by(issue_delivery_dead_letters::newsletter_issue_id)
+        .select((
+            issue_delivery_dead_letters::newsletter_issue_id,
+            diesel::dsl::count_star(),
+        ))
+        .load::<(Uuid, i64)>(connection)
+        .await?
+        .into_iter()
+        .collect();
+    Ok(issues
+        .into_iter()
+        .map(|(issue, author)| {
+            let id = issue.newsletter_issue_id;
+            IssueSummary {
+                issue,
+                author,
+                pending: pending.get(&id).copied().unwrap_or(0),
+                failed: failed.get(&id).copied().unwrap_or(0),
+            }
+        })
+        .collect())
+}
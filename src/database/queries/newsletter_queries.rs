@@ -1,5 +1,5 @@
 use crate::database::DatabaseConnection;
-use crate::domain::{InvalidEmail, SubscriberEmail};
+use crate::domain::{InvalidEmail, SubscriberEmail, SubscriptionStatus};
 use crate::schema::subscriptions::dsl::*;
 use crate::schema::users::dsl::*;
 use diesel::prelude::*;
@@ -16,8 +16,8 @@ pub async fn get_confirmed_subscribers(
 ) -> Result<Vec<Result<ConfirmedSubscriber, InvalidEmail>>, diesel::result::Error>
 {
     let emails: Vec<String> = subscriptions
-        .filter(status.eq("confirmed"))
-        .select(email)
+        .filter(status.eq(SubscriptionStatus::Confirmed))
+        .select(crate::schema::subscriptions::email)
         .load(connection)
         .await?;
     let confirmed_subscribers = emails
@@ -44,6 +44,7 @@ pub async fn get_stored_credentials(
 ) -> Result<(uuid::Uuid, Secret<String>), ValidateUserError> {
     let row: Option<(uuid::Uuid, String)> = users
         .filter(username.eq(&uname))
+        .filter(disabled_at.is_null())
         .select((user_id, password_hash))
         .first(connection)
         .await
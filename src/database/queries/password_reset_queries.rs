@@ -0,0 +1,103 @@
+use crate::database::DatabaseConnection;
+use crate::models::PasswordResetTokens;
+use crate::schema::{password_reset_tokens, users};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+/// The enabled user whose account uses `email`, if any.
+#[tracing::instrument(name = "Get user by email", skip(connection, email))]
+pub async fn get_user_id_by_email(
+    connection: &mut DatabaseConnection,
+    email: &str,
+) -> Result<Option<Uuid>, diesel::result::Error> {
+    users::table
+        .filter(users::email.eq(email))
+        .filter(users::disabled_at.is_null())
+        .select(users::user_id)
+        .first(connection)
+        .await
+        .optional()
+}
+
+#[tracing::instrument(
+    name = "Store password reset token",
+    skip(connection, token),
+    fields(user_id = %token.user_id)
+)]
+pub async fn store_password_reset_token(
+    connection: &mut DatabaseConnection,
+    token: &PasswordResetTokens,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(password_reset_tokens::table)
+        .values(token)
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// The user a still valid reset token was issued to.
+#[tracing::instrument(name = "Get password reset user", skip(connection))]
+pub async fn get_password_reset_user(
+    connection: &mut DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<Uuid>, diesel::result::Error> {
+    password_reset_tokens::table
+        .find(token_hash)
+        .filter(password_reset_tokens::expires_at.gt(Utc::now()))
+        .select(password_reset_tokens::user_id)
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Uses up a still valid reset token, along with every other token of the
+/// same user, and sets the user's new password, returning the user it was
+/// issued to.
+///
+/// Deleting the token locks it, so of concurrent resets with the same link
+/// only one gets to change the password, and the token is only spent if the
+/// password is changed too.
+#[tracing::instrument(
+    name = "Reset password with token",
+    skip(connection, password_hash)
+)]
+pub async fn reset_password_with_token(
+    connection: &mut DatabaseConnection,
+    token_hash: &str,
+    password_hash: Secret<String>,
+) -> Result<Option<Uuid>, diesel::result::Error> {
+    connection
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                let Some(user_id) = diesel::delete(
+                    password_reset_tokens::table.find(token_hash).filter(
+                        password_reset_tokens::expires_at.gt(Utc::now()),
+                    ),
+                )
+                .returning(password_reset_tokens::user_id)
+                .get_result::<Uuid>(conn)
+                .await
+                .optional()?
+                else {
+                    return Ok(None);
+                };
+                diesel::delete(
+                    password_reset_tokens::table
+                        .filter(password_reset_tokens::user_id.eq(user_id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::update(users::table.find(user_id))
+                    .set(users::password_hash.eq(password_hash.expose_secret()))
+                    .execute(conn)
+                    .await?;
+                Ok(Some(user_id))
+            }
+            .scope_boxed()
+        })
+        .await
+}
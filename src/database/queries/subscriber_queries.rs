@@ -0,0 +1,279 @@
+use crate::database::DatabaseConnection;
+use crate::domain::{InvalidStatusTransition, SubscriptionStatus};
+use crate::models::{SubscriptionStatusChanges, Subscriptions};
+use crate::schema::{
+    subscription_status_changes, subscription_tokens, subscriptions,
+};
+use chrono::{DateTime, Utc};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Maps the given emails to subscriber ids and names, leaving out anyone who
+/// is no longer confirmed.
+#[tracing::instrument(
+    name = "Get confirmed recipients",
+    skip(connection, emails)
+)]
+pub async fn get_confirmed_recipients(
+    connection: &mut DatabaseConnection,
+    emails: &[&str],
+) -> Result<HashMap<String, (Uuid, String)>, diesel::result::Error> {
+    let rows: Vec<(String, Uuid, String)> = subscriptions::table
+        .filter(subscriptions::email.eq_any(emails))
+        .filter(subscriptions::status.eq(SubscriptionStatus::Confirmed))
+        .select((subscriptions::email, subscriptions::id, subscriptions::name))
+        .load(connection)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(email, id, name)| (email, (id, name)))
+        .collect())
+}
+
+/// Returns which of the given emails already belong to a subscriber.
+#[tracing::instrument(name = "Get existing emails", skip(connection, emails))]
+pub async fn get_existing_emails(
+    connection: &mut DatabaseConnection,
+    emails: &[&str],
+) -> Result<HashSet<String>, diesel::result::Error> {
+    let rows: Vec<String> = subscriptions::table
+        .filter(subscriptions::email.eq_any(emails))
+        .select(subscriptions::email)
+        .load(connection)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Locks the subscriber row until the surrounding transaction ends.
+#[tracing::instrument(name = "Get subscriber by email", skip(connection))]
+pub async fn get_subscriber_by_email(
+    connection: &mut DatabaseConnection,
+    email: &str,
+) -> Result<Option<Subscriptions>, diesel::result::Error> {
+    subscriptions::table
+        .filter(subscriptions::email.eq(email))
+        .select(Subscriptions::as_select())
+        .for_update()
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Moves a subscriber to `next`, provided the domain allows the transition,
+/// and records the change in the subscriber's status history.
+#[tracing::instrument(name = "Change subscriber status", skip(connection))]
+pub async fn change_subscriber_status(
+    connection: &mut DatabaseConnection,
+    subscriber_id: &Uuid,
+    next: SubscriptionStatus,
+) -> Result<(), StatusChangeError> {
+    connection
+        .transaction::<_, StatusChangeError, _>(|conn| {
+            async move {
+                let current: SubscriptionStatus = subscriptions::table
+                    .find(subscriber_id)
+                    .select(subscriptions::status)
+                    .first(conn)
+                    .await
+                    .optional()?
+                    .ok_or(StatusChangeError::UnknownSubscriber)?;
+                let next = current.transition_to(next)?;
+                if next == current {
+                    return Ok(());
+                }
+                // Guard on the status we validated against, in case it
+                // changed meanwhile.
+                let updated = diesel::update(
+                    subscriptions::table
+                        .find(subscriber_id)
+                        .filter(subscriptions::status.eq(current)),
+                )
+                .set(subscriptions::status.eq(next))
+                .execute(conn)
+                .await?;
+                if updated == 0 {
+                    return Err(StatusChangeError::ConcurrentChange);
+                }
+                diesel::insert_into(subscription_status_changes::table)
+                    .values(SubscriptionStatusChanges::new(
+                        *subscriber_id,
+                        current,
+                        next,
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StatusChangeError {
+    #[error("There is no subscriber with this id.")]
+    UnknownSubscriber,
+    #[error(transparent)]
+    InvalidTransition(#[from] InvalidStatusTransition),
+    #[error("The subscriber status was changed concurrently.")]
+    ConcurrentChange,
+    #[error("A database error has ocurred when changing a subscriber status")]
+    DatabaseError(#[from] diesel::result::Error),
+}
+
+/// Narrows down the subscribers shown in the admin area.
+#[derive(Debug, Default)]
+pub struct SubscriberFilter {
+    /// Case insensitive match against the email or the name.
+    pub search: Option<String>,
+    pub status: Option<SubscriptionStatus>,
+}
+
+impl SubscriberFilter {
+    fn apply<'a>(&'a self) -> subscriptions::BoxedQuery<'a, Pg> {
+        let mut query = subscriptions::table.into_boxed();
+        if let Some(search) = &self.search {
+            let pattern = format!("%{}%", escape_like(search));
+            query = query.filter(
+                subscriptions::email
+                    .ilike(pattern.clone())
+                    .or(subscriptions::name.ilike(pattern)),
+            );
+        }
+        if let Some(status) = self.status {
+            query = query.filter(subscriptions::status.eq(status));
+        }
+        query
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[tracing::instrument(name = "Search subscribers", skip(connection))]
+pub async fn search_subscribers(
+    connection: &mut DatabaseConnection,
+    filter: &SubscriberFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Subscriptions>, diesel::result::Error> {
+    filter
+        .apply()
+        .order((subscriptions::subscribed_at.desc(), subscriptions::id))
+        .limit(limit)
+        .offset(offset)
+        .select(Subscriptions::as_select())
+        .load(connection)
+        .await
+}
+
+/// Where a scan of the subscribers, in the order of `search_subscribers`,
+/// left off.
+#[derive(Debug, Clone, Copy)]
+pub struct SubscriberCursor {
+    subscribed_at: DateTime<Utc>,
+    id: Uuid,
+}
+
+impl From<&Subscriptions> for SubscriberCursor {
+    fn from(subscriber: &Subscriptions) -> Self {
+        Self {
+            subscribed_at: subscriber.subscribed_at,
+            id: subscriber.id,
+        }
+    }
+}
+
+/// The next `limit` subscribers after `after`. Unlike an offset, the cursor
+/// does not skip or repeat rows when subscribers come and go mid-scan.
+#[tracing::instrument(name = "Search subscribers after", skip(connection))]
+pub async fn search_subscribers_after(
+    connection: &mut DatabaseConnection,
+    filter: &SubscriberFilter,
+    after: Option<SubscriberCursor>,
+    limit: i64,
+) -> Result<Vec<Subscriptions>, diesel::result::Error> {
+    let mut query = filter.apply();
+    if let Some(after) = after {
+        query = query.filter(
+            subscriptions::subscribed_at.lt(after.subscribed_at).or(
+                subscriptions::subscribed_at
+                    .eq(after.subscribed_at)
+                    .and(subscriptions::id.gt(after.id)),
+            ),
+        );
+    }
+    query
+        .order((subscriptions::subscribed_at.desc(), subscriptions::id))
+        .limit(limit)
+        .select(Subscriptions::as_select())
+        .load(connection)
+        .await
+}
+
+#[tracing::instrument(name = "Count subscribers", skip(connection))]
+pub async fn count_subscribers(
+    connection: &mut DatabaseConnection,
+    filter: &SubscriberFilter,
+) -> Result<i64, diesel::result::Error> {
+    filter.apply().count().get_result(connection).await
+}
+
+#[tracing::instrument(name = "Get subscriber", skip(connection))]
+pub async fn get_subscriber(
+    connection: &mut DatabaseConnection,
+    subscriber_id: &Uuid,
+) -> Result<Option<Subscriptions>, diesel::result::Error> {
+    subscriptions::table
+        .find(subscriber_id)
+        .select(Subscriptions::as_select())
+        .first(connection)
+        .await
+        .optional()
+}
+
+#[tracing::instrument(name = "Get subscriber status history", skip(connection))]
+pub async fn get_status_history(
+    connection: &mut DatabaseConnection,
+    subscriber_id: &Uuid,
+) -> Result<Vec<SubscriptionStatusChanges>, diesel::result::Error> {
+    subscription_status_changes::table
+        .filter(subscription_status_changes::subscriber_id.eq(subscriber_id))
+        .order(subscription_status_changes::changed_at)
+        .select(SubscriptionStatusChanges::as_select())
+        .load(connection)
+        .await
+}
+
+/// Returns `false` when there was no such subscriber.
+#[tracing::instrument(name = "Delete subscriber", skip(connection))]
+pub async fn delete_subscriber(
+    connection: &mut DatabaseConnection,
+    subscriber_id: &Uuid,
+) -> Result<bool, diesel::result::Error> {
+    connection
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                diesel::delete(subscription_tokens::table.filter(
+                    subscription_tokens::subscriber_id.eq(subscriber_id),
+                ))
+                .execute(conn)
+                .await?;
+                let deleted =
+                    diesel::delete(subscriptions::table.find(subscriber_id))
+                        .execute(conn)
+                        .await?;
+                Ok(deleted > 0)
+            }
+            .scope_boxed()
+        })
+        .await
+}
@@ -1,15 +1,12 @@
 use crate::{
-    database::DatabaseConnection,
-    domain::SubscriptionToken,
-    models::SubscriptionTokens,
-    schema::{self, subscriptions},
+    database::DatabaseConnection, domain::SubscriptionToken,
+    models::SubscriptionTokens, schema,
 };
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use diesel::prelude::*;
 use diesel::SelectableHelper;
 use diesel_async::RunQueryDsl;
 use schema::subscription_tokens::dsl::*;
-use schema::subscriptions::dsl::*;
 use uuid::Uuid;
 
 #[tracing::instrument(
@@ -56,16 +53,29 @@ pub async fn get_subscriber_id_for_token(
     Ok(value)
 }
 
-#[tracing::instrument(name = "Set subscriber to confirmed", skip(connection))]
-pub async fn confirm_subscriber(
+#[tracing::instrument(name = "Getting the latest token age", skip(connection))]
+pub async fn get_latest_token_generated_at(
     connection: &mut DatabaseConnection,
     sub_id: &Uuid,
-) -> Result<(), diesel::result::Error> {
-    diesel::update(subscriptions::table.find(sub_id))
-        .set(status.eq("confirmed"))
+) -> Result<Option<DateTime<Utc>>, diesel::result::Error> {
+    subscription_tokens
+        .filter(subscriber_id.eq(sub_id))
+        .select(diesel::dsl::max(generated_at))
+        .first(connection)
+        .await
+}
+
+#[tracing::instrument(
+    name = "Invalidating subscriber tokens",
+    skip(connection)
+)]
+pub async fn delete_tokens_for_subscriber(
+    connection: &mut DatabaseConnection,
+    sub_id: &Uuid,
+) -> Result<usize, diesel::result::Error> {
+    diesel::delete(subscription_tokens.filter(subscriber_id.eq(sub_id)))
         .execute(connection)
-        .await?;
-    Ok(())
+        .await
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -0,0 +1,142 @@
+use crate::database::DatabaseConnection;
+use crate::models::{IssueClicks, IssueLinks, IssueOpens};
+use crate::schema::{
+    issue_clicks, issue_links, issue_opens, newsletter_issues,
+};
+use diesel::dsl::count_distinct;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Makes sure the issue has a tracked link for every URL, returning the id
+/// of each.
+#[tracing::instrument(name = "Store issue links", skip(connection, urls))]
+pub async fn store_issue_links(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    urls: &[&str],
+) -> Result<HashMap<String, Uuid>, diesel::result::Error> {
+    let links: Vec<IssueLinks> = urls
+        .iter()
+        .map(|url| IssueLinks::new(issue_id, url))
+        .collect();
+    diesel::insert_into(issue_links::table)
+        .values(&links)
+        .on_conflict((issue_links::newsletter_issue_id, issue_links::url))
+        .do_nothing()
+        .execute(connection)
+        .await?;
+    let rows: Vec<(String, Uuid)> = issue_links::table
+        .filter(issue_links::newsletter_issue_id.eq(issue_id))
+        .filter(issue_links::url.eq_any(urls))
+        .select((issue_links::url, issue_links::id))
+        .load(connection)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+#[tracing::instrument(name = "Get issue link", skip(connection))]
+pub async fn get_issue_link(
+    connection: &mut DatabaseConnection,
+    link_id: Uuid,
+) -> Result<IssueLinks, diesel::result::Error> {
+    issue_links::table
+        .find(link_id)
+        .select(IssueLinks::as_select())
+        .first(connection)
+        .await
+}
+
+#[tracing::instrument(name = "Record issue open", skip(connection))]
+pub async fn record_open(
+    connection: &mut DatabaseConnection,
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(issue_opens::table)
+        .values(IssueOpens::new(issue_id, subscriber_id))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Record issue click", skip(connection))]
+pub async fn record_click(
+    connection: &mut DatabaseConnection,
+    link_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(issue_clicks::table)
+        .values(IssueClicks::new(link_id, subscriber_id))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// How many subscribers opened an issue or clicked any of its links.
+pub struct IssueEngagement {
+    pub issue_id: Uuid,
+    pub title: String,
+    pub tracking_enabled: bool,
+    pub delivered: i32,
+    pub opened: i64,
+    pub clicked: i64,
+}
+
+/// Engagement for the latest published issues, newest first.
+#[tracing::instrument(name = "Get issue engagement", skip(connection))]
+pub async fn get_issue_engagement(
+    connection: &mut DatabaseConnection,
+    limit: i64,
+) -> Result<Vec<IssueEngagement>, diesel::result::Error> {
+    let issues: Vec<(Uuid, String, bool, i32)> = newsletter_issues::table
+        .filter(newsletter_issues::published_at.is_not_null())
+        .order(newsletter_issues::published_at.desc())
+        .limit(limit)
+        .select((
+            newsletter_issues::newsletter_issue_id,
+            newsletter_issues::title,
+            newsletter_issues::tracking_enabled,
+            newsletter_issues::delivered_count,
+        ))
+        .load(connection)
+        .await?;
+    let issue_ids: Vec<Uuid> = issues.iter().map(|issue| issue.0).collect();
+    let opened: HashMap<Uuid, i64> = issue_opens::table
+        .filter(issue_opens::newsletter_issue_id.eq_any(&issue_ids))
+        .group_by(issue_opens::newsletter_issue_id)
+        .select((
+            issue_opens::newsletter_issue_id,
+            count_distinct(issue_opens::subscriber_id),
+        ))
+        .load::<(Uuid, i64)>(connection)
+        .await?
+        .into_iter()
+        .collect();
+    let clicked: HashMap<Uuid, i64> = issue_clicks::table
+        .inner_join(issue_links::table)
+        .filter(issue_links::newsletter_issue_id.eq_any(&issue_ids))
+        .group_by(issue_links::newsletter_issue_id)
+        .select((
+            issue_links::newsletter_issue_id,
+            count_distinct(issue_clicks::subscriber_id),
+        ))
+        .load::<(Uuid, i64)>(connection)
+        .await?
+        .into_iter()
+        .collect();
+    Ok(issues
+        .into_iter()
+        .map(
+            |(issue_id, title, tracking_enabled, delivered)| IssueEngagement {
+                issue_id,
+                title,
+                tracking_enabled,
+                delivered,
+                opened: opened.get(&issue_id).copied().unwrap_or_default(),
+                clicked: clicked.get(&issue_id).copied().unwrap_or_default(),
+            },
+        )
+        .collect())
+}
@@ -0,0 +1,145 @@
+use crate::database::DatabaseConnection;
+use crate::models::RecoveryCodes;
+use crate::schema::{recovery_codes, users};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// The encrypted TOTP secret of the user, when two-factor authentication is
+/// enabled.
+#[tracing::instrument(name = "Get TOTP secret", skip(connection))]
+pub async fn get_totp_secret(
+    connection: &mut DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Option<String>, diesel::result::Error> {
+    users::table
+        .find(user_id)
+        .select(users::totp_secret)
+        .first::<Option<String>>(connection)
+        .await
+        .optional()
+        .map(Option::flatten)
+}
+
+/// Turns two-factor authentication on, replacing any previous recovery
+/// codes.
+#[tracing::instrument(
+    name = "Enable two-factor authentication",
+    skip(connection, encrypted_secret, codes)
+)]
+pub async fn store_two_factor(
+    connection: &mut DatabaseConnection,
+    user_id: Uuid,
+    encrypted_secret: &str,
+    codes: &[RecoveryCodes],
+) -> Result<(), diesel::result::Error> {
+    connection
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                diesel::update(users::table.find(user_id))
+                    .set((
+                        users::totp_secret.eq(encrypted_secret),
+                        users::totp_last_step.eq(None::<i64>),
+                    ))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    recovery_codes::table
+                        .filter(recovery_codes::user_id.eq(user_id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::insert_into(recovery_codes::table)
+                    .values(codes)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+#[tracing::instrument(
+    name = "Disable two-factor authentication",
+    skip(connection)
+)]
+pub async fn remove_two_factor(
+    connection: &mut DatabaseConnection,
+    user_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    connection
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                diesel::update(users::table.find(user_id))
+                    .set((
+                        users::totp_secret.eq(None::<String>),
+                        users::totp_last_step.eq(None::<i64>),
+                    ))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    recovery_codes::table
+                        .filter(recovery_codes::user_id.eq(user_id)),
+                )
+                .execute(conn)
+                .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Records that the code of `step` was used, unless it or a later one
+/// already was. Returns whether the code may be accepted.
+#[tracing::instrument(name = "Record TOTP step", skip(connection))]
+pub async fn record_totp_step(
+    connection: &mut DatabaseConnection,
+    user_id: Uuid,
+    step: i64,
+) -> Result<bool, diesel::result::Error> {
+    let updated = diesel::update(
+        users::table.find(user_id).filter(
+            users::totp_last_step
+                .is_null()
+                .or(users::totp_last_step.lt(step)),
+        ),
+    )
+    .set(users::totp_last_step.eq(step))
+    .execute(connection)
+    .await?;
+    Ok(updated == 1)
+}
+
+#[tracing::instrument(name = "Get unused recovery codes", skip(connection))]
+pub async fn get_unused_recovery_codes(
+    connection: &mut DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<RecoveryCodes>, diesel::result::Error> {
+    recovery_codes::table
+        .filter(recovery_codes::user_id.eq(user_id))
+        .filter(recovery_codes::used_at.is_null())
+        .select(RecoveryCodes::as_select())
+        .load(connection)
+        .await
+}
+
+/// Marks a recovery code as used. Returns false if it already was.
+#[tracing::instrument(name = "Use recovery code", skip(connection))]
+pub async fn use_recovery_code(
+    connection: &mut DatabaseConnection,
+    code_id: Uuid,
+) -> Result<bool, diesel::result::Error> {
+    let updated = diesel::update(
+        recovery_codes::table
+            .find(code_id)
+            .filter(recovery_codes::used_at.is_null()),
+    )
+    .set(recovery_codes::used_at.eq(Utc::now()))
+    .execute(connection)
+    .await?;
+    Ok(updated == 1)
+}
@@ -1,8 +1,12 @@
+use crate::domain::UserRole;
+use crate::models::Users;
 use crate::schema;
 use crate::{database::DatabaseConnection, schema::users::dsl::*};
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
 use secrecy::{ExposeSecret, Secret};
 use uuid::Uuid;
 
@@ -19,6 +23,21 @@ pub async fn get_username(
         .context("Failed to retrieve username from database")
 }
 
+/// `None` when the user no longer exists or has been disabled.
+#[tracing::instrument(name = "Get user role query", skip(connection))]
+pub async fn get_user_role(
+    connection: &mut DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<UserRole>, diesel::result::Error> {
+    users
+        .filter(schema::users::user_id.eq(id))
+        .filter(schema::users::disabled_at.is_null())
+        .select(schema::users::role)
+        .first(connection)
+        .await
+        .optional()
+}
+
 #[tracing::instrument(name = "Change password query", skip(connection, hash))]
 pub async fn change_password_query(
     connection: &mut DatabaseConnection,
@@ -32,3 +51,157 @@ pub async fn change_password_query(
         .await?;
     Ok(())
 }
+
+/// An admin user as listed in the user management page.
+#[derive(Queryable)]
+pub struct AdminUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: UserRole,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub email: Option<String>,
+}
+
+#[tracing::instrument(name = "Get admin users query", skip(connection))]
+pub async fn get_admin_users(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<AdminUser>, diesel::result::Error> {
+    users
+        .order(schema::users::username)
+        .select((
+            schema::users::user_id,
+            schema::users::username,
+            schema::users::role,
+            schema::users::disabled_at,
+            schema::users::email,
+        ))
+        .load(connection)
+        .await
+}
+
+/// Stores the first owner, reachable at `owner_email`, unless someone got
+/// there first.
+///
+/// Returns whether the user was stored.
+#[tracing::instrument(
+    name = "Create first owner query",
+    skip(connection, user, owner_email),
+    fields(username = %user.username)
+)]
+pub async fn create_first_owner(
+    connection: &mut DatabaseConnection,
+    user: &Users,
+    owner_email: &str,
+) -> Result<bool, diesel::result::Error> {
+    connection
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                // Serializes concurrent setups without blocking reads.
+                diesel::sql_query("LOCK TABLE users IN EXCLUSIVE MODE")
+                    .execute(conn)
+                    .await?;
+                let existing: i64 = users.count().get_result(conn).await?;
+                if existing > 0 {
+                    return Ok(false);
+                }
+                diesel::insert_into(users)
+                    .values((user, schema::users::email.eq(owner_email)))
+                    .execute(conn)
+                    .await?;
+                Ok(true)
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+#[tracing::instrument(name = "Count users query", skip(connection))]
+pub async fn count_users(
+    connection: &mut DatabaseConnection,
+) -> Result<i64, diesel::result::Error> {
+    users.count().get_result(connection).await
+}
+
+/// Disables or re-enables a user. Disabled users can neither log in nor use
+/// the sessions they already have.
+#[tracing::instrument(name = "Set user disabled query", skip(connection))]
+pub async fn set_user_disabled(
+    connection: &mut DatabaseConnection,
+    id: Uuid,
+    disabled: bool,
+) -> Result<(), UserChangeError> {
+    connection
+        .transaction::<_, UserChangeError, _>(|conn| {
+            async move {
+                if disabled {
+                    ensure_not_last_owner(conn, id).await?;
+                }
+                let updated =
+                    diesel::update(users.filter(schema::users::user_id.eq(id)))
+                        .set(
+                            schema::users::disabled_at
+                                .eq(disabled.then(Utc::now)),
+                        )
+                        .execute(conn)
+                        .await?;
+                if updated == 0 {
+                    return Err(UserChangeError::UnknownUser);
+                }
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+#[tracing::instrument(name = "Delete user query", skip(connection))]
+pub async fn delete_user(
+    connection: &mut DatabaseConnection,
+    id: Uuid,
+) -> Result<(), UserChangeError> {
+    connection
+        .transaction::<_, UserChangeError, _>(|conn| {
+            async move {
+                ensure_not_last_owner(conn, id).await?;
+                let deleted =
+                    diesel::delete(users.filter(schema::users::user_id.eq(id)))
+                        .execute(conn)
+                        .await?;
+                if deleted == 0 {
+                    return Err(UserChangeError::UnknownUser);
+                }
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Fails if `id` is the only enabled owner, who is the only one left able
+/// to manage users.
+async fn ensure_not_last_owner(
+    connection: &mut DatabaseConnection,
+    id: Uuid,
+) -> Result<(), UserChangeError> {
+    let owners: Vec<Uuid> = users
+        .filter(schema::users::role.eq(UserRole::Owner))
+        .filter(schema::users::disabled_at.is_null())
+        .select(schema::users::user_id)
+        .for_update()
+        .load(connection)
+        .await?;
+    if owners == [id] {
+        return Err(UserChangeError::LastOwner);
+    }
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum UserChangeError {
+    #[error("There is no such user.")]
+    UnknownUser,
+    #[error("At least one enabled owner must remain.")]
+    LastOwner,
+    #[error("A database error has ocurred when changing a user")]
+    DatabaseError(#[from] diesel::result::Error),
+}
@@ -1,11 +1,33 @@
 mod admin_password;
+mod admin_username;
+mod email_event_kind;
+mod issue_content;
+mod issue_slug;
+mod issue_status;
 mod new_subscriber;
+mod one_time_token;
 mod subscriber_email;
 mod subscriber_name;
+mod subscription_status;
 mod subscription_token;
+mod totp_secret;
+mod tracking_token;
+mod unsubscribe_token;
+mod user_role;
 
 pub use admin_password::*;
+pub use admin_username::*;
+pub use email_event_kind::*;
+pub use issue_content::*;
+pub use issue_slug::*;
+pub use issue_status::*;
 pub use new_subscriber::*;
+pub use one_time_token::*;
 pub use subscriber_email::*;
 pub use subscriber_name::*;
+pub use subscription_status::*;
 pub use subscription_token::*;
+pub use totp_secret::*;
+pub use tracking_token::*;
+pub use unsubscribe_token::*;
+pub use user_role::*;
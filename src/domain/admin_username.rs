@@ -0,0 +1,58 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The name an admin user logs in with.
+#[derive(Debug, Clone)]
+pub struct Username(String);
+
+impl TryFrom<String> for Username {
+    type Error = InvalidUsernameError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.trim().is_empty() {
+            Err(InvalidUsernameError::Empty)
+        } else if value.graphemes(true).count() > 64 {
+            Err(InvalidUsernameError::TooLong)
+        } else if value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            Err(InvalidUsernameError::InvalidCharacters)
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl AsRef<str> for Username {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvalidUsernameError {
+    #[error("The username cannot be empty.")]
+    Empty,
+    #[error("The username cannot be longer than 64 characters.")]
+    TooLong,
+    #[error("The username cannot contain spaces.")]
+    InvalidCharacters,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Username;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn usernames_up_to_64_graphemes_are_accepted() {
+        assert_ok!(Username::try_from("ё".repeat(64)));
+        assert_err!(Username::try_from("a".repeat(65)));
+    }
+
+    #[test]
+    fn empty_usernames_are_rejected() {
+        assert_err!(Username::try_from(" ".to_string()));
+    }
+
+    #[test]
+    fn usernames_with_spaces_are_rejected() {
+        assert_err!(Username::try_from("ada lovelace".to_string()));
+    }
+}
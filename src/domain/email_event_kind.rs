@@ -0,0 +1,78 @@
+use diesel::{
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::Pg,
+    serialize::{IsNull, ToSql},
+    sql_types::Text,
+};
+use std::io::Write;
+
+use super::SubscriptionStatus;
+
+/// A delivery problem reported by the email provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression)]
+#[diesel(sql_type = Text)]
+pub enum EmailEventKind {
+    /// The address does not exist or no longer accepts mail.
+    HardBounce,
+    /// A temporary failure, like a full mailbox.
+    SoftBounce,
+    SpamComplaint,
+}
+
+impl EmailEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HardBounce => "hard_bounce",
+            Self::SoftBounce => "soft_bounce",
+            Self::SpamComplaint => "spam_complaint",
+        }
+    }
+
+    /// The status the subscriber moves to, if the event rules out mailing
+    /// them again.
+    pub fn subscription_status(&self) -> Option<SubscriptionStatus> {
+        match self {
+            Self::HardBounce => Some(SubscriptionStatus::Bounced),
+            Self::SoftBounce => None,
+            Self::SpamComplaint => Some(SubscriptionStatus::Complained),
+        }
+    }
+}
+
+impl std::fmt::Display for EmailEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for EmailEventKind {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "hard_bounce" => Ok(Self::HardBounce),
+            "soft_bounce" => Ok(Self::SoftBounce),
+            "spam_complaint" => Ok(Self::SpamComplaint),
+            other => Err(format!("{} is not an email event.", other)),
+        }
+    }
+}
+
+impl FromSql<Text, Pg> for EmailEventKind {
+    fn from_sql(
+        bytes: <Pg as diesel::backend::Backend>::RawValue<'_>,
+    ) -> diesel::deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(EmailEventKind::try_from(value)?)
+    }
+}
+
+impl ToSql<Text, Pg> for EmailEventKind {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::markdown;
+use crate::personalization::{
+    render_markdown_template, InvalidIssueTemplate, IssueTemplates,
+};
+
+/// The body of a newsletter issue.
+///
+/// It is written in Markdown, from which both versions of the email are
+/// rendered, unless the author overrides them with their own text or HTML.
+/// Both versions are templates personalized for every recipient, and the
+/// HTML is sanitized once personalized, whichever way it was written.
+#[derive(Debug)]
+pub struct IssueContent {
+    markdown: Option<String>,
+    text: String,
+    html: String,
+}
+
+impl IssueContent {
+    pub fn parse(
+        markdown: &str,
+        text_override: &str,
+        html_override: &str,
+    ) -> Result<Self, InvalidIssueContent> {
+        let markdown = Some(markdown).filter(|m| !m.trim().is_empty());
+        let text = match (text_override.trim().is_empty(), markdown) {
+            (false, _) => text_override.to_string(),
+            (true, Some(markdown)) => {
+                render_markdown_template(markdown, markdown::to_plain_text)
+            }
+            (true, None) => return Err(InvalidIssueContent::Incomplete),
+        };
+        let html = match (html_override.trim().is_empty(), markdown) {
+            (false, _) => html_override.to_string(),
+            (true, Some(markdown)) => {
+                render_markdown_template(markdown, markdown::to_html)
+            }
+            (true, None) => return Err(InvalidIssueContent::Incomplete),
+        };
+        IssueTemplates::validate(&text, &html)?;
+        Ok(Self {
+            markdown: markdown.map(str::to_string),
+            text,
+            html,
+        })
+    }
+
+    pub fn markdown(&self) -> Option<&str> {
+        self.markdown.as_deref()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvalidIssueContent {
+    #[error(
+        "The newsletter needs a Markdown body, or both a text and an HTML \
+         version."
+    )]
+    Incomplete,
+    #[error(transparent)]
+    InvalidTemplate(#[from] InvalidIssueTemplate),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueContent;
+    use crate::personalization::{IssueTemplates, PersonalizationContext};
+    use claims::assert_err;
+
+    #[test]
+    fn both_versions_are_rendered_from_markdown() {
+        let content = IssueContent::parse("Hello *there*", "", "").unwrap();
+        assert_eq!(content.markdown(), Some("Hello *there*"));
+        assert_eq!(content.text(), "Hello there");
+        assert_eq!(content.html(), "<p>Hello <em>there</em></p>\n");
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_markdown() {
+        let content =
+            IssueContent::parse("Hello *there*", "", "<b>Custom</b>").unwrap();
+        assert_eq!(content.text(), "Hello there");
+        assert_eq!(content.html(), "<b>Custom</b>");
+    }
+
+    #[test]
+    fn overrides_alone_are_accepted() {
+        let content = IssueContent::parse(" ", "Text", "<p>Html</p>").unwrap();
+        assert_eq!(content.markdown(), None);
+        assert_eq!(content.text(), "Text");
+    }
+
+    #[test]
+    fn a_missing_version_without_markdown_is_rejected() {
+        assert_err!(IssueContent::parse("", "", "<p>Html</p>"));
+        assert_err!(IssueContent::parse("", "Text", ""));
+    }
+
+    #[test]
+    fn markdown_keeps_its_placeholders() {
+        let content =
+            IssueContent::parse("Hi **{{ subscriber.name }}**", "", "")
+                .unwrap();
+        assert_eq!(content.text(), "Hi {{ subscriber.name }}");
+        assert_eq!(
+            content.html(),
+            "<p>Hi <strong>{{ subscriber.name }}</strong></p>\n"
+        );
+    }
+
+    #[test]
+    fn html_overrides_are_sanitized_like_markdown() {
+        let content = IssueContent::parse(
+            "",
+            "Text",
+            r#"<p onclick="steal()">Hi</p><script>steal()</script>"#,
+        )
+        .unwrap();
+        let (_, html) = IssueTemplates::parse(content.text(), content.html())
+            .unwrap()
+            .render(&PersonalizationContext::sample())
+            .unwrap();
+        assert_eq!(html, "<p>Hi</p>");
+    }
+
+    #[test]
+    fn html_overrides_keep_their_placeholders() {
+        let content = IssueContent::parse(
+            "",
+            "Text",
+            r#"<a href="{{ archive_url }}">{{ subscriber.name }}</a>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            content.html(),
+            r#"<a href="{{ archive_url }}">{{ subscriber.name }}</a>"#
+        );
+    }
+
+    #[test]
+    fn broken_placeholders_are_rejected() {
+        assert_err!(IssueContent::parse("Hi {{ subscriber.nmae }}", "", ""));
+        assert_err!(IssueContent::parse("", "Hi {{ name", "<p>Hi</p>"));
+    }
+}
@@ -0,0 +1,61 @@
+use uuid::Uuid;
+
+/// The URL-friendly name a published issue is archived under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    /// Lowercases the ASCII letters and digits of the title and joins the
+    /// words with dashes.
+    pub fn from_title(title: &str) -> Self {
+        let slug = title
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join("-");
+        if slug.is_empty() {
+            Self("issue".to_string())
+        } else {
+            Self(slug)
+        }
+    }
+
+    /// Tells apart issues that share a title. The end of the id is used as
+    /// the start of a v7 id is a timestamp.
+    pub fn disambiguate(self, issue_id: Uuid) -> Self {
+        let id = issue_id.simple().to_string();
+        Self(format!("{}-{}", self.0, &id[id.len() - 8..]))
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueSlug;
+    use uuid::Uuid;
+
+    #[test]
+    fn titles_become_lowercase_words_joined_by_dashes() {
+        let slug = IssueSlug::from_title("  Hello, World!  Issue #3 ");
+        assert_eq!(slug.as_ref(), "hello-world-issue-3");
+    }
+
+    #[test]
+    fn titles_without_ascii_words_fall_back_to_issue() {
+        assert_eq!(IssueSlug::from_title("¿¡ 🎉 !?").as_ref(), "issue");
+    }
+
+    #[test]
+    fn disambiguated_slugs_end_with_the_end_of_the_id() {
+        let id =
+            Uuid::parse_str("0190a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b").unwrap();
+        let slug = IssueSlug::from_title("Weekly").disambiguate(id);
+        assert_eq!(slug.as_ref(), "weekly-2e3f4a5b");
+    }
+}
@@ -0,0 +1,162 @@
+use diesel::{
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::Pg,
+    serialize::{IsNull, ToSql},
+};
+use std::io::Write;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromSqlRow,
+    AsExpression,
+    serde::Serialize,
+)]
+#[diesel(sql_type = crate::schema::sql_types::NewsletterIssueStatus)]
+#[serde(rename_all = "snake_case")]
+pub enum NewsletterIssueStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+}
+
+impl NewsletterIssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Scheduled => "scheduled",
+            Self::Sending => "sending",
+            Self::Sent => "sent",
+        }
+    }
+
+    /// Only issues that have not started going out can be edited.
+    pub fn is_editable(&self) -> bool {
+        matches!(self, Self::Draft | Self::Scheduled)
+    }
+
+    /// Checks that an issue may move from this status to `next`.
+    ///
+    /// Drafts can be scheduled and unscheduled freely, but once an issue
+    /// starts going out it can only finish.
+    pub fn transition_to(
+        self,
+        next: NewsletterIssueStatus,
+    ) -> Result<NewsletterIssueStatus, InvalidIssueTransition> {
+        use NewsletterIssueStatus::*;
+        let allowed = matches!(
+            (self, next),
+            (Draft, Scheduled | Sending)
+                | (Scheduled, Draft | Scheduled | Sending)
+                | (Sending, Sent)
+        );
+        if allowed {
+            Ok(next)
+        } else {
+            Err(InvalidIssueTransition {
+                from: self,
+                to: next,
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for NewsletterIssueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for NewsletterIssueStatus {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "draft" => Ok(Self::Draft),
+            "scheduled" => Ok(Self::Scheduled),
+            "sending" => Ok(Self::Sending),
+            "sent" => Ok(Self::Sent),
+            other => Err(format!("{} is not an issue status.", other)),
+        }
+    }
+}
+
+impl FromSql<crate::schema::sql_types::NewsletterIssueStatus, Pg>
+    for NewsletterIssueStatus
+{
+    fn from_sql(
+        bytes: <Pg as diesel::backend::Backend>::RawValue<'_>,
+    ) -> diesel::deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(NewsletterIssueStatus::try_from(value)?)
+    }
+}
+
+impl ToSql<crate::schema::sql_types::NewsletterIssueStatus, Pg>
+    for NewsletterIssueStatus
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("An issue cannot go from {from} to {to}.")]
+pub struct InvalidIssueTransition {
+    pub from: NewsletterIssueStatus,
+    pub to: NewsletterIssueStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterIssueStatus::{self, *};
+    use claims::{assert_err, assert_ok_eq};
+
+    const ALL: [NewsletterIssueStatus; 4] = [Draft, Scheduled, Sending, Sent];
+
+    #[test]
+    fn drafts_can_be_scheduled_and_unscheduled() {
+        assert_ok_eq!(Draft.transition_to(Scheduled), Scheduled);
+        assert_ok_eq!(Scheduled.transition_to(Draft), Draft);
+    }
+
+    #[test]
+    fn scheduled_issues_can_be_rescheduled() {
+        assert_ok_eq!(Scheduled.transition_to(Scheduled), Scheduled);
+    }
+
+    #[test]
+    fn issues_being_sent_cannot_go_back() {
+        for status in [Draft, Scheduled, Sending] {
+            assert_err!(Sending.transition_to(status));
+        }
+        for status in ALL {
+            assert_err!(Sent.transition_to(status));
+        }
+    }
+
+    #[test]
+    fn only_issues_being_sent_can_be_marked_as_sent() {
+        assert_err!(Draft.transition_to(Sent));
+        assert_err!(Scheduled.transition_to(Sent));
+        assert_ok_eq!(Sending.transition_to(Sent), Sent);
+    }
+
+    #[test]
+    fn statuses_round_trip_through_their_names() {
+        for status in ALL {
+            assert_ok_eq!(
+                NewsletterIssueStatus::try_from(status.as_str()),
+                status
+            );
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::distributions::Alphanumeric;
+use rand::thread_rng;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Secret token of a link that can be used once. Only its hash is stored,
+/// so reading the database does not give the links away.
+#[derive(Debug)]
+pub struct OneTimeToken(String);
+
+/// One-time token of the signup link sent to an invited admin user.
+pub type InvitationToken = OneTimeToken;
+
+/// One-time token of the link sent to an admin user who forgot their
+/// password.
+pub type PasswordResetToken = OneTimeToken;
+
+impl OneTimeToken {
+    const LENGTH: usize = 32;
+
+    pub fn generate() -> Self {
+        let mut rng = thread_rng();
+        Self(
+            std::iter::repeat_with(|| rng.sample(Alphanumeric))
+                .map(char::from)
+                .take(Self::LENGTH)
+                .collect(),
+        )
+    }
+
+    /// The form in which the token is stored and looked up.
+    pub fn hash(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.as_bytes()))
+    }
+}
+
+impl TryFrom<String> for OneTimeToken {
+    type Error = InvalidOneTimeToken;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.len() != Self::LENGTH
+            || !value.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(InvalidOneTimeToken());
+        }
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for OneTimeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("This link is invalid or has expired.")]
+pub struct InvalidOneTimeToken();
+
+#[cfg(test)]
+mod tests {
+    use super::OneTimeToken;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn generated_tokens_are_valid() {
+        let token = OneTimeToken::generate();
+        assert_ok!(OneTimeToken::try_from(token.as_ref().to_string()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        assert_err!(OneTimeToken::try_from("a".repeat(31)));
+        assert_err!(OneTimeToken::try_from(format!("{}-", "a".repeat(31))));
+        assert_err!(OneTimeToken::try_from(format!("{}/", "a".repeat(31))));
+    }
+
+    #[test]
+    fn the_hash_does_not_reveal_the_token() {
+        let token = OneTimeToken::generate();
+        let other = OneTimeToken::generate();
+        assert_ne!(token.hash(), token.as_ref());
+        assert_eq!(token.hash(), token.hash());
+        assert_ne!(token.hash(), other.hash());
+    }
+}
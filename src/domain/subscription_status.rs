@@ -0,0 +1,175 @@
+use diesel::{
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::Pg,
+    serialize::{IsNull, ToSql},
+};
+use std::io::Write;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromSqlRow,
+    AsExpression,
+    serde::Serialize,
+)]
+#[diesel(sql_type = crate::schema::sql_types::SubscriptionStatus)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    Pending,
+    Confirmed,
+    Unsubscribed,
+    Bounced,
+    Complained,
+}
+
+impl SubscriptionStatus {
+    pub const ALL: [SubscriptionStatus; 5] = [
+        Self::Pending,
+        Self::Confirmed,
+        Self::Unsubscribed,
+        Self::Bounced,
+        Self::Complained,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Confirmed => "confirmed",
+            Self::Unsubscribed => "unsubscribed",
+            Self::Bounced => "bounced",
+            Self::Complained => "complained",
+        }
+    }
+
+    /// Checks that a subscriber may move from this status to `next`.
+    ///
+    /// Staying in the same status is always allowed. Opting out is always
+    /// allowed, but coming back requires confirming the address again.
+    pub fn transition_to(
+        self,
+        next: SubscriptionStatus,
+    ) -> Result<SubscriptionStatus, InvalidStatusTransition> {
+        use SubscriptionStatus::*;
+        let allowed = self == next
+            || matches!(
+                (self, next),
+                (Pending, Confirmed | Unsubscribed | Bounced | Complained)
+                    | (Confirmed, Unsubscribed | Bounced | Complained)
+                    | (Unsubscribed, Pending)
+                    | (Bounced, Pending | Unsubscribed)
+                    | (Complained, Unsubscribed)
+            );
+        if allowed {
+            Ok(next)
+        } else {
+            Err(InvalidStatusTransition {
+                from: self,
+                to: next,
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for SubscriptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for SubscriptionStatus {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pending" => Ok(Self::Pending),
+            "confirmed" => Ok(Self::Confirmed),
+            "unsubscribed" => Ok(Self::Unsubscribed),
+            "bounced" => Ok(Self::Bounced),
+            "complained" => Ok(Self::Complained),
+            other => Err(format!("{} is not a subscription status.", other)),
+        }
+    }
+}
+
+impl FromSql<crate::schema::sql_types::SubscriptionStatus, Pg>
+    for SubscriptionStatus
+{
+    fn from_sql(
+        bytes: <Pg as diesel::backend::Backend>::RawValue<'_>,
+    ) -> diesel::deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(SubscriptionStatus::try_from(value)?)
+    }
+}
+
+impl ToSql<crate::schema::sql_types::SubscriptionStatus, Pg>
+    for SubscriptionStatus
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("A subscriber cannot go from {from} to {to}.")]
+pub struct InvalidStatusTransition {
+    pub from: SubscriptionStatus,
+    pub to: SubscriptionStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriptionStatus::{self, *};
+    use claims::{assert_err, assert_ok_eq};
+
+    const ALL: [SubscriptionStatus; 5] =
+        [Pending, Confirmed, Unsubscribed, Bounced, Complained];
+
+    #[test]
+    fn pending_subscribers_can_be_confirmed() {
+        assert_ok_eq!(Pending.transition_to(Confirmed), Confirmed);
+    }
+
+    #[test]
+    fn every_subscriber_can_unsubscribe() {
+        for status in ALL {
+            assert_ok_eq!(status.transition_to(Unsubscribed), Unsubscribed);
+        }
+    }
+
+    #[test]
+    fn staying_in_the_same_status_is_allowed() {
+        for status in ALL {
+            assert_ok_eq!(status.transition_to(status), status);
+        }
+    }
+
+    #[test]
+    fn only_pending_subscribers_can_be_confirmed() {
+        for status in [Unsubscribed, Bounced, Complained] {
+            assert_err!(status.transition_to(Confirmed));
+        }
+    }
+
+    #[test]
+    fn complaints_are_never_resubscribed() {
+        assert_err!(Complained.transition_to(Pending));
+    }
+
+    #[test]
+    fn statuses_round_trip_through_their_names() {
+        for status in ALL {
+            assert_ok_eq!(
+                SubscriptionStatus::try_from(status.as_str()),
+                status
+            );
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Stateless token tying opens and clicks to the issue and subscriber an
+/// email was sent for.
+///
+/// Shaped as `<issue id>.<subscriber id>.<signature>`, signed with the
+/// application's `hmac_secret`.
+#[derive(Debug)]
+pub struct TrackingToken(String);
+
+impl TrackingToken {
+    pub fn generate(
+        issue_id: Uuid,
+        subscriber_id: Uuid,
+        secret: &Secret<String>,
+    ) -> Self {
+        let signature = URL_SAFE_NO_PAD.encode(
+            signer(issue_id, subscriber_id, secret)
+                .finalize()
+                .into_bytes(),
+        );
+        Self(format!(
+            "{}.{}.{}",
+            issue_id.simple(),
+            subscriber_id.simple(),
+            signature
+        ))
+    }
+
+    /// Returns the issue and subscriber the token was issued for.
+    pub fn verify(
+        token: &str,
+        secret: &Secret<String>,
+    ) -> Result<(Uuid, Uuid), InvalidTrackingToken> {
+        let mut parts = token.splitn(3, '.');
+        let (Some(issue_id), Some(subscriber_id), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(InvalidTrackingToken());
+        };
+        let issue_id =
+            Uuid::try_parse(issue_id).map_err(|_| InvalidTrackingToken())?;
+        let subscriber_id = Uuid::try_parse(subscriber_id)
+            .map_err(|_| InvalidTrackingToken())?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| InvalidTrackingToken())?;
+        signer(issue_id, subscriber_id, secret)
+            .verify_slice(&signature)
+            .map_err(|_| InvalidTrackingToken())?;
+        Ok((issue_id, subscriber_id))
+    }
+}
+
+fn signer(
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    secret: &Secret<String>,
+) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any size");
+    mac.update(b"tracking:");
+    mac.update(issue_id.as_bytes());
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+impl AsRef<str> for TrackingToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Tracking token is invalid.")]
+pub struct InvalidTrackingToken();
+
+#[cfg(test)]
+mod tests {
+    use super::TrackingToken;
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn secret() -> Secret<String> {
+        Secret::new("super-secret".to_string())
+    }
+
+    #[test]
+    fn generated_tokens_are_valid() {
+        let issue_id = Uuid::now_v7();
+        let subscriber_id = Uuid::now_v7();
+        let token = TrackingToken::generate(issue_id, subscriber_id, &secret());
+        assert_ok_eq!(
+            TrackingToken::verify(token.as_ref(), &secret()),
+            (issue_id, subscriber_id)
+        );
+    }
+
+    #[test]
+    fn tampered_ids_are_rejected() {
+        let token =
+            TrackingToken::generate(Uuid::now_v7(), Uuid::now_v7(), &secret());
+        let (issue_id, rest) = token.as_ref().split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let forged =
+            format!("{}.{}.{}", issue_id, Uuid::now_v7().simple(), signature);
+        assert_err!(TrackingToken::verify(&forged, &secret()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not-a-token", "abc.def", "a.b.c", ".."] {
+            assert_err!(TrackingToken::verify(token, &secret()));
+        }
+    }
+}
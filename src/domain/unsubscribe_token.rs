@@ -0,0 +1,104 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Stateless token proving the holder received an email for a subscriber.
+///
+/// Shaped as `<subscriber id>.<signature>`, signed with the application's
+/// `hmac_secret`, so unsubscribe links never expire and need no storage.
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: Uuid, secret: &Secret<String>) -> Self {
+        let signature = URL_SAFE_NO_PAD
+            .encode(signer(subscriber_id, secret).finalize().into_bytes());
+        Self(format!("{}.{}", subscriber_id.simple(), signature))
+    }
+
+    /// Returns the subscriber the token was issued for.
+    pub fn verify(
+        token: &str,
+        secret: &Secret<String>,
+    ) -> Result<Uuid, InvalidUnsubscribeToken> {
+        let (subscriber_id, signature) =
+            token.split_once('.').ok_or(InvalidUnsubscribeToken())?;
+        let subscriber_id = Uuid::try_parse(subscriber_id)
+            .map_err(|_| InvalidUnsubscribeToken())?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| InvalidUnsubscribeToken())?;
+        signer(subscriber_id, secret)
+            .verify_slice(&signature)
+            .map_err(|_| InvalidUnsubscribeToken())?;
+        Ok(subscriber_id)
+    }
+}
+
+fn signer(subscriber_id: Uuid, secret: &Secret<String>) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any size");
+    mac.update(b"unsubscribe:");
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unsubscribe token is invalid.")]
+pub struct InvalidUnsubscribeToken();
+
+#[cfg(test)]
+mod tests {
+    use super::UnsubscribeToken;
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn secret() -> Secret<String> {
+        Secret::new("super-secret".to_string())
+    }
+
+    #[test]
+    fn generated_tokens_are_valid() {
+        let subscriber_id = Uuid::now_v7();
+        let token = UnsubscribeToken::generate(subscriber_id, &secret());
+        assert_ok_eq!(
+            UnsubscribeToken::verify(token.as_ref(), &secret()),
+            subscriber_id
+        );
+    }
+
+    #[test]
+    fn tokens_signed_with_another_secret_are_rejected() {
+        let token = UnsubscribeToken::generate(
+            Uuid::now_v7(),
+            &Secret::new("another-secret".to_string()),
+        );
+        assert_err!(UnsubscribeToken::verify(token.as_ref(), &secret()));
+    }
+
+    #[test]
+    fn tampered_subscriber_ids_are_rejected() {
+        let token = UnsubscribeToken::generate(Uuid::now_v7(), &secret());
+        let (_, signature) = token.as_ref().split_once('.').unwrap();
+        let forged = format!("{}.{}", Uuid::now_v7().simple(), signature);
+        assert_err!(UnsubscribeToken::verify(&forged, &secret()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not-a-token", "abc.def", "."] {
+            assert_err!(UnsubscribeToken::verify(token, &secret()));
+        }
+    }
+}
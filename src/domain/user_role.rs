@@ -0,0 +1,119 @@
+use diesel::{
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::Pg,
+    serialize::{IsNull, ToSql},
+};
+use std::io::Write;
+
+/// What an admin user may do.
+///
+/// Each role can do everything the ones below it can: viewers read the
+/// admin area, editors also write and publish issues and manage
+/// subscribers, and owners also manage the other admin users.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromSqlRow,
+    AsExpression,
+    serde::Serialize,
+)]
+#[diesel(sql_type = crate::schema::sql_types::UserRole)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl UserRole {
+    pub const ALL: [UserRole; 3] = [Self::Owner, Self::Editor, Self::Viewer];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Editor => "editor",
+            Self::Viewer => "viewer",
+        }
+    }
+
+    /// Whether this role may do what `required` may.
+    pub fn includes(self, required: UserRole) -> bool {
+        self.rank() >= required.rank()
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Self::Owner => 2,
+            Self::Editor => 1,
+            Self::Viewer => 0,
+        }
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for UserRole {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "owner" => Ok(Self::Owner),
+            "editor" => Ok(Self::Editor),
+            "viewer" => Ok(Self::Viewer),
+            other => Err(format!("{} is not a user role.", other)),
+        }
+    }
+}
+
+impl FromSql<crate::schema::sql_types::UserRole, Pg> for UserRole {
+    fn from_sql(
+        bytes: <Pg as diesel::backend::Backend>::RawValue<'_>,
+    ) -> diesel::deserialize::Result<Self> {
+        let value = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(UserRole::try_from(value)?)
+    }
+}
+
+impl ToSql<crate::schema::sql_types::UserRole, Pg> for UserRole {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserRole::{self, *};
+    use claims::assert_ok_eq;
+
+    #[test]
+    fn roles_include_the_ones_below_them() {
+        assert!(Owner.includes(Editor));
+        assert!(Owner.includes(Viewer));
+        assert!(Editor.includes(Viewer));
+    }
+
+    #[test]
+    fn roles_do_not_include_the_ones_above_them() {
+        assert!(!Viewer.includes(Editor));
+        assert!(!Viewer.includes(Owner));
+        assert!(!Editor.includes(Owner));
+    }
+
+    #[test]
+    fn roles_round_trip_through_their_names() {
+        for role in UserRole::ALL {
+            assert_ok_eq!(UserRole::try_from(role.as_str()), role);
+        }
+    }
+}
@@ -1,31 +1,37 @@
 use crate::domain::SubscriberEmail;
-use reqwest::Client;
-use secrecy::{ExposeSecret, Secret};
 
+mod file_sink;
+mod postmark;
 pub mod send;
+mod smtp;
+mod transport;
+
+pub use file_sink::*;
+pub use postmark::*;
+pub use smtp::*;
+pub use transport::*;
+
+/// A newsletter recipient along with their personal one-click unsubscribe URL
+/// and the bodies personalized for them.
+pub struct NewsletterRecipient {
+    pub email: SubscriberEmail,
+    pub unsubscribe_url: String,
+    pub text_content: String,
+    pub html_content: String,
+}
 
 #[derive(Debug)]
 pub struct EmailClient {
-    api_token: Secret<String>,
     sender: SubscriberEmail,
-    http_client: Client,
-    base_url: String,
+    transport: Box<dyn EmailTransport>,
 }
 
 impl EmailClient {
     pub fn new(
-        base_url: &str,
         sender: SubscriberEmail,
-        api_token: Secret<String>,
-        timeout: std::time::Duration,
+        transport: Box<dyn EmailTransport>,
     ) -> Self {
-        let http_client = Client::builder().timeout(timeout).build().unwrap();
-        Self {
-            base_url: base_url.to_string(),
-            sender,
-            http_client,
-            api_token,
-        }
+        Self { sender, transport }
     }
     pub async fn send_email(
         &self,
@@ -33,42 +39,57 @@ impl EmailClient {
         text_content: &str,
         html_content: &str,
         subject: &str,
-    ) -> Result<(), reqwest::Error> {
-        let url = format!("{}/email", &self.base_url);
-        let request_body = SendEmailRequest {
+    ) -> Result<(), EmailError> {
+        let email = Email {
             from: self.sender.as_ref(),
             to: recipient.as_ref(),
             subject,
             html_body: html_content,
             text_body: text_content,
+            headers: Vec::new(),
         };
-
-        let _builder = self
-            .http_client
-            .post(url)
-            .header("X-Postmark-Server-Token", self.api_token.expose_secret())
-            .json(&request_body)
-            .send()
-            .await?
-            .error_for_status()?;
-        Ok(())
+        self.transport.send(&email).await
     }
-}
 
-#[derive(serde::Serialize)]
-#[serde(rename_all = "PascalCase")]
-struct SendEmailRequest<'a> {
-    from: &'a str,
-    to: &'a str,
-    subject: &'a str,
-    html_body: &'a str,
-    text_body: &'a str,
+    /// Sends every recipient their email under the same subject, returning
+    /// one outcome per recipient in the same order.
+    ///
+    /// Every email carries RFC 8058 one-click unsubscribe headers.
+    pub async fn send_email_batch(
+        &self,
+        recipients: &[NewsletterRecipient],
+        subject: &str,
+    ) -> Vec<Result<(), EmailError>> {
+        let emails: Vec<Email> = recipients
+            .iter()
+            .map(|recipient| Email {
+                from: self.sender.as_ref(),
+                to: recipient.email.as_ref(),
+                subject,
+                html_body: &recipient.html_content,
+                text_body: &recipient.text_content,
+                headers: vec![
+                    (
+                        "List-Unsubscribe".to_string(),
+                        format!("<{}>", recipient.unsubscribe_url),
+                    ),
+                    (
+                        "List-Unsubscribe-Post".to_string(),
+                        "List-Unsubscribe=One-Click".to_string(),
+                    ),
+                ],
+            })
+            .collect();
+        self.transport.send_batch(&emails).await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::domain::SubscriberEmail;
-    use crate::email_client::EmailClient;
+    use crate::email_client::{
+        EmailClient, NewsletterRecipient, PostmarkTransport,
+    };
     use claims::{assert_err, assert_ok};
     use fake::faker::internet::en::SafeEmail;
     use fake::faker::lorem::en::{Paragraph, Sentence};
@@ -76,7 +97,22 @@ mod tests {
     use secrecy::Secret;
     use wiremock::matchers::{any, header, header_exists, method, path};
     use wiremock::Request;
-    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::{Mock, MockServer, Respond, ResponseTemplate};
+
+    /// Accepts every message of a batch, as Postmark does.
+    struct PostmarkBatchResponder;
+
+    impl Respond for PostmarkBatchResponder {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let messages: Vec<serde_json::Value> =
+                serde_json::from_slice(&request.body).unwrap();
+            let results: Vec<serde_json::Value> = messages
+                .iter()
+                .map(|_| serde_json::json!({"ErrorCode": 0, "Message": "OK"}))
+                .collect();
+            ResponseTemplate::new(200).set_body_json(results)
+        }
+    }
 
     struct SendEmailBodyMatcher;
 
@@ -96,10 +132,42 @@ mod tests {
         }
     }
 
+    struct SendEmailBatchBodyMatcher(usize);
+
+    impl wiremock::Match for SendEmailBatchBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<Vec<serde_json::Value>, _> =
+                serde_json::from_slice(&request.body);
+            match result {
+                Ok(messages) => {
+                    messages.len() == self.0
+                        && messages.iter().all(|body| {
+                            body.get("From").is_some()
+                                && body.get("To").is_some()
+                                && body.get("Subject").is_some()
+                                && body.get("HtmlBody").is_some()
+                                && body.get("TextBody").is_some()
+                                && body.get("Headers").is_some()
+                        })
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
     fn email() -> SubscriberEmail {
         SubscriberEmail::try_from(SafeEmail().fake::<String>()).unwrap()
     }
 
+    fn recipient() -> NewsletterRecipient {
+        NewsletterRecipient {
+            email: email(),
+            unsubscribe_url: "https://example.com/unsubscribe".to_string(),
+            text_content: content(),
+            html_content: content(),
+        }
+    }
+
     fn subject() -> String {
         Sentence(1..2).fake()
     }
@@ -110,10 +178,12 @@ mod tests {
 
     fn email_client(base_url: &str) -> EmailClient {
         EmailClient::new(
-            base_url,
             email(),
-            Secret::new(Faker.fake()),
-            std::time::Duration::from_millis(200),
+            Box::new(PostmarkTransport::new(
+                base_url,
+                Secret::new(Faker.fake()),
+                std::time::Duration::from_millis(200),
+            )),
         )
     }
 
@@ -173,6 +243,24 @@ mod tests {
         assert_err!(outcome);
     }
 
+    #[tokio::test]
+    async fn client_errors_are_not_transient() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &content(), &content(), &subject())
+            .await;
+
+        assert!(!assert_err!(outcome).is_transient());
+    }
+
     #[tokio::test]
     async fn send_email_fail_when_server_takes_too_long() {
         let mock_server = MockServer::start().await;
@@ -191,6 +279,101 @@ mod tests {
             .send_email(&email(), &content(), &content(), &subject())
             .await;
 
-        assert_err!(outcome);
+        assert!(assert_err!(outcome).is_transient());
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_sends_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .and(SendEmailBatchBodyMatcher(2))
+            .respond_with(PostmarkBatchResponder)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcomes = email_client
+            .send_email_batch(&[recipient(), recipient()], &subject())
+            .await;
+
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_splits_recipients_in_chunks_of_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+        let recipients: Vec<NewsletterRecipient> =
+            (0..501).map(|_| recipient()).collect();
+
+        Mock::given(path("/email/batch"))
+            .and(SendEmailBatchBodyMatcher(500))
+            .respond_with(PostmarkBatchResponder)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .and(SendEmailBatchBodyMatcher(1))
+            .respond_with(PostmarkBatchResponder)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcomes =
+            email_client.send_email_batch(&recipients, &subject()).await;
+
+        assert_eq!(outcomes.len(), 501);
+        assert!(outcomes.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_reports_individual_failures() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!([
+                    {"ErrorCode": 0, "Message": "OK"},
+                    {"ErrorCode": 406, "Message": "Inactive recipient"},
+                ]),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcomes = email_client
+            .send_email_batch(&[recipient(), recipient()], &subject())
+            .await;
+
+        assert_ok!(&outcomes[0]);
+        assert!(!assert_err!(&outcomes[1]).is_transient());
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_fails_every_message_when_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcomes = email_client
+            .send_email_batch(&[recipient(), recipient()], &subject())
+            .await;
+
+        assert_eq!(outcomes.len(), 2);
+        for outcome in &outcomes {
+            assert!(assert_err!(outcome).is_transient());
+        }
     }
 }
@@ -0,0 +1,70 @@
+use super::{Email, EmailError, EmailTransport};
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use std::path::PathBuf;
+
+/// Writes every email as an `.eml` file instead of delivering it, so local
+/// environments can inspect outgoing mail without an email provider.
+#[derive(Debug)]
+pub struct FileSinkTransport {
+    directory: PathBuf,
+    sink: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileSinkTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let directory = directory.into();
+        let sink = AsyncFileTransport::new(&directory);
+        Self { directory, sink }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for FileSinkTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailError> {
+        let message = email.to_message()?;
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|e| EmailError::Unavailable(e.into()))?;
+        let id = self
+            .sink
+            .send(message)
+            .await
+            .map_err(|e| EmailError::Unavailable(e.into()))?;
+        tracing::info!(email_id = %id, "Email written to the file sink.");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileSinkTransport;
+    use crate::email_client::{Email, EmailTransport};
+    use claims::assert_ok;
+
+    #[tokio::test]
+    async fn send_writes_the_email_to_the_directory() {
+        let directory =
+            std::env::temp_dir().join(uuid::Uuid::now_v7().to_string());
+        let transport = FileSinkTransport::new(&directory);
+        let email = Email {
+            from: "sender@example.com",
+            to: "recipient@example.com",
+            subject: "Hello",
+            html_body: "<p>Hello there</p>",
+            text_body: "Hello there",
+            headers: Vec::new(),
+        };
+
+        assert_ok!(transport.send(&email).await);
+
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let contents = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(contents.contains("To: recipient@example.com"));
+        assert!(contents.contains("Subject: Hello"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
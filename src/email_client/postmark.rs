@@ -0,0 +1,178 @@
+use super::{Email, EmailError, EmailTransport};
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+
+/// Largest number of messages Postmark accepts in a single batch call.
+const MAX_BATCH_SIZE: usize = 500;
+
+#[derive(Debug)]
+pub struct PostmarkTransport {
+    api_token: Secret<String>,
+    http_client: Client,
+    base_url: String,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        base_url: &str,
+        api_token: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            base_url: base_url.to_string(),
+            http_client,
+            api_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for PostmarkTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailError> {
+        let url = format!("{}/email", &self.base_url);
+        let request_body = SendEmailRequest::from(email);
+        self.http_client
+            .post(url)
+            .header("X-Postmark-Server-Token", self.api_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(classify)?;
+        Ok(())
+    }
+
+    async fn send_batch(
+        &self,
+        emails: &[Email<'_>],
+    ) -> Vec<Result<(), EmailError>> {
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for chunk in emails.chunks(MAX_BATCH_SIZE) {
+            match self.send_chunk(chunk).await {
+                Ok(results) => outcomes.extend(results),
+                Err(e) => {
+                    // The whole call failed, so every message in it did too.
+                    let transient = is_transient(&e);
+                    outcomes.extend(chunk.iter().map(|_| {
+                        let error = anyhow::anyhow!("{}", e);
+                        if transient {
+                            Err(EmailError::Unavailable(error))
+                        } else {
+                            Err(EmailError::Rejected(error))
+                        }
+                    }));
+                }
+            }
+        }
+        outcomes
+    }
+}
+
+impl PostmarkTransport {
+    #[tracing::instrument(
+        name = "Send email batch",
+        skip_all,
+        fields(batch_size = chunk.len())
+    )]
+    async fn send_chunk(
+        &self,
+        chunk: &[Email<'_>],
+    ) -> Result<Vec<Result<(), EmailError>>, reqwest::Error> {
+        let url = format!("{}/email/batch", &self.base_url);
+        let request_body: Vec<SendEmailRequest> =
+            chunk.iter().map(SendEmailRequest::from).collect();
+        let results: Vec<BatchResult> = self
+            .http_client
+            .post(url)
+            .header("X-Postmark-Server-Token", self.api_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let mut results = results.into_iter();
+        // Postmark answers with one result per message, in request order.
+        Ok(chunk
+            .iter()
+            .map(|_| match results.next() {
+                Some(BatchResult { error_code: 0, .. }) => Ok(()),
+                Some(BatchResult {
+                    error_code,
+                    message,
+                }) => Err(EmailError::Rejected(anyhow::anyhow!(
+                    "Postmark error {}: {}",
+                    error_code,
+                    message
+                ))),
+                None => Err(EmailError::Rejected(anyhow::anyhow!(
+                    "Postmark did not report a result for this message."
+                ))),
+            })
+            .collect())
+    }
+}
+
+/// Timeouts, connection failures, rate limiting and server errors are worth
+/// retrying; any other client error will fail the same way again.
+fn is_transient(error: &reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => {
+            status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status.is_server_error()
+        }
+        None => error.is_timeout() || error.is_connect() || error.is_request(),
+    }
+}
+
+fn classify(error: reqwest::Error) -> EmailError {
+    if is_transient(&error) {
+        EmailError::Unavailable(error.into())
+    } else {
+        EmailError::Rejected(error.into())
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<Header<'a>>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Header<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl<'a> From<&'a Email<'a>> for SendEmailRequest<'a> {
+    fn from(email: &'a Email<'a>) -> Self {
+        Self {
+            from: email.from,
+            to: email.to,
+            subject: email.subject,
+            html_body: email.html_body,
+            text_body: email.text_body,
+            headers: email
+                .headers
+                .iter()
+                .map(|(name, value)| Header { name, value })
+                .collect(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BatchResult {
+    error_code: i64,
+    message: String,
+}
@@ -1,17 +1,20 @@
 use crate::{
-    domain::{NewSubscriber, SubscriptionToken},
+    domain::{
+        InvitationToken, PasswordResetToken, SubscriberEmail,
+        SubscriptionToken, UserRole,
+    },
     TEMPLATES,
 };
 
-use super::EmailClient;
+use super::{EmailClient, EmailError};
 
 #[tracing::instrument(
     name = "Send a confirmation email to the new subscriber",
-    skip(email_client, new_subscriber)
+    skip(email_client, subscriber_email)
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+    subscriber_email: &SubscriberEmail,
     base_url: &str,
     token: &SubscriptionToken,
 ) -> Result<(), SendEmailError> {
@@ -28,7 +31,7 @@ pub async fn send_confirmation_email(
                         confirmation_link);
     email_client
         .send_email(
-            &new_subscriber.email,
+            subscriber_email,
             &plain_text_body,
             &html_body,
             "Welcome to reingma's newsletter!",
@@ -38,10 +41,98 @@ pub async fn send_confirmation_email(
     tracing::info!("Email sent to subscriber.");
     Ok(())
 }
+#[tracing::instrument(
+    name = "Send an invitation email to a new admin user",
+    skip(email_client, email, token)
+)]
+pub async fn send_invitation_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+    token: &InvitationToken,
+    role: UserRole,
+) -> Result<(), SendEmailError> {
+    let signup_link = format!("{}/signup?token={}", base_url, token.as_ref());
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("link", &signup_link);
+    tera_context.insert("role", role.as_str());
+    let html_body =
+        TEMPLATES.render("emails/invitation.html", &tera_context)?;
+    let plain_text_body = format!(
+        "You have been invited to help run reingma's newsletter as {}.\n\
+        Visit {} to create your account.",
+        role, signup_link
+    );
+    email_client
+        .send_email(
+            email,
+            &plain_text_body,
+            &html_body,
+            "You have been invited to reingma's newsletter",
+        )
+        .await?;
+    tracing::info!("Invitation email sent.");
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Send a password reset email to an admin user",
+    skip(email_client, email, token)
+)]
+pub async fn send_password_reset_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+    token: &PasswordResetToken,
+) -> Result<(), SendEmailError> {
+    let reset_link =
+        format!("{}/login/reset?token={}", base_url, token.as_ref());
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("link", &reset_link);
+    let html_body =
+        TEMPLATES.render("emails/password_reset.html", &tera_context)?;
+    let plain_text_body = format!(
+        "Someone asked to reset the password of your account.\n\
+        Visit {} within the next hour to choose a new one.\n\
+        If it was not you, you can ignore this email.",
+        reset_link
+    );
+    email_client
+        .send_email(email, &plain_text_body, &html_body, "Reset your password")
+        .await?;
+    tracing::info!("Password reset email sent.");
+    Ok(())
+}
+
+/// Wraps a newsletter issue in the email layout, returning its text and HTML
+/// versions.
+pub fn render_newsletter_email(
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+    view_online_url: Option<&str>,
+    tracking_pixel_url: Option<&str>,
+) -> Result<(String, String), tera::Error> {
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("title", title);
+    tera_context.insert("content", html_content);
+    tera_context.insert("view_online_url", &view_online_url);
+    tera_context.insert("tracking_pixel_url", &tracking_pixel_url);
+    let html_body =
+        TEMPLATES.render("emails/newsletter.html", &tera_context)?;
+    let plain_text_body = match view_online_url {
+        Some(url) => {
+            format!("View this issue online: {}\n\n{}", url, text_content)
+        }
+        None => text_content.to_string(),
+    };
+    Ok((plain_text_body, html_body))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum SendEmailError {
     #[error("Could not render email template.")]
     TemplateRenderError(#[from] tera::Error),
     #[error("Could not deliver email.")]
-    DeliveryError(#[from] reqwest::Error),
+    DeliveryError(#[from] EmailError),
 }
@@ -0,0 +1,176 @@
+use super::{Email, EmailError, EmailTransport};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use secrecy::{ExposeSecret, Secret};
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain text connection, only meant for local mail catchers.
+    Off,
+    StartTls,
+    Implicit,
+}
+
+#[derive(Debug)]
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        host: &str,
+        port: u16,
+        tls: SmtpTls,
+        credentials: Option<(String, Secret<String>)>,
+        timeout: std::time::Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let builder = match tls {
+            SmtpTls::Off => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+            }
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+            }
+            SmtpTls::Implicit => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+            }
+        };
+        let mut builder = builder.port(port).timeout(Some(timeout));
+        if let Some((username, password)) = credentials {
+            builder = builder
+                .credentials(Credentials::new(
+                    username,
+                    password.expose_secret().to_owned(),
+                ))
+                .authentication(vec![Mechanism::Plain, Mechanism::Login]);
+        }
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailError> {
+        let message = email.to_message()?;
+        self.mailer.send(message).await.map_err(|e| {
+            // Only a 5xx reply means the server will never accept the email.
+            if e.is_permanent() {
+                EmailError::Rejected(e.into())
+            } else {
+                EmailError::Unavailable(e.into())
+            }
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SmtpTls, SmtpTransport};
+    use crate::email_client::{Email, EmailError, EmailTransport};
+    use claims::{assert_err, assert_ok};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Accepts a single SMTP session and returns the received DATA.
+    async fn fake_smtp_server(
+        rcpt_reply: &'static str,
+    ) -> (u16, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Ok(Some(line)) = lines.next_line().await {
+                if in_data {
+                    if line == "." {
+                        writer.write_all(b"250 Queued\r\n").await.unwrap();
+                        break;
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                let reply = match line.split(' ').next().unwrap() {
+                    "EHLO" => "250 localhost\r\n",
+                    "RCPT" => rcpt_reply,
+                    "DATA" => {
+                        in_data = true;
+                        "354 End data with <CR><LF>.<CR><LF>\r\n"
+                    }
+                    _ => "250 OK\r\n",
+                };
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+            data
+        });
+        (port, handle)
+    }
+
+    fn transport(port: u16) -> SmtpTransport {
+        SmtpTransport::new(
+            "127.0.0.1",
+            port,
+            SmtpTls::Off,
+            None,
+            std::time::Duration::from_secs(2),
+        )
+        .unwrap()
+    }
+
+    fn email() -> Email<'static> {
+        Email {
+            from: "sender@example.com",
+            to: "recipient@example.com",
+            subject: "Hello",
+            html_body: "<p>Hello there</p>",
+            text_body: "Hello there",
+            headers: vec![(
+                "List-Unsubscribe".to_string(),
+                "<https://example.com/unsubscribe>".to_string(),
+            )],
+        }
+    }
+
+    #[tokio::test]
+    async fn send_delivers_a_multipart_message_with_headers() {
+        let (port, server) = fake_smtp_server("250 OK\r\n").await;
+        let transport = transport(port);
+
+        assert_ok!(transport.send(&email()).await);
+
+        let data = server.await.unwrap();
+        assert!(data.contains("Subject: Hello"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("<p>Hello there</p>"));
+        assert!(data
+            .contains("List-Unsubscribe: <https://example.com/unsubscribe>"));
+    }
+
+    #[tokio::test]
+    async fn permanent_rejections_are_not_transient() {
+        let (port, _server) = fake_smtp_server("550 No such user\r\n").await;
+        let outcome = transport(port).send(&email()).await;
+
+        let error = assert_err!(outcome);
+        assert!(matches!(error, EmailError::Rejected(_)));
+    }
+
+    #[tokio::test]
+    async fn temporary_failures_are_transient() {
+        let (port, _server) = fake_smtp_server("451 Try again later\r\n").await;
+        let outcome = transport(port).send(&email()).await;
+
+        let error = assert_err!(outcome);
+        assert!(error.is_transient());
+    }
+}
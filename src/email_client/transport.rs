@@ -0,0 +1,74 @@
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+
+pub struct Email<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    /// Extra headers as `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Email<'_> {
+    /// Builds a `multipart/alternative` MIME message for the transports that
+    /// speak raw email rather than a provider API.
+    pub(super) fn to_message(&self) -> Result<Message, EmailError> {
+        let from: Mailbox = self
+            .from
+            .parse()
+            .map_err(|e| EmailError::Rejected(anyhow::Error::new(e)))?;
+        let to: Mailbox = self
+            .to
+            .parse()
+            .map_err(|e| EmailError::Rejected(anyhow::Error::new(e)))?;
+        let mut builder = Message::builder().from(from).to(to);
+        for (name, value) in &self.headers {
+            let name = HeaderName::new_from_ascii(name.clone())
+                .map_err(|e| EmailError::Rejected(anyhow::Error::new(e)))?;
+            builder = builder.raw_header(HeaderValue::new(name, value.clone()));
+        }
+        builder
+            .subject(self.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                self.text_body.to_owned(),
+                self.html_body.to_owned(),
+            ))
+            .map_err(|e| EmailError::Rejected(anyhow::Error::new(e)))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EmailError {
+    #[error("The email transport is unavailable, try again later.")]
+    Unavailable(#[source] anyhow::Error),
+    #[error("The email was rejected by the transport.")]
+    Rejected(#[source] anyhow::Error),
+}
+
+impl EmailError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, EmailError::Unavailable(_))
+    }
+}
+
+#[async_trait::async_trait]
+pub trait EmailTransport: std::fmt::Debug + Send + Sync {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailError>;
+
+    /// Sends every email and reports one outcome per email, in order.
+    ///
+    /// Transports without a batch API fall back to sending one at a time.
+    async fn send_batch(
+        &self,
+        emails: &[Email<'_>],
+    ) -> Vec<Result<(), EmailError>> {
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for email in emails {
+            outcomes.push(self.send(email).await);
+        }
+        outcomes
+    }
+}
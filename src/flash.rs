@@ -0,0 +1,180 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+    response::{IntoResponseParts, ResponseParts},
+};
+use axum_extra::extract::{cookie::Key, SignedCookieJar};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use cookie::Cookie;
+
+const FLASH_COOKIE: &str = "_flash";
+
+#[derive(
+    serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FlashMessage {
+    pub level: FlashLevel,
+    pub message: String,
+}
+
+/// Messages shown once on the next page the user sees, kept in the signed
+/// cookie jar in between.
+///
+/// As an extractor it holds the messages left by the previous request;
+/// returned as a response part it stores the ones added since, along with
+/// any that were not taken for rendering.
+pub struct Flash {
+    jar: SignedCookieJar,
+    incoming: Vec<FlashMessage>,
+    outgoing: Vec<FlashMessage>,
+}
+
+impl Flash {
+    pub fn info(self, message: impl Into<String>) -> Self {
+        self.push(FlashLevel::Info, message)
+    }
+
+    pub fn success(self, message: impl Into<String>) -> Self {
+        self.push(FlashLevel::Success, message)
+    }
+
+    pub fn warning(self, message: impl Into<String>) -> Self {
+        self.push(FlashLevel::Warning, message)
+    }
+
+    pub fn error(self, message: impl Into<String>) -> Self {
+        self.push(FlashLevel::Error, message)
+    }
+
+    pub fn push(
+        mut self,
+        level: FlashLevel,
+        message: impl Into<String>,
+    ) -> Self {
+        self.outgoing.push(FlashMessage {
+            level,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// The messages left for this request, which are then cleared.
+    pub fn take(&mut self) -> Vec<FlashMessage> {
+        std::mem::take(&mut self.incoming)
+    }
+
+    /// Takes the messages into `flash_messages`, as read by
+    /// `partials/flash.html`.
+    pub fn insert_into(&mut self, context: &mut tera::Context) {
+        context.insert("flash_messages", &self.take());
+    }
+
+    fn encode(messages: &[FlashMessage]) -> String {
+        let json = serde_json::to_vec(messages)
+            .expect("Flash messages are always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    fn decode(value: &str) -> Option<Vec<FlashMessage>> {
+        let json = URL_SAFE_NO_PAD.decode(value).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Flash
+where
+    S: Send + Sync,
+    Key: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let jar = SignedCookieJar::from_request_parts(parts, state).await?;
+        let incoming = jar
+            .get(FLASH_COOKIE)
+            .and_then(|cookie| Self::decode(cookie.value()))
+            .unwrap_or_default();
+        Ok(Self {
+            jar,
+            incoming,
+            outgoing: Vec::new(),
+        })
+    }
+}
+
+impl IntoResponseParts for Flash {
+    type Error = Infallible;
+
+    fn into_response_parts(
+        self,
+        res: ResponseParts,
+    ) -> Result<ResponseParts, Self::Error> {
+        let Self {
+            jar,
+            mut incoming,
+            outgoing,
+        } = self;
+        incoming.extend(outgoing);
+        let jar = if !incoming.is_empty() {
+            let cookie = Cookie::build((FLASH_COOKIE, Self::encode(&incoming)))
+                .path("/")
+                .secure(true)
+                .http_only(true);
+            jar.add(cookie)
+        } else if jar.get(FLASH_COOKIE).is_some() {
+            jar.remove(Cookie::build(FLASH_COOKIE).path("/"))
+        } else {
+            jar
+        };
+        jar.into_response_parts(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_survive_the_cookie_encoding() {
+        let messages = vec![
+            FlashMessage {
+                level: FlashLevel::Success,
+                message: "Saved; \"quoted\", with commas.".to_string(),
+            },
+            FlashMessage {
+                level: FlashLevel::Warning,
+                message: "Ünïcödé too".to_string(),
+            },
+        ];
+        let encoded = Flash::encode(&messages);
+        assert!(!encoded.contains(';'));
+        assert_eq!(Flash::decode(&encoded), Some(messages));
+    }
+
+    #[test]
+    fn unreadable_cookies_are_ignored() {
+        assert_eq!(Flash::decode("not a flash cookie"), None);
+    }
+
+    #[test]
+    fn levels_are_named_for_templates() {
+        let level = serde_json::to_string(&FlashLevel::Warning).unwrap();
+        assert_eq!(level, r#""warning""#);
+    }
+}
@@ -3,45 +3,125 @@ use crate::models::{HeaderPair, Idempotency};
 
 use super::IdempotencyKey;
 use crate::schema::idempotency::dsl::*;
+use anyhow::Context;
 use axum::body;
 use axum::http::{Response, StatusCode};
+use chrono::Utc;
 use diesel::prelude::*;
+use diesel::sql_types;
 use diesel::SelectableHelper;
 use diesel_async::RunQueryDsl;
 use uuid::Uuid;
 
+pub enum NextAction {
+    StartProcessing,
+    ReturnSavedResponse(Response<body::Body>),
+}
+
+/// Reserves the idempotency key for the current request.
+///
+/// Must run inside a transaction: a concurrent request holding the same key
+/// blocks on the insert until the first one commits its response, and then
+/// replays it.
+#[tracing::instrument(name = "Try processing request", skip(connection, key))]
+pub async fn try_processing(
+    connection: &mut DatabaseConnection,
+    key: &IdempotencyKey,
+    id: Uuid,
+) -> Result<NextAction, anyhow::Error> {
+    let inserted_rows = diesel::insert_into(idempotency)
+        .values((
+            user_id.eq(id),
+            idempotency_key.eq(key.as_ref()),
+            created_at.eq(Utc::now()),
+        ))
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .await
+        .context("Failed to reserve the idempotency key.")?;
+    if inserted_rows > 0 {
+        Ok(NextAction::StartProcessing)
+    } else {
+        let saved_response = get_saved_response(connection, key, id)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "We expected a saved response, we didn't find it"
+                )
+            })?;
+        Ok(NextAction::ReturnSavedResponse(saved_response))
+    }
+}
+
+#[tracing::instrument(name = "Get saved response", skip(connection, key))]
 pub async fn get_saved_response(
     connection: &mut DatabaseConnection,
-    key: IdempotencyKey,
+    key: &IdempotencyKey,
     id: Uuid,
 ) -> Result<Option<Response<body::Body>>, anyhow::Error> {
-    let key: String = String::from(key);
     let saved_response: Option<Idempotency> = idempotency
-        .filter(idempotency_key.eq(key).and(user_id.eq(id)))
+        .filter(idempotency_key.eq(key.as_ref()).and(user_id.eq(id)))
         .select(Idempotency::as_select())
         .first(connection)
         .await
         .optional()?;
-    if let Some(r) = saved_response {
+    if let Some(saved) = saved_response.and_then(|r| r.request) {
         let status_code =
-            StatusCode::from_u16(r.request.response_status_code.try_into()?)?;
+            StatusCode::from_u16(saved.response_status_code.try_into()?)?;
         let mut response = Response::builder().status(status_code);
-        for HeaderPair { name, value } in r.request.response_headers {
+        for HeaderPair { name, value } in saved.response_headers {
             response = response.header(name, value);
         }
         let response: Response<body::Body> =
-            response.body(body::Body::from(r.request.response_body))?;
+            response.body(body::Body::from(saved.response_body))?;
         Ok(Some(response))
     } else {
         Ok(None)
     }
 }
 
+#[tracing::instrument(name = "Save response", skip(connection, key, response))]
 pub async fn save_response(
     connection: &mut DatabaseConnection,
-    key: IdempotencyKey,
+    key: &IdempotencyKey,
     id: Uuid,
     response: Response<body::Body>,
-) -> Result<(), anyhow::Error> {
-    todo!()
+) -> Result<Response<body::Body>, anyhow::Error> {
+    let (response_head, response_body) = response.into_parts();
+    let response_body = body::to_bytes(response_body, usize::MAX)
+        .await
+        .context("Failed to read response body.")?;
+    let (header_names, header_values): (Vec<String>, Vec<Vec<u8>>) =
+        response_head
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (name.as_str().to_owned(), value.as_bytes().to_owned())
+            })
+            .unzip();
+    // diesel-async cannot resolve the oid of `header_pair` while it is nested
+    // inside `http_request`, so the composite is assembled on the database side.
+    diesel::sql_query(
+        "UPDATE idempotency SET request = ROW($3, ARRAY( \
+            SELECT ROW(h.name, h.value)::header_pair \
+            FROM UNNEST($4::TEXT[], $5::BYTEA[]) \
+                WITH ORDINALITY AS h(name, value, position) \
+            ORDER BY h.position \
+        ), $6, $7)::http_request \
+        WHERE user_id = $1 AND idempotency_key = $2",
+    )
+    .bind::<sql_types::Uuid, _>(id)
+    .bind::<sql_types::Text, _>(key.as_ref())
+    .bind::<sql_types::SmallInt, _>(response_head.status.as_u16() as i16)
+    .bind::<sql_types::Array<sql_types::Text>, _>(header_names)
+    .bind::<sql_types::Array<sql_types::Bytea>, _>(header_values)
+    .bind::<sql_types::Bytea, _>(response_body.to_vec())
+    .bind::<sql_types::Text, _>(format!("{:?}", response_head.version))
+    .execute(connection)
+    .await
+    .context("Failed to store the response.")?;
+    Ok(Response::from_parts(
+        response_head,
+        body::Body::from(response_body),
+    ))
 }
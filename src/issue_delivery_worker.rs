@@ -0,0 +1,487 @@
+use crate::{
+    database::{
+        queries::{
+            dead_letter_task, delete_confirmation_email, delete_task,
+            dequeue_batch, dequeue_confirmation_emails, finish_issue_if_done,
+            get_confirmed_recipients, get_issue, record_deliveries,
+            reschedule_confirmation_email, reschedule_task, store_issue_links,
+            PendingConfirmation,
+        },
+        DatabaseConnection, DatabaseConnectionPool,
+    },
+    domain::{
+        SubscriberEmail, SubscriptionToken, TrackingToken, UnsubscribeToken,
+    },
+    email_client::{
+        send::{
+            render_newsletter_email, send_confirmation_email, SendEmailError,
+        },
+        EmailClient, EmailError, NewsletterRecipient,
+    },
+    models::IssueDeliveryQueue,
+    personalization::{
+        IssueTemplates, PersonalizationContext, SubscriberDetails,
+    },
+    startup::HmacSecret,
+    tracking::{find_links, open_pixel_url, rewrite_links},
+};
+use anyhow::Context;
+use chrono::Utc;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Attempts made before a transient failure is moved to the dead letters.
+const MAX_ATTEMPTS: i16 = 6;
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+/// Tasks of the same issue delivered together in one batch.
+const BATCH_SIZE: i64 = 500;
+/// Queued confirmation emails sent per run.
+const CONFIRMATION_BATCH_SIZE: i64 = 50;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+#[tracing::instrument(
+    name = "Execute delivery batch",
+    skip_all,
+    fields(
+        newsletter_issue_id = tracing::field::Empty,
+        batch_size = tracing::field::Empty
+    ),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &DatabaseConnectionPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut connection = crate::database::get_connection(pool.clone())
+        .await
+        .context("Could not get database pool")?;
+    connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                let tasks = dequeue_batch(conn, BATCH_SIZE).await?;
+                let issue_id = match tasks.first() {
+                    Some(task) => task.newsletter_issue_id,
+                    None => return Ok(ExecutionOutcome::EmptyQueue),
+                };
+                tracing::Span::current()
+                    .record(
+                        "newsletter_issue_id",
+                        tracing::field::display(&issue_id),
+                    )
+                    .record("batch_size", tasks.len());
+                let emails: Vec<&str> = tasks
+                    .iter()
+                    .map(|task| task.subscriber_email.as_str())
+                    .collect();
+                let subscribers =
+                    get_confirmed_recipients(conn, &emails).await?;
+                let issue = get_issue(conn, issue_id).await?;
+                let archive_url = match &issue.slug {
+                    Some(slug) => format!("{}/issues/{}", base_url, slug),
+                    None => format!("{}/issues", base_url),
+                };
+                let templates = IssueTemplates::parse(
+                    &issue.text_content,
+                    &issue.html_content,
+                );
+                // Only the links written into the issue are tracked: the
+                // personalized ones, like the unsubscribe link, are left alone.
+                let links = if issue.tracking_enabled {
+                    let urls = find_links(&issue.html_content);
+                    let urls: Vec<&str> =
+                        urls.iter().map(String::as_str).collect();
+                    store_issue_links(conn, issue_id, &urls).await?
+                } else {
+                    HashMap::new()
+                };
+                let personalize = |email: SubscriberEmail,
+                                   subscriber_id: Uuid,
+                                   name: &str|
+                 -> Result<
+                    NewsletterRecipient,
+                    anyhow::Error,
+                > {
+                    let unsubscribe_url =
+                        unsubscribe_url(base_url, subscriber_id, hmac_secret);
+                    let context = PersonalizationContext {
+                        subscriber: SubscriberDetails {
+                            name,
+                            email: email.as_ref(),
+                        },
+                        unsubscribe_url: &unsubscribe_url,
+                        archive_url: &archive_url,
+                    };
+                    let (text, html) = templates
+                        .as_ref()
+                        .map_err(|e| anyhow::anyhow!(e.to_string()))?
+                        .render(&context)
+                        .context("Failed to personalize the issue")?;
+                    let (html, tracking_pixel_url) = if issue.tracking_enabled {
+                        let token = TrackingToken::generate(
+                            issue_id,
+                            subscriber_id,
+                            &hmac_secret.0,
+                        );
+                        (
+                            rewrite_links(&html, &links, base_url, &token),
+                            Some(open_pixel_url(base_url, &token)),
+                        )
+                    } else {
+                        (html, None)
+                    };
+                    let (text_content, html_content) = render_newsletter_email(
+                        &issue.title,
+                        &text,
+                        &html,
+                        Some(&archive_url),
+                        tracking_pixel_url.as_deref(),
+                    )?;
+                    Ok(NewsletterRecipient {
+                        email,
+                        unsubscribe_url,
+                        text_content,
+                        html_content,
+                    })
+                };
+                let mut recipients = Vec::with_capacity(tasks.len());
+                let mut deliverable = Vec::with_capacity(tasks.len());
+                for task in tasks {
+                    let (subscriber_id, name) =
+                        match subscribers.get(&task.subscriber_email) {
+                            Some(subscriber) => subscriber,
+                            None => {
+                                tracing::info!(
+                                    subscriber_email = %task.subscriber_email,
+                                    "Skipping a subscriber who is no longer \
+                                     confirmed."
+                                );
+                                delete_task(
+                                    conn,
+                                    task.newsletter_issue_id,
+                                    &task.subscriber_email,
+                                )
+                                .await?;
+                                continue;
+                            }
+                        };
+                    let email = task.subscriber_email.clone();
+                    let e = match SubscriberEmail::try_from(email) {
+                        Ok(email) => {
+                            match personalize(email, *subscriber_id, name) {
+                                Ok(recipient) => {
+                                    recipients.push(recipient);
+                                    deliverable.push(task);
+                                    continue;
+                                }
+                                Err(e) => {
+                                    tracing::error!(
+                                        error.cause_chain = ?e,
+                                        error.message = %e,
+                                        "Skipping a confirmed subscriber. \
+                                         Their issue could not be rendered."
+                                    );
+                                    format!("{:#}", e)
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!(
+                                error.cause_chain = ?e,
+                                error.message = %e,
+                                "Skipping a confirmed subscriber. \
+                                 Their stored contact details are invalid."
+                            );
+                            e.to_string()
+                        }
+                    };
+                    dead_letter_task(
+                        conn,
+                        task.newsletter_issue_id,
+                        &task.subscriber_email,
+                        task.attempts + 1,
+                        &e,
+                    )
+                    .await?;
+                }
+                let outcomes = email_client
+                    .send_email_batch(&recipients, &issue.title)
+                    .await;
+                let mut delivered = 0;
+                for (task, outcome) in deliverable.iter().zip(outcomes) {
+                    if outcome.is_ok() {
+                        delivered += 1;
+                    }
+                    record_outcome(conn, task, outcome).await?;
+                }
+                record_deliveries(conn, issue_id, delivered).await?;
+                finish_issue_if_done(conn, issue_id).await?;
+                Ok(ExecutionOutcome::TaskCompleted)
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Sends the confirmation emails queued by subscriber imports.
+#[tracing::instrument(
+    name = "Send queued confirmation emails",
+    skip_all,
+    fields(batch_size = tracing::field::Empty),
+    err
+)]
+pub async fn try_send_confirmation_emails(
+    pool: &DatabaseConnectionPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut connection = crate::database::get_connection(pool.clone())
+        .await
+        .context("Could not get database pool")?;
+    connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                let pending =
+                    dequeue_confirmation_emails(conn, CONFIRMATION_BATCH_SIZE)
+                        .await?;
+                if pending.is_empty() {
+                    return Ok(ExecutionOutcome::EmptyQueue);
+                }
+                tracing::Span::current().record("batch_size", pending.len());
+                for confirmation in pending {
+                    send_queued_confirmation(
+                        conn,
+                        email_client,
+                        base_url,
+                        confirmation,
+                    )
+                    .await?;
+                }
+                Ok(ExecutionOutcome::TaskCompleted)
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+async fn send_queued_confirmation(
+    conn: &mut DatabaseConnection,
+    email_client: &EmailClient,
+    base_url: &str,
+    confirmation: PendingConfirmation,
+) -> Result<(), anyhow::Error> {
+    let PendingConfirmation { task, email } = confirmation;
+    let attempts = task.attempts + 1;
+    let details = SubscriberEmail::try_from(email)
+        .map_err(|e| e.to_string())
+        .and_then(|email| {
+            SubscriptionToken::try_from(task.subscription_token.clone())
+                .map(|token| (email, token))
+                .map_err(|e| e.to_string())
+        });
+    let outcome = match details {
+        Ok((email, token)) => {
+            send_confirmation_email(email_client, &email, base_url, &token)
+                .await
+        }
+        Err(e) => {
+            tracing::error!(
+                error.message = %e,
+                "Dropping a confirmation email. Its stored details are \
+                 invalid."
+            );
+            delete_confirmation_email(conn, &task.subscription_token).await?;
+            return Ok(());
+        }
+    };
+    let e = match outcome {
+        Ok(()) => {
+            delete_confirmation_email(conn, &task.subscription_token).await?;
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+    let transient = matches!(
+        &e,
+        SendEmailError::DeliveryError(e) if e.is_transient()
+    );
+    let e = anyhow::Error::from(e);
+    if transient && attempts < MAX_ATTEMPTS {
+        let delay = backoff_with_jitter(attempts);
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            attempts,
+            retry_in_seconds = delay.as_secs(),
+            "Failed to send a queued confirmation email. Retrying later."
+        );
+        reschedule_confirmation_email(
+            conn,
+            &task.subscription_token,
+            attempts,
+            Utc::now()
+                + chrono::Duration::from_std(delay)
+                    .context("Backoff is out of range")?,
+        )
+        .await?;
+    } else {
+        // The subscriber can still ask for a new link from the resend page.
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            attempts,
+            "Failed to send a queued confirmation email. Giving up."
+        );
+        delete_confirmation_email(conn, &task.subscription_token).await?;
+    }
+    Ok(())
+}
+
+fn unsubscribe_url(
+    base_url: &str,
+    subscriber_id: uuid::Uuid,
+    hmac_secret: &HmacSecret,
+) -> String {
+    let token = UnsubscribeToken::generate(subscriber_id, &hmac_secret.0);
+    format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        base_url,
+        token.as_ref()
+    )
+}
+
+async fn record_outcome(
+    conn: &mut DatabaseConnection,
+    task: &IssueDeliveryQueue,
+    outcome: Result<(), EmailError>,
+) -> Result<(), anyhow::Error> {
+    let attempts = task.attempts + 1;
+    let e = match outcome {
+        Ok(()) => {
+            delete_task(conn, task.newsletter_issue_id, &task.subscriber_email)
+                .await?;
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+    let transient = e.is_transient();
+    let e = anyhow::Error::from(e);
+    // Keep the whole chain: the transport's own reason lives in the source.
+    let last_error = format!("{:#}", e);
+    if transient && attempts < MAX_ATTEMPTS {
+        let delay = backoff_with_jitter(attempts);
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            subscriber_email = %task.subscriber_email,
+            attempts,
+            retry_in_seconds = delay.as_secs(),
+            "Failed to deliver issue to a confirmed \
+             subscriber. Retrying later."
+        );
+        reschedule_task(
+            conn,
+            task.newsletter_issue_id,
+            &task.subscriber_email,
+            attempts,
+            &last_error,
+            Utc::now()
+                + chrono::Duration::from_std(delay)
+                    .context("Backoff is out of range")?,
+        )
+        .await?;
+    } else {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            subscriber_email = %task.subscriber_email,
+            attempts,
+            "Failed to deliver issue to a confirmed \
+             subscriber. Giving up."
+        );
+        dead_letter_task(
+            conn,
+            task.newsletter_issue_id,
+            &task.subscriber_email,
+            attempts,
+            &last_error,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Exponential backoff capped at `MAX_BACKOFF`, with the upper half of the
+/// delay randomised so failed deliveries do not retry in lockstep.
+fn backoff_with_jitter(attempts: i16) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let delay = BASE_BACKOFF
+        .saturating_mul(2u32.pow(exponent))
+        .min(MAX_BACKOFF);
+    let half = delay / 2;
+    half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+}
+
+pub async fn run_worker_until_stopped(
+    pool: DatabaseConnectionPool,
+    email_client: Arc<EmailClient>,
+    base_url: String,
+    hmac_secret: HmacSecret,
+) -> Result<(), anyhow::Error> {
+    loop {
+        let confirmations =
+            try_send_confirmation_emails(&pool, &email_client, &base_url).await;
+        let issues =
+            try_execute_task(&pool, &email_client, &base_url, &hmac_secret)
+                .await;
+        match (confirmations, issues) {
+            (Err(_), _) | (_, Err(_)) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            (
+                Ok(ExecutionOutcome::EmptyQueue),
+                Ok(ExecutionOutcome::EmptyQueue),
+            ) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff_with_jitter, BASE_BACKOFF, MAX_BACKOFF};
+
+    #[test]
+    fn first_retry_waits_around_the_base_backoff() {
+        let delay = backoff_with_jitter(1);
+        assert!(delay >= BASE_BACKOFF / 2);
+        assert!(delay <= BASE_BACKOFF);
+    }
+
+    #[test]
+    fn backoff_grows_exponentially() {
+        let delay = backoff_with_jitter(3);
+        assert!(delay >= BASE_BACKOFF * 2);
+        assert!(delay <= BASE_BACKOFF * 4);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        for attempts in [10, 100, i16::MAX] {
+            assert!(backoff_with_jitter(attempts) <= MAX_BACKOFF);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::database::{
+    queries::{get_due_scheduled_issues, publish_issue},
+    DatabaseConnectionPool,
+};
+use anyhow::Context;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use std::time::Duration;
+
+/// How often the scheduler looks for issues that are due.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Queues every scheduled issue whose time has come.
+///
+/// Returns how many issues were published.
+#[tracing::instrument(name = "Publish scheduled issues", skip_all, err)]
+pub async fn publish_due_issues(
+    pool: &DatabaseConnectionPool,
+) -> Result<usize, anyhow::Error> {
+    let mut connection = crate::database::get_connection(pool.clone())
+        .await
+        .context("Could not get database pool")?;
+    connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                let issue_ids = get_due_scheduled_issues(conn).await?;
+                for issue_id in &issue_ids {
+                    let recipients = publish_issue(conn, *issue_id)
+                        .await
+                        .context("Failed to publish a scheduled issue")?;
+                    tracing::info!(
+                        newsletter_issue_id = %issue_id,
+                        recipients,
+                        "Scheduled newsletter issue queued for delivery."
+                    );
+                }
+                Ok(issue_ids.len())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+pub async fn run_scheduler_until_stopped(
+    pool: DatabaseConnectionPool,
+) -> Result<(), anyhow::Error> {
+    loop {
+        // Failures are already logged by `publish_due_issues`.
+        let _ = publish_due_issues(&pool).await;
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
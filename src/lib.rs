@@ -1,15 +1,23 @@
 pub mod authentication;
+pub mod client_ip;
 pub mod configuration;
 pub mod database;
 pub mod domain;
 pub mod email_client;
+pub mod flash;
 pub mod idempotency;
+pub mod issue_delivery_worker;
+pub mod issue_scheduler;
+pub mod markdown;
 pub mod models;
+pub mod personalization;
+pub mod rate_limit;
 pub mod routes;
 pub mod schema;
 pub mod session_state;
 pub mod startup;
 pub mod telemetry;
+pub mod tracking;
 pub mod utils;
 
 use once_cell::sync::Lazy;
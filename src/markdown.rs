@@ -0,0 +1,134 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+fn options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH
+}
+
+/// Renders Markdown to HTML, dropping anything unsafe such as scripts or
+/// inline event handlers.
+pub fn to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(
+        &mut html,
+        Parser::new_ext(markdown, options()),
+    );
+    sanitize_html(&html)
+}
+
+/// Drops anything unsafe, such as scripts or inline event handlers, from
+/// HTML that did not come from Markdown.
+pub fn sanitize_html(html: &str) -> String {
+    ammonia::clean(html)
+}
+
+/// Renders Markdown to the plain-text alternative of an email: markup is
+/// dropped, list items keep a bullet and links keep their address.
+pub fn to_plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut links = Vec::new();
+    for event in Parser::new_ext(markdown, options()) {
+        match event {
+            Event::Start(Tag::List(start)) => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => {
+                text.push('\n')
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url),
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = links.pop() {
+                    text.push_str(&format!(" ({})", url));
+                }
+            }
+            Event::Text(content) | Event::Code(content) => {
+                text.push_str(&content)
+            }
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("----------\n\n"),
+            Event::End(TagEnd::Paragraph) if !lists.is_empty() => {
+                text.push('\n')
+            }
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote
+                | TagEnd::Table,
+            ) => text.push_str("\n\n"),
+            Event::End(TagEnd::TableRow | TagEnd::TableHead) => text.push('\n'),
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            _ => {}
+        }
+    }
+    text.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_html, to_plain_text};
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let html = to_html(
+            "# Hello\n\nSome *emphasis* and a [link](https://example.com).",
+        );
+        assert!(html.contains("<h1>Hello</h1>"));
+        assert!(html.contains("<em>emphasis</em>"));
+        assert!(html.contains("href=\"https://example.com\""));
+    }
+
+    #[test]
+    fn unsafe_html_is_removed() {
+        let html = to_html(
+            "<script>alert(1)</script>\n\n<a href=\"javascript:alert(1)\" \
+             onclick=\"alert(1)\">click</a>",
+        );
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("onclick"));
+    }
+
+    #[test]
+    fn plain_text_drops_markup_and_keeps_links() {
+        let text = to_plain_text(
+            "# Hello\n\nSome **bold** text and a [link](https://example.com).",
+        );
+        assert_eq!(
+            text,
+            "Hello\n\nSome bold text and a link (https://example.com)."
+        );
+    }
+
+    #[test]
+    fn plain_text_keeps_list_markers() {
+        let text =
+            to_plain_text("Items:\n\n- one\n- two\n\n1. first\n2. second");
+        assert_eq!(text, "Items:\n\n- one\n- two\n\n1. first\n2. second");
+    }
+
+    #[test]
+    fn plain_text_indents_nested_lists() {
+        let text = to_plain_text("- one\n  - inner\n- two");
+        assert_eq!(text, "- one\n  - inner\n- two");
+    }
+}
@@ -9,6 +9,11 @@ use diesel::{
 };
 use uuid::Uuid;
 
+use crate::domain::{
+    EmailEventKind, InvitationToken, IssueContent, NewsletterIssueStatus,
+    PasswordResetToken, SubscriberEmail, SubscriptionStatus, UserRole,
+};
+
 #[derive(Insertable, Queryable, Selectable)]
 #[diesel(table_name = crate::schema::subscriptions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -16,7 +21,7 @@ pub struct Subscriptions {
     pub id: Uuid,
     pub email: String,
     pub name: String,
-    pub status: String,
+    pub status: SubscriptionStatus,
     pub subscribed_at: DateTime<Utc>,
 }
 
@@ -27,7 +32,7 @@ impl Subscriptions {
             email,
             name,
             subscribed_at: Utc::now(),
-            status: "pending_confirmation".to_string(),
+            status: SubscriptionStatus::Pending,
         }
     }
 }
@@ -51,6 +56,33 @@ impl SubscriptionTokens {
     }
 }
 
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::subscription_status_changes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubscriptionStatusChanges {
+    pub id: Uuid,
+    pub subscriber_id: Uuid,
+    pub from_status: SubscriptionStatus,
+    pub to_status: SubscriptionStatus,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl SubscriptionStatusChanges {
+    pub fn new(
+        subscriber_id: Uuid,
+        from_status: SubscriptionStatus,
+        to_status: SubscriptionStatus,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            subscriber_id,
+            from_status,
+            to_status,
+            changed_at: Utc::now(),
+        }
+    }
+}
+
 #[derive(Insertable, Queryable, Selectable)]
 #[diesel(table_name = crate::schema::users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -58,14 +90,103 @@ pub struct Users {
     pub user_id: Uuid,
     pub username: String,
     pub password_hash: String,
+    pub role: UserRole,
 }
 
 impl Users {
-    pub fn new(user_id: Uuid, username: &str, password_hash: &str) -> Self {
+    pub fn new(
+        user_id: Uuid,
+        username: &str,
+        password_hash: &str,
+        role: UserRole,
+    ) -> Self {
         Self {
             user_id,
             username: username.to_string(),
             password_hash: password_hash.to_string(),
+            role,
+        }
+    }
+}
+
+/// How long the signup link of an invitation stays valid.
+const INVITATION_VALIDITY_DAYS: i64 = 7;
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::user_invitations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserInvitations {
+    pub token_hash: String,
+    pub email: String,
+    pub role: UserRole,
+    pub invited_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl UserInvitations {
+    pub fn new(
+        token: &InvitationToken,
+        email: &SubscriberEmail,
+        role: UserRole,
+        invited_by: Uuid,
+    ) -> Self {
+        let created_at = Utc::now();
+        Self {
+            token_hash: token.hash(),
+            email: email.as_ref().to_string(),
+            role,
+            invited_by: Some(invited_by),
+            created_at,
+            expires_at: created_at
+                + chrono::Duration::days(INVITATION_VALIDITY_DAYS),
+        }
+    }
+}
+
+/// How long a password reset link stays valid.
+const PASSWORD_RESET_VALIDITY_MINUTES: i64 = 60;
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::password_reset_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PasswordResetTokens {
+    pub token_hash: String,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PasswordResetTokens {
+    pub fn new(token: &PasswordResetToken, user_id: Uuid) -> Self {
+        let created_at = Utc::now();
+        Self {
+            token_hash: token.hash(),
+            user_id,
+            created_at,
+            expires_at: created_at
+                + chrono::Duration::minutes(PASSWORD_RESET_VALIDITY_MINUTES),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecoveryCodes {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl RecoveryCodes {
+    pub fn new(user_id: Uuid, code_hash: &str) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            code_hash: code_hash.to_string(),
+            used_at: None,
         }
     }
 }
@@ -76,10 +197,149 @@ impl Users {
 pub struct Idempotency {
     pub user_id: Uuid,
     pub idempotency_key: String,
-    pub request: HttpRequest,
+    pub request: Option<HttpRequest>,
     pub created_at: DateTime<Utc>,
 }
 
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::newsletter_issues)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewsletterIssues {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub author_id: Option<Uuid>,
+    pub status: NewsletterIssueStatus,
+    pub created_at: DateTime<Utc>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub recipients_count: i32,
+    pub delivered_count: i32,
+    pub slug: Option<String>,
+    pub markdown_content: Option<String>,
+    pub tracking_enabled: bool,
+}
+
+impl NewsletterIssues {
+    /// A new draft; it goes out once it is published or scheduled.
+    pub fn new(
+        author_id: Uuid,
+        title: &str,
+        content: &IssueContent,
+        tracking_enabled: bool,
+    ) -> Self {
+        Self {
+            newsletter_issue_id: Uuid::now_v7(),
+            title: title.to_string(),
+            text_content: content.text().to_string(),
+            html_content: content.html().to_string(),
+            published_at: None,
+            author_id: Some(author_id),
+            status: NewsletterIssueStatus::Draft,
+            created_at: Utc::now(),
+            scheduled_for: None,
+            recipients_count: 0,
+            delivered_count: 0,
+            slug: None,
+            markdown_content: content.markdown().map(str::to_string),
+            tracking_enabled,
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::confirmation_email_queue)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ConfirmationEmailQueue {
+    pub subscription_token: String,
+    pub attempts: i16,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::issue_delivery_queue)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IssueDeliveryQueue {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_email: String,
+    pub attempts: i16,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::issue_delivery_dead_letters)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IssueDeliveryDeadLetters {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_email: String,
+    pub attempts: i16,
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::issue_links)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IssueLinks {
+    pub id: Uuid,
+    pub newsletter_issue_id: Uuid,
+    pub url: String,
+}
+
+impl IssueLinks {
+    pub fn new(newsletter_issue_id: Uuid, url: &str) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            newsletter_issue_id,
+            url: url.to_string(),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::issue_opens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IssueOpens {
+    pub id: Uuid,
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    pub opened_at: DateTime<Utc>,
+}
+
+impl IssueOpens {
+    pub fn new(newsletter_issue_id: Uuid, subscriber_id: Uuid) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            newsletter_issue_id,
+            subscriber_id,
+            opened_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::issue_clicks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IssueClicks {
+    pub id: Uuid,
+    pub link_id: Uuid,
+    pub subscriber_id: Uuid,
+    pub clicked_at: DateTime<Utc>,
+}
+
+impl IssueClicks {
+    pub fn new(link_id: Uuid, subscriber_id: Uuid) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            link_id,
+            subscriber_id,
+            clicked_at: Utc::now(),
+        }
+    }
+}
+
 #[derive(Debug, FromSqlRow, AsExpression, Clone)]
 #[diesel(sql_type = crate::schema::sql_types::HeaderPair)]
 pub struct HeaderPair {
@@ -164,3 +424,39 @@ impl ToSql<crate::schema::sql_types::HttpRequest, Pg> for HttpRequest {
         )
     }
 }
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::email_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EmailEvents {
+    pub id: Uuid,
+    pub subscriber_id: Option<Uuid>,
+    pub email: String,
+    pub kind: EmailEventKind,
+    pub description: String,
+    pub status_changed: bool,
+    pub occurred_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl EmailEvents {
+    pub fn new(
+        subscriber_id: Option<Uuid>,
+        email: &str,
+        kind: EmailEventKind,
+        description: &str,
+        status_changed: bool,
+        occurred_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            subscriber_id,
+            email: email.to_string(),
+            kind,
+            description: description.to_string(),
+            status_changed,
+            occurred_at,
+            received_at: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use tera::{Tera, Value};
+
+use crate::markdown;
+
+const TEXT_TEMPLATE: &str = "issue.txt";
+// The extension turns on autoescaping for the values put in the HTML.
+const HTML_TEMPLATE: &str = "issue.html";
+/// Tera built-ins that read the server's environment or change between
+/// renders, which issue bodies have no business calling. Markup slipped past
+/// autoescaping, with `safe` or `{% raw %}`, is left to `render` to sanitize.
+const DISABLED_FUNCTIONS: [&str; 3] = ["get_env", "now", "get_random"];
+
+/// What newsletter bodies can refer to, as documented on the issue forms:
+///
+/// - `subscriber.name` and `subscriber.email`,
+/// - `unsubscribe_url`, the recipient's one-click unsubscribe link,
+/// - `archive_url`, the issue's page in the public archive.
+#[derive(serde::Serialize)]
+pub struct PersonalizationContext<'a> {
+    pub subscriber: SubscriberDetails<'a>,
+    pub unsubscribe_url: &'a str,
+    pub archive_url: &'a str,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriberDetails<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+}
+
+impl PersonalizationContext<'static> {
+    /// Stand-in values used to validate and preview bodies.
+    pub fn sample() -> Self {
+        Self {
+            subscriber: SubscriberDetails {
+                name: "Ursula Le Guin",
+                email: "ursula_le_guin@gmail.com",
+            },
+            unsubscribe_url: "https://example.com/unsubscribe",
+            archive_url: "https://example.com/issues/sample",
+        }
+    }
+}
+
+impl<'a> PersonalizationContext<'a> {
+    /// Neutral values for readers of the public archive.
+    pub fn public(archive_url: &'a str) -> Self {
+        Self {
+            subscriber: SubscriberDetails {
+                name: "reader",
+                email: "",
+            },
+            unsubscribe_url: "",
+            archive_url,
+        }
+    }
+}
+
+/// The text and HTML bodies of an issue, compiled once and rendered for
+/// every recipient.
+pub struct IssueTemplates(Tera);
+
+impl IssueTemplates {
+    pub fn parse(
+        text_content: &str,
+        html_content: &str,
+    ) -> Result<Self, InvalidIssueTemplate> {
+        let mut tera = Tera::default();
+        for name in DISABLED_FUNCTIONS {
+            tera.register_function(
+                name,
+                move |_: &HashMap<String, Value>| -> tera::Result<Value> {
+                    Err(tera::Error::msg(format!(
+                        "`{}` cannot be used in a newsletter.",
+                        name
+                    )))
+                },
+            );
+        }
+        tera.add_raw_templates([
+            (TEXT_TEMPLATE, text_content),
+            (HTML_TEMPLATE, html_content),
+        ])
+        .map_err(|e| InvalidIssueTemplate::from_tera(&e))?;
+        Ok(Self(tera))
+    }
+
+    /// Fails on anything rendering a real recipient could trip over, such as
+    /// misspelt variables.
+    pub fn validate(
+        text_content: &str,
+        html_content: &str,
+    ) -> Result<(), InvalidIssueTemplate> {
+        Self::parse(text_content, html_content)?
+            .render(&PersonalizationContext::sample())
+            .map_err(|e| InvalidIssueTemplate::from_tera(&e))?;
+        Ok(())
+    }
+
+    /// Returns the text and HTML bodies.
+    ///
+    /// The HTML is sanitized once rendered, as filters like `safe` and
+    /// `{% raw %}` blocks can put any markup in it.
+    pub fn render(
+        &self,
+        context: &PersonalizationContext,
+    ) -> Result<(String, String), tera::Error> {
+        let context = tera::Context::from_serialize(context)?;
+        let html = self.0.render(HTML_TEMPLATE, &context)?;
+        Ok((
+            self.0.render(TEXT_TEMPLATE, &context)?,
+            markdown::sanitize_html(&html),
+        ))
+    }
+}
+
+/// Runs `render` over Markdown containing template tags.
+///
+/// Rendering Markdown escapes quotes and URL-encodes braces, which would
+/// break the tags, so they are swapped for inert placeholders meanwhile.
+pub fn render_markdown_template(
+    markdown: &str,
+    render: impl Fn(&str) -> String,
+) -> String {
+    let mut tags = Vec::new();
+    let mut protected = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some((start, end)) = next_tag(rest) {
+        protected.push_str(&rest[..start]);
+        protected.push_str(&placeholder(tags.len()));
+        tags.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    protected.push_str(rest);
+    let mut rendered = render(&protected);
+    for (index, tag) in tags.iter().enumerate() {
+        rendered = rendered.replace(&placeholder(index), tag);
+    }
+    rendered
+}
+
+/// Finds the first `{{ }}`, `{% %}` or `{# #}` tag.
+fn next_tag(text: &str) -> Option<(usize, usize)> {
+    let start =
+        text.match_indices('{')
+            .map(|(index, _)| index)
+            .find(|&index| {
+                matches!(
+                    text.as_bytes().get(index + 1),
+                    Some(b'{' | b'%' | b'#')
+                )
+            })?;
+    let closing = match text.as_bytes()[start + 1] {
+        b'{' => "}}",
+        b'%' => "%}",
+        _ => "#}",
+    };
+    let end = text[start + 2..].find(closing)? + start + 2 + closing.len();
+    Some((start, end))
+}
+
+fn placeholder(index: usize) -> String {
+    format!("TEMPLATETAG{}TEMPLATETAG", index)
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("The newsletter is not a valid template: {0}")]
+pub struct InvalidIssueTemplate(String);
+
+impl InvalidIssueTemplate {
+    /// Tera keeps the useful part of its messages in the error sources.
+    fn from_tera(e: &tera::Error) -> Self {
+        let mut message = e.to_string();
+        let mut source = std::error::Error::source(e);
+        while let Some(cause) = source {
+            message = format!("{}: {}", message, cause);
+            source = cause.source();
+        }
+        Self(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        render_markdown_template, IssueTemplates, PersonalizationContext,
+    };
+    use crate::markdown;
+    use claims::assert_err;
+
+    #[test]
+    fn bodies_are_rendered_with_the_recipient_details() {
+        let templates = IssueTemplates::parse(
+            "Hi {{ subscriber.name }}",
+            "<p>Hi {{ subscriber.name }}</p>",
+        )
+        .unwrap();
+        let mut context = PersonalizationContext::sample();
+        context.subscriber.name = "<b>Ursula</b>";
+
+        let (text, html) = templates.render(&context).unwrap();
+
+        assert_eq!(text, "Hi <b>Ursula</b>");
+        assert_eq!(html, "<p>Hi &lt;b&gt;Ursula&lt;/b&gt;</p>");
+    }
+
+    #[test]
+    fn markup_let_through_by_the_template_is_sanitized() {
+        let templates = IssueTemplates::parse(
+            "",
+            "{{ \"<script>steal()</script>\" | safe }}\
+             <a href=\"{{ 'javascript:alert(1)' }}\">Hi</a>\
+             {% raw %}<img src=x onerror=\"steal()\">{% endraw %}",
+        )
+        .unwrap();
+
+        let (_, html) =
+            templates.render(&PersonalizationContext::sample()).unwrap();
+
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("onerror"));
+        assert!(html.contains(">Hi</a>"));
+    }
+
+    #[test]
+    fn broken_syntax_is_rejected() {
+        assert_err!(IssueTemplates::validate("Hi {{ subscriber.name", ""));
+        assert_err!(IssueTemplates::validate("", "{% if %}"));
+    }
+
+    #[test]
+    fn unknown_variables_are_rejected() {
+        let e = IssueTemplates::validate("Hi {{ subscriber.nmae }}", "")
+            .unwrap_err();
+        assert!(e.to_string().contains("subscriber.nmae"));
+    }
+
+    #[test]
+    fn functions_reaching_the_server_are_rejected() {
+        for body in [
+            "{{ get_env(name=\"DATABASE_URL\") }}",
+            "{{ now() }}",
+            "{{ get_random(end=10) }}",
+        ] {
+            let e = IssueTemplates::validate(body, "").unwrap_err();
+            assert!(e.to_string().contains("cannot be used in a newsletter"));
+        }
+    }
+
+    #[test]
+    fn template_tags_survive_markdown_rendering() {
+        let html = render_markdown_template(
+            "Hi {{ subscriber.name | default(value=\"you\") }}, \
+             [unsubscribe]({{ unsubscribe_url }})",
+            markdown::to_html,
+        );
+        assert!(html.contains("{{ subscriber.name | default(value=\"you\") }}"));
+        assert!(html.contains("href=\"{{ unsubscribe_url }}\""));
+    }
+
+    #[test]
+    fn unterminated_tags_are_left_alone() {
+        let text =
+            render_markdown_template("Hi {{ name", markdown::to_plain_text);
+        assert_eq!(text, "Hi {{ name");
+    }
+}
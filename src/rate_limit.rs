@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{FromRef, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_sessions_redis_store::fred::prelude::*;
+
+use crate::client_ip::{ClientIp, TrustedProxies};
+use crate::configuration::{RateLimit, RateLimitBackend};
+
+/// Token bucket in a Redis hash, refilled and drawn from atomically.
+/// Returns 0 when the request is allowed, otherwise the milliseconds
+/// until a token is available.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local burst = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(bucket[1]) or burst
+local updated = tonumber(bucket[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - updated) * rate)
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait = math.ceil((1 - tokens) / rate * 1000)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', tostring(now))
+redis.call('EXPIRE', KEYS[1], math.ceil(burst / rate) + 1)
+return wait
+"#;
+
+/// Buckets kept in memory before some are dropped to make room.
+const MAX_MEMORY_BUCKETS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Refills the bucket up to `now`, then takes a token if there is one.
+    fn take(&mut self, limit: RateLimit, now: Instant) -> Option<Duration> {
+        let rate = limit.per_second();
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate)
+            .min(limit.burst as f64);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+
+    fn is_full(&self, limit: RateLimit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.tokens + elapsed.as_secs_f64() * limit.per_second()
+            >= limit.burst as f64
+    }
+}
+
+/// Drops the full buckets, which are no different from new ones. Should
+/// that not be enough, as with many clients at once, the least recently
+/// used half is forgotten too, so memory stays bounded.
+fn make_room(
+    buckets: &mut HashMap<String, Bucket>,
+    limit: RateLimit,
+    now: Instant,
+) {
+    buckets.retain(|_, bucket| !bucket.is_full(limit, now));
+    if buckets.len() < MAX_MEMORY_BUCKETS / 2 {
+        return;
+    }
+    let mut updated: Vec<Instant> =
+        buckets.values().map(|bucket| bucket.updated).collect();
+    let middle = updated.len() / 2;
+    let (_, &mut cutoff, _) = updated.select_nth_unstable(middle);
+    buckets.retain(|_, bucket| bucket.updated > cutoff);
+}
+
+#[derive(Clone)]
+enum RateLimitStore {
+    Memory(Arc<Mutex<HashMap<String, Bucket>>>),
+    Redis(RedisPool),
+}
+
+/// Rate limits one route by client IP, for use with
+/// `middleware::from_fn_with_state` and [`rate_limit`].
+#[derive(Clone)]
+pub struct RateLimiter {
+    scope: &'static str,
+    limit: RateLimit,
+    store: RateLimitStore,
+    trusted_proxies: TrustedProxies,
+}
+
+impl FromRef<RateLimiter> for TrustedProxies {
+    fn from_ref(limiter: &RateLimiter) -> Self {
+        limiter.trusted_proxies.clone()
+    }
+}
+
+/// Shares one store between the limiters of every route.
+#[derive(Clone)]
+pub struct RateLimiters {
+    store: RateLimitStore,
+    trusted_proxies: TrustedProxies,
+}
+
+impl RateLimiters {
+    pub fn new(
+        backend: RateLimitBackend,
+        redis: RedisPool,
+        trusted_proxies: TrustedProxies,
+    ) -> Self {
+        let store = match backend {
+            RateLimitBackend::Memory => RateLimitStore::Memory(Arc::default()),
+            RateLimitBackend::Redis => RateLimitStore::Redis(redis),
+        };
+        Self {
+            store,
+            trusted_proxies,
+        }
+    }
+
+    /// A limiter whose buckets are keyed under `scope`.
+    pub fn limiter(
+        &self,
+        scope: &'static str,
+        limit: RateLimit,
+    ) -> RateLimiter {
+        RateLimiter {
+            scope,
+            limit,
+            store: self.store.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+        }
+    }
+}
+
+impl RateLimiter {
+    /// `Some` with how long to wait when `client` is out of tokens.
+    pub async fn check(
+        &self,
+        client: IpAddr,
+    ) -> Result<Option<Duration>, RedisError> {
+        let key = format!("rate_limit:{}:{}", self.scope, client);
+        match &self.store {
+            RateLimitStore::Memory(buckets) => {
+                let now = Instant::now();
+                let mut buckets = buckets.lock().unwrap();
+                if buckets.len() >= MAX_MEMORY_BUCKETS {
+                    make_room(&mut buckets, self.limit, now);
+                }
+                let bucket = buckets.entry(key).or_insert(Bucket {
+                    tokens: self.limit.burst as f64,
+                    updated: now,
+                });
+                Ok(bucket.take(self.limit, now))
+            }
+            RateLimitStore::Redis(redis) => {
+                let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+                let wait: u64 = redis
+                    .eval(
+                        TOKEN_BUCKET_SCRIPT,
+                        key,
+                        vec![
+                            self.limit.burst.to_string(),
+                            self.limit.per_second().to_string(),
+                            now.to_string(),
+                        ],
+                    )
+                    .await?;
+                Ok((wait > 0).then(|| Duration::from_millis(wait)))
+            }
+        }
+    }
+}
+
+/// Answers with 429 Too Many Requests once the client's bucket is empty.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    ClientIp(client): ClientIp,
+    request: Request,
+    next: Next,
+) -> Response {
+    let retry_after = match limiter.check(client).await {
+        Ok(None) => return next.run(request).await,
+        Ok(Some(retry_after)) => retry_after,
+        Err(e) => {
+            // An unavailable Redis should not take the routes down with it.
+            tracing::error!("Could not check the rate limit. Reason {:?}", e);
+            return next.run(request).await;
+        }
+    };
+    tracing::warn!(
+        scope = limiter.scope,
+        client = %client,
+        "Rate limited request"
+    );
+    let seconds = retry_after.as_secs_f64().ceil() as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, seconds.max(1).to_string())],
+        "Too many requests.",
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        burst: 2,
+        per_minute: 60,
+    };
+
+    fn full_bucket(now: Instant) -> Bucket {
+        Bucket {
+            tokens: LIMIT.burst as f64,
+            updated: now,
+        }
+    }
+
+    #[test]
+    fn a_full_bucket_allows_a_burst() {
+        let now = Instant::now();
+        let mut bucket = full_bucket(now);
+        assert_eq!(bucket.take(LIMIT, now), None);
+        assert_eq!(bucket.take(LIMIT, now), None);
+        assert!(bucket.take(LIMIT, now).is_some());
+    }
+
+    #[test]
+    fn an_empty_bucket_says_when_a_token_is_available() {
+        let now = Instant::now();
+        let mut bucket = full_bucket(now);
+        bucket.tokens = 0.0;
+        let wait = bucket.take(LIMIT, now).unwrap();
+        assert_eq!(wait.as_secs(), 1);
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let now = Instant::now();
+        let mut bucket = full_bucket(now);
+        bucket.tokens = 0.0;
+        let later = now + Duration::from_secs(1);
+        assert_eq!(bucket.take(LIMIT, later), None);
+        assert!(bucket.take(LIMIT, later).is_some());
+    }
+
+    #[test]
+    fn the_memory_store_stays_bounded_under_many_clients() {
+        let now = Instant::now();
+        let mut buckets: HashMap<String, Bucket> = (0..MAX_MEMORY_BUCKETS)
+            .map(|client| {
+                let bucket = Bucket {
+                    tokens: 0.0,
+                    updated: now + Duration::from_millis(client as u64),
+                };
+                (client.to_string(), bucket)
+            })
+            .collect();
+
+        make_room(&mut buckets, LIMIT, now);
+
+        assert!(buckets.len() <= MAX_MEMORY_BUCKETS / 2);
+        let newest = (MAX_MEMORY_BUCKETS - 1).to_string();
+        assert!(buckets.contains_key(&newest));
+    }
+
+    #[test]
+    fn refills_stop_at_the_burst_size() {
+        let now = Instant::now();
+        let mut bucket = full_bucket(now);
+        let later = now + Duration::from_secs(60);
+        assert!(bucket.is_full(LIMIT, later));
+        for _ in 0..LIMIT.burst {
+            assert_eq!(bucket.take(LIMIT, later), None);
+        }
+        assert!(bucket.take(LIMIT, later).is_some());
+    }
+}
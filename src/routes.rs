@@ -1,13 +1,27 @@
 mod admin;
+mod archive;
 mod confirm_subscriptions;
 mod health_check;
 mod home;
 mod login;
+mod resend_confirmation;
+mod setup;
+mod signup;
 mod subscriptions;
+mod tracking;
+mod unsubscribe;
+mod webhooks;
 
 pub use admin::*;
+pub use archive::*;
 pub use confirm_subscriptions::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use resend_confirmation::*;
+pub use setup::*;
+pub use signup::*;
 pub use subscriptions::*;
+pub use tracking::*;
+pub use unsubscribe::*;
+pub use webhooks::*;
@@ -1,8 +1,20 @@
 mod dashboard;
+mod email_events;
+mod failed_deliveries;
+mod issues;
 mod logout;
 mod newsletters;
 mod reset_password;
+mod subscribers;
+mod two_factor;
+mod users;
 pub use dashboard::admin_dashboard;
+pub use email_events::*;
+pub use failed_deliveries::*;
+pub use issues::*;
 pub use logout::logout;
 pub use newsletters::*;
 pub use reset_password::*;
+pub use subscribers::*;
+pub use two_factor::*;
+pub use users::*;
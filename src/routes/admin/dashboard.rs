@@ -4,17 +4,37 @@ use axum::{
     extract::State,
     http::{Response, StatusCode},
     response::{IntoResponse, Redirect},
+    Extension,
 };
 use tracing::instrument;
 
 use crate::{
-    database::queries::get_username, session_state::TypedSession,
-    startup::ApplicationState, TEMPLATES,
+    database::queries::{get_issue_engagement, get_username},
+    domain::UserRole,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
 };
 
+/// Published issues whose engagement is shown on the dashboard.
+const ENGAGEMENT_ISSUES: i64 = 10;
+
+#[derive(serde::Serialize)]
+struct EngagementRow {
+    id: String,
+    title: String,
+    tracking_enabled: bool,
+    delivered: i32,
+    opened: i64,
+    clicked: i64,
+    open_rate: String,
+    click_rate: String,
+}
+
 #[instrument(skip(app_state, session))]
 pub async fn admin_dashboard(
     State(app_state): State<ApplicationState>,
+    Extension(role): Extension<UserRole>,
     session: TypedSession,
 ) -> Result<Response<Body>, DashboardError> {
     let mut connection =
@@ -30,8 +50,31 @@ pub async fn admin_dashboard(
     } else {
         return Ok(Redirect::to("/login").into_response());
     };
-    let mut tera_context = tera::Context::new();
+    let engagement: Vec<EngagementRow> =
+        get_issue_engagement(&mut connection, ENGAGEMENT_ISSUES)
+            .await
+            .context("Could not get issue engagement")?
+            .into_iter()
+            .map(|issue| EngagementRow {
+                id: issue.issue_id.to_string(),
+                title: issue.title,
+                tracking_enabled: issue.tracking_enabled,
+                delivered: issue.delivered,
+                opened: issue.opened,
+                clicked: issue.clicked,
+                open_rate: rate(issue.opened, issue.delivered),
+                click_rate: rate(issue.clicked, issue.delivered),
+            })
+            .collect();
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
     tera_context.insert("user_name", &username.to_string());
+    tera_context.insert("role", &role);
+    tera_context.insert("can_edit", &role.includes(UserRole::Editor));
+    tera_context.insert("can_manage_users", &role.includes(UserRole::Owner));
+    tera_context.insert("engagement", &engagement);
     let html_body = TEMPLATES
         .render("pages/admin_dashboard.html", &tera_context)
         .context("Could not render login page.")?;
@@ -41,6 +84,14 @@ pub async fn admin_dashboard(
         .context("Could not render html.")?)
 }
 
+/// Share of the delivered emails, as a percentage.
+fn rate(count: i64, delivered: i32) -> String {
+    if delivered <= 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", count as f64 * 100.0 / delivered as f64)
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum DashboardError {
     #[error("Something went wrong.")]
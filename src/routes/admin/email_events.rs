@@ -0,0 +1,84 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+use tracing::instrument;
+
+use crate::{
+    database::queries::get_email_events, models::EmailEvents,
+    startup::ApplicationState, TEMPLATES,
+};
+
+/// Events shown on the bounces and complaints page.
+const EMAIL_EVENTS_SHOWN: i64 = 200;
+
+#[derive(serde::Serialize)]
+pub(super) struct EmailEventRow {
+    subscriber_id: Option<String>,
+    email: String,
+    kind: String,
+    description: String,
+    status_changed: bool,
+    occurred_at: String,
+}
+
+impl From<EmailEvents> for EmailEventRow {
+    fn from(event: EmailEvents) -> Self {
+        Self {
+            subscriber_id: event.subscriber_id.map(|id| id.to_string()),
+            email: event.email,
+            kind: event.kind.to_string(),
+            description: event.description,
+            status_changed: event.status_changed,
+            occurred_at: event.occurred_at.to_rfc2822(),
+        }
+    }
+}
+
+#[instrument(name = "Requesting email events page", skip(app_state))]
+pub async fn email_events_page(
+    State(app_state): State<ApplicationState>,
+) -> Result<Response<Body>, EmailEventsError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let events: Vec<EmailEventRow> =
+        get_email_events(&mut connection, EMAIL_EVENTS_SHOWN)
+            .await
+            .context("Could not get email events")?
+            .into_iter()
+            .map(EmailEventRow::from)
+            .collect();
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("events", &events);
+    let html_body = TEMPLATES
+        .render("pages/email_events.html", &tera_context)
+        .context("Could not render email events page.")?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/html")
+        .body(html_body.into())
+        .context("Could not create response.")?)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EmailEventsError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for EmailEventsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
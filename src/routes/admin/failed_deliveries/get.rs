@@ -0,0 +1,90 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+use tracing::instrument;
+
+use crate::{
+    database::queries::get_dead_letters, flash::Flash,
+    session_state::TypedSession, startup::ApplicationState, TEMPLATES,
+};
+
+#[derive(serde::Serialize)]
+struct FailedDeliveryRow {
+    newsletter_issue_id: String,
+    issue_title: String,
+    subscriber_email: String,
+    attempts: i16,
+    last_error: String,
+    failed_at: String,
+}
+
+#[instrument(
+    name = "Requesting failed deliveries page",
+    skip(app_state, session, flash)
+)]
+pub async fn failed_deliveries_page(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), FailedDeliveriesError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let failed_deliveries: Vec<FailedDeliveryRow> =
+        get_dead_letters(&mut connection)
+            .await
+            .context("Could not get failed deliveries")?
+            .into_iter()
+            .map(|failed| FailedDeliveryRow {
+                newsletter_issue_id: failed
+                    .dead_letter
+                    .newsletter_issue_id
+                    .to_string(),
+                issue_title: failed.issue_title,
+                subscriber_email: failed.dead_letter.subscriber_email,
+                attempts: failed.dead_letter.attempts,
+                last_error: failed.dead_letter.last_error,
+                failed_at: failed.dead_letter.failed_at.to_rfc2822(),
+            })
+            .collect();
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("failed_deliveries", &failed_deliveries);
+    let html_body = TEMPLATES
+        .render("pages/failed_deliveries.html", &tera_context)
+        .context("Could not render failed deliveries page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FailedDeliveriesError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for FailedDeliveriesError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    database::queries::requeue_dead_letter, flash::Flash,
+    startup::ApplicationState,
+};
+
+#[derive(serde::Deserialize)]
+pub struct RequeueForm {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+}
+
+#[instrument(name = "Requeue failed delivery", skip(app_state, flash, form))]
+pub async fn requeue_failed_delivery(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Form(form): Form<RequeueForm>,
+) -> Result<(Flash, Redirect), RequeueDeliveryError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let requeued = requeue_dead_letter(
+        &mut connection,
+        form.newsletter_issue_id,
+        &form.subscriber_email,
+    )
+    .await
+    .context("Failed to requeue delivery")?;
+    let flash = if requeued {
+        flash.success(format!(
+            "Delivery to {} has been requeued.",
+            form.subscriber_email
+        ))
+    } else {
+        flash.warning(format!(
+            "There is no failed delivery to {}.",
+            form.subscriber_email
+        ))
+    };
+    Ok((flash, Redirect::to("/admin/deliveries/failed")))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RequeueDeliveryError {
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for RequeueDeliveryError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+mod actions;
+mod edit;
+mod history;
+
+pub use actions::*;
+pub use edit::*;
+pub use history::*;
+
+use axum::{
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::domain::{InvalidIssueContent, IssueContent};
+
+#[derive(serde::Deserialize)]
+pub struct IssueForm {
+    title: String,
+    #[serde(default)]
+    content_markdown: String,
+    #[serde(default)]
+    content_text: String,
+    #[serde(default)]
+    content_html: String,
+    #[serde(default)]
+    track_engagement: Option<String>,
+}
+
+impl IssueForm {
+    fn content(&self) -> Result<IssueContent, InvalidIssueContent> {
+        IssueContent::parse(
+            &self.content_markdown,
+            &self.content_text,
+            &self.content_html,
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum IssuesError {
+    #[error("There is no such newsletter issue.")]
+    UnknownIssue,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for IssuesError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnknownIssue => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("There is no such newsletter issue.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+    Extension, Form,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{IssueForm, IssuesError};
+use crate::{
+    authentication::UserId,
+    database::queries::{
+        insert_newsletter_issue, publish_issue, schedule_issue,
+        update_issue_content, IssueChangeError,
+    },
+    flash::Flash,
+    startup::ApplicationState,
+};
+
+#[derive(serde::Deserialize)]
+pub struct ScheduleForm {
+    scheduled_for: String,
+}
+
+#[instrument(
+    name = "Create newsletter draft",
+    skip(app_state, flash, form),
+    fields(user_id = %*user_id)
+)]
+pub async fn create_issue(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Extension(user_id): Extension<UserId>,
+    Form(form): Form<IssueForm>,
+) -> Result<(Flash, Redirect), IssuesError> {
+    if form.title.trim().is_empty() {
+        return Ok((
+            flash.error("The title cannot be empty."),
+            Redirect::to("/admin/issues/new"),
+        ));
+    }
+    let content = match form.content() {
+        Ok(content) => content,
+        Err(e) => {
+            return Ok((
+                flash.error(e.to_string()),
+                Redirect::to("/admin/issues/new"),
+            ))
+        }
+    };
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let issue_id = insert_newsletter_issue(
+        &mut connection,
+        *user_id,
+        &form.title,
+        &content,
+        form.track_engagement.is_some(),
+    )
+    .await
+    .context("Failed to store the draft")?;
+    Ok((
+        flash.success("The draft has been saved."),
+        Redirect::to(&issue_path(issue_id)),
+    ))
+}
+
+#[instrument(name = "Update newsletter draft", skip(app_state, flash, form))]
+pub async fn update_issue(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(issue_id): Path<Uuid>,
+    Form(form): Form<IssueForm>,
+) -> Result<(Flash, Redirect), IssuesError> {
+    if form.title.trim().is_empty() {
+        return Ok((
+            flash.error("The title cannot be empty."),
+            Redirect::to(&issue_path(issue_id)),
+        ));
+    }
+    let content = match form.content() {
+        Ok(content) => content,
+        Err(e) => {
+            return Ok((
+                flash.error(e.to_string()),
+                Redirect::to(&issue_path(issue_id)),
+            ))
+        }
+    };
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let outcome = update_issue_content(
+        &mut connection,
+        issue_id,
+        &form.title,
+        &content,
+        form.track_engagement.is_some(),
+    )
+    .await;
+    redirect_with_outcome(outcome, issue_id, "The draft has been saved.", flash)
+}
+
+#[instrument(name = "Schedule newsletter issue", skip(app_state, flash, form))]
+pub async fn schedule_issue_delivery(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(issue_id): Path<Uuid>,
+    Form(form): Form<ScheduleForm>,
+) -> Result<(Flash, Redirect), IssuesError> {
+    let scheduled_for = match parse_schedule(&form.scheduled_for) {
+        Some(at) if at > Utc::now() => at,
+        _ => {
+            return Ok((
+                flash.error("The delivery time must be a date in the future."),
+                Redirect::to(&issue_path(issue_id)),
+            ))
+        }
+    };
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let outcome =
+        schedule_issue(&mut connection, issue_id, Some(scheduled_for)).await;
+    let message =
+        format!("The issue will be sent on {}.", scheduled_for.to_rfc2822());
+    redirect_with_outcome(outcome, issue_id, &message, flash)
+}
+
+#[instrument(name = "Unschedule newsletter issue", skip(app_state, flash))]
+pub async fn unschedule_issue_delivery(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(issue_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), IssuesError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let outcome = schedule_issue(&mut connection, issue_id, None).await;
+    redirect_with_outcome(
+        outcome,
+        issue_id,
+        "The issue is a draft again.",
+        flash,
+    )
+}
+
+#[instrument(name = "Publish newsletter draft", skip(app_state, flash))]
+pub async fn publish_draft(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(issue_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), IssuesError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    match publish_issue(&mut connection, issue_id).await {
+        Ok(_) => Ok((
+            flash.success(
+                "The newsletter issue has been accepted - \
+                emails will go out shortly.",
+            ),
+            Redirect::to("/admin/issues"),
+        )),
+        Err(e) => redirect_with_outcome(Err(e), issue_id, "", flash),
+    }
+}
+
+/// Accepts RFC 3339 timestamps as well as the `YYYY-MM-DDTHH:MM` value of a
+/// `datetime-local` input, which is read as UTC.
+fn parse_schedule(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+                .map(|at| at.and_utc())
+        })
+        .ok()
+}
+
+fn issue_path(issue_id: Uuid) -> String {
+    format!("/admin/issues/{}", issue_id)
+}
+
+fn redirect_with_outcome(
+    outcome: Result<(), IssueChangeError>,
+    issue_id: Uuid,
+    success: &str,
+    flash: Flash,
+) -> Result<(Flash, Redirect), IssuesError> {
+    let flash = match outcome {
+        Ok(()) => flash.success(success),
+        Err(IssueChangeError::UnknownIssue) => {
+            return Err(IssuesError::UnknownIssue)
+        }
+        Err(e @ IssueChangeError::NotEditable(_))
+        | Err(e @ IssueChangeError::InvalidTransition(_)) => {
+            flash.error(e.to_string())
+        }
+        Err(e) => {
+            return Err(anyhow!(e)
+                .context("Failed to change the newsletter issue")
+                .into())
+        }
+    };
+    Ok((flash, Redirect::to(&issue_path(issue_id))))
+}
@@ -0,0 +1,94 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{Response, StatusCode},
+};
+use diesel::OptionalExtension;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::IssuesError;
+use crate::{
+    database::queries::get_issue, flash::Flash, markdown,
+    session_state::TypedSession, startup::ApplicationState, TEMPLATES,
+};
+
+#[instrument(name = "Requesting new issue page", skip(session, flash))]
+pub async fn new_issue_form(
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), IssuesError> {
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("editable", &true);
+    render_issue_page(flash, tera_context)
+}
+
+#[instrument(name = "Requesting issue page", skip(app_state, session, flash))]
+pub async fn issue_page(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+    Path(issue_id): Path<Uuid>,
+) -> Result<(Flash, Response<Body>), IssuesError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let issue = get_issue(&mut connection, issue_id)
+        .await
+        .optional()
+        .context("Could not get newsletter issue")?
+        .ok_or(IssuesError::UnknownIssue)?;
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("issue_id", &issue.newsletter_issue_id.to_string());
+    tera_context.insert("title", &issue.title);
+    // Versions rendered from the Markdown are left out of the override fields
+    // so that they follow later edits of the Markdown.
+    let (content_text, content_html) = match &issue.markdown_content {
+        Some(markdown) => (
+            Some(&issue.text_content)
+                .filter(|text| **text != markdown::to_plain_text(markdown)),
+            Some(&issue.html_content)
+                .filter(|html| **html != markdown::to_html(markdown)),
+        ),
+        None => (Some(&issue.text_content), Some(&issue.html_content)),
+    };
+    tera_context.insert("content_markdown", &issue.markdown_content);
+    tera_context.insert("content_text", &content_text);
+    tera_context.insert("content_html", &content_html);
+    tera_context.insert("plain_text", &issue.text_content);
+    tera_context.insert("tracking_enabled", &issue.tracking_enabled);
+    tera_context.insert("status", &issue.status.to_string());
+    tera_context.insert("editable", &issue.status.is_editable());
+    tera_context.insert(
+        "scheduled_for",
+        &issue.scheduled_for.map(|at| at.to_rfc2822()),
+    );
+    render_issue_page(flash, tera_context)
+}
+
+fn render_issue_page(
+    flash: Flash,
+    tera_context: tera::Context,
+) -> Result<(Flash, Response<Body>), IssuesError> {
+    let html_body = TEMPLATES
+        .render("pages/issue.html", &tera_context)
+        .context("Could not render issue page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
@@ -0,0 +1,76 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+};
+use tracing::instrument;
+
+use super::IssuesError;
+use crate::{
+    database::queries::get_issue_history, flash::Flash,
+    startup::ApplicationState, TEMPLATES,
+};
+
+#[derive(serde::Serialize)]
+struct IssueRow {
+    id: String,
+    title: String,
+    status: String,
+    editable: bool,
+    author: String,
+    created_at: String,
+    scheduled_for: Option<String>,
+    published_at: Option<String>,
+    recipients: i32,
+    delivered: i32,
+    pending: i64,
+    failed: i64,
+}
+
+#[instrument(name = "Requesting issue history page", skip(app_state, flash))]
+pub async fn issues_page(
+    State(app_state): State<ApplicationState>,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), IssuesError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let issues: Vec<IssueRow> = get_issue_history(&mut connection)
+        .await
+        .context("Could not get issue history")?
+        .into_iter()
+        .map(|summary| IssueRow {
+            id: summary.issue.newsletter_issue_id.to_string(),
+            title: summary.issue.title,
+            status: summary.issue.status.to_string(),
+            editable: summary.issue.status.is_editable(),
+            author: summary.author.unwrap_or_else(|| "unknown".to_string()),
+            created_at: summary.issue.created_at.to_rfc2822(),
+            scheduled_for: summary
+                .issue
+                .scheduled_for
+                .map(|at| at.to_rfc2822()),
+            published_at: summary.issue.published_at.map(|at| at.to_rfc2822()),
+            recipients: summary.issue.recipients_count,
+            delivered: summary.issue.delivered_count,
+            pending: summary.pending,
+            failed: summary.failed,
+        })
+        .collect();
+    let mut tera_context = tera::Context::new();
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("issues", &issues);
+    let html_body = TEMPLATES
+        .render("pages/issues.html", &tera_context)
+        .context("Could not render issue history page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
@@ -1,31 +1,29 @@
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use axum::{
     http::{Response, StatusCode},
     response::{IntoResponse, Redirect},
 };
-use axum_extra::extract::SignedCookieJar;
 use tracing::instrument;
 
-use crate::{session_state::TypedSession, utils::redirect_with_flash};
+use crate::{flash::Flash, session_state::TypedSession};
 
-#[instrument(name = "logout requested", skip(jar, session))]
+#[instrument(name = "logout requested", skip(flash, session))]
 pub async fn logout(
-    jar: SignedCookieJar,
+    flash: Flash,
     session: TypedSession,
-) -> Result<(SignedCookieJar, Redirect), LogoutError> {
+) -> Result<(Flash, Redirect), LogoutError> {
     if session
         .get_user_id()
         .await
         .context("Faild to get session data.")?
         .is_none()
     {
-        Ok((jar, Redirect::to("/admin/dashboard")))
+        Ok((flash, Redirect::to("/admin/dashboard")))
     } else {
         session.logout().await.context("Failed to logout.")?;
-        Ok(redirect_with_flash(
-            "/login",
-            anyhow!("You have successfully logged out."),
-            jar,
+        Ok((
+            flash.success("You have successfully logged out."),
+            Redirect::to("/login"),
         ))
     }
 }
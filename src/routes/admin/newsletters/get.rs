@@ -4,24 +4,26 @@ use axum::{
     http::{Response, StatusCode},
     response::IntoResponse,
 };
-use axum_extra::extract::SignedCookieJar;
 use tracing::instrument;
 
-use crate::{utils::get_flash_error, TEMPLATES};
+use crate::{flash::Flash, session_state::TypedSession, TEMPLATES};
 
-#[instrument(name = "Requesting newsletters page", skip(jar))]
+#[instrument(name = "Requesting newsletters page", skip(session, flash))]
 pub async fn newsletters_form(
-    jar: SignedCookieJar,
-) -> Result<(SignedCookieJar, Response<Body>), NewsletterFormError> {
-    let mut tera_context = tera::Context::new();
-    let (jar, message) = get_flash_error(jar);
-    tera_context.insert("message", &message);
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), NewsletterFormError> {
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
     tera_context.insert("idempotency_key", &uuid::Uuid::now_v7());
     let html_body = TEMPLATES
         .render("pages/newsletters.html", &tera_context)
         .context("Could not render login page.")?;
     Ok((
-        jar,
+        flash,
         Response::builder()
             .status(StatusCode::OK)
             .header(axum::http::header::CONTENT_TYPE, "text/html")
@@ -1,11 +1,12 @@
 use crate::{
     authentication::UserId,
-    database::queries::get_confirmed_subscribers,
-    idempotency::{get_saved_response, IdempotencyKey},
+    database::queries::{insert_newsletter_issue, publish_issue},
+    domain::IssueContent,
+    flash::Flash,
+    idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
     startup::ApplicationState,
-    utils::redirect_with_flash,
 };
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use axum::{
     async_trait,
     body::Body,
@@ -17,38 +18,56 @@ use axum::{
     response::{IntoResponse, Redirect},
     Extension,
 };
-use axum_extra::extract::SignedCookieJar;
-use cookie::{Cookie, Key};
-pub enum PublishNewsletterResponses {
-    SavedResponse(Response<Body>),
-    Redirect(Redirect),
-}
+use cookie::Key;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
 
 #[derive(serde::Deserialize)]
 pub struct NewsletterForm {
     title: String,
+    #[serde(default)]
+    content_markdown: String,
+    #[serde(default)]
     content_text: String,
+    #[serde(default)]
     content_html: String,
+    #[serde(default)]
+    track_engagement: Option<String>,
     idempotency_key: String,
 }
 #[tracing::instrument(
     name = "Publish a newsletter issue",
-    skip(app_state, form),
+    skip(app_state, flash, form),
     fields( user_id=tracing::field::Empty)
 )]
 pub async fn publish_newsletter(
     State(app_state): State<ApplicationState>,
-    jar: SignedCookieJar,
+    flash: Flash,
     Extension(valid_id): Extension<UserId>,
     Form(form): Form<NewsletterForm>,
-) -> Result<(SignedCookieJar, PublishNewsletterResponses), PublishNewsletterError>
-{
+) -> Result<Response<Body>, PublishNewsletterError> {
     let NewsletterForm {
         title,
+        content_markdown,
         content_text,
         content_html,
+        track_engagement,
         idempotency_key,
     } = form;
+    let content = match IssueContent::parse(
+        &content_markdown,
+        &content_text,
+        &content_html,
+    ) {
+        Ok(content) => content,
+        Err(e) => {
+            return Ok((
+                flash.error(e.to_string()),
+                Redirect::to("/admin/newsletters"),
+            )
+                .into_response())
+        }
+    };
     let idempotency_key: IdempotencyKey = idempotency_key
         .try_into()
         .context("Failed to parse idempotency key")?;
@@ -56,61 +75,50 @@ pub async fn publish_newsletter(
         crate::database::get_connection(app_state.database_pool)
             .await
             .context("Could not get database pool")?;
-    if let Some(saved_response) =
-        get_saved_response(&mut connection, idempotency_key, *valid_id)
-            .await
-            .context("Failed to retrieve saved responses")?
-    {
-        let cookie =
-            Cookie::build(("_flash", "The newsletter has been published"))
-                .path("/")
-                .secure(true);
-        let jar = jar.add(cookie);
-        return Ok((
-            jar,
-            PublishNewsletterResponses::SavedResponse(saved_response),
-        ));
-    }
     tracing::Span::current()
         .record("user_id", &tracing::field::display(&valid_id));
-    let subscribers = get_confirmed_subscribers(&mut connection)
-        .await
-        .context("Could not get confirmed subscribers")?;
-    let email_client = app_state.email_client;
-    for subscriber in subscribers {
-        match subscriber {
-            Ok(valid_subscriber) => {
-                email_client
-                    .send_email(
-                        &valid_subscriber.confirmed_email,
-                        &content_text,
-                        &content_html,
-                        &title,
-                    )
+    connection
+        .transaction::<_, PublishNewsletterError, _>(|conn| {
+            async move {
+                match try_processing(conn, &idempotency_key, *valid_id)
                     .await
-                    .with_context(|| {
-                        format!(
-                            "Failed to send newsletter issue to {}",
-                            valid_subscriber.confirmed_email
-                        )
-                    })?;
-            }
-            Err(error) => {
-                tracing::warn!(
-                    error.cause_chain = ?error,
-                    "Skipping a confirmed subscriber. \
-                     Their stored contact details are invalid."
-                );
+                    .context("Failed to reserve the idempotency key")?
+                {
+                    NextAction::StartProcessing => {}
+                    NextAction::ReturnSavedResponse(saved_response) => {
+                        return Ok(saved_response);
+                    }
+                }
+                let issue_id = insert_newsletter_issue(
+                    conn,
+                    *valid_id,
+                    &title,
+                    &content,
+                    track_engagement.is_some(),
+                )
+                .await
+                .context("Failed to store newsletter issue details")?;
+                publish_issue(conn, issue_id)
+                    .await
+                    .context("Failed to enqueue delivery tasks")?;
+                tracing::info!("Newsletter issue queued for delivery.");
+                let response = (
+                    flash.success(
+                        "The newsletter issue has been accepted - \
+                        emails will go out shortly.",
+                    ),
+                    Redirect::to("/admin/newsletters"),
+                )
+                    .into_response();
+                let response =
+                    save_response(conn, &idempotency_key, *valid_id, response)
+                        .await
+                        .context("Failed to save the response")?;
+                Ok(response)
             }
-        }
-    }
-    tracing::info!("Email delivered to subscribers.");
-    let (new_jar, redirect) = redirect_with_flash(
-        "/admin/newsletters",
-        anyhow!("Newsletter delivered successfully"),
-        jar,
-    );
-    Ok((new_jar, PublishNewsletterResponses::Redirect(redirect)))
+            .scope_boxed()
+        })
+        .await
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -119,6 +127,8 @@ pub enum PublishNewsletterError {
     AuthError(#[source] anyhow::Error),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
+    #[error("Unkown database error.")]
+    DatabaseError(#[from] diesel::result::Error),
 }
 
 impl IntoResponse for PublishNewsletterError {
@@ -126,7 +136,8 @@ impl IntoResponse for PublishNewsletterError {
         //TODO: there has to be a better way to handle errors.
         tracing::error!("{} Reason {:?}", self, self);
         match self {
-            PublishNewsletterError::UnexpectedError(_) => Response::builder()
+            PublishNewsletterError::UnexpectedError(_)
+            | PublishNewsletterError::DatabaseError(_) => Response::builder()
                 .status(StatusCode::INTERNAL_SERVER_ERROR)
                 .body("Something went wrong.".into())
                 .unwrap(),
@@ -142,15 +153,6 @@ impl IntoResponse for PublishNewsletterError {
     }
 }
 
-impl IntoResponse for PublishNewsletterResponses {
-    fn into_response(self) -> axum::response::Response<Body> {
-        match self {
-            PublishNewsletterResponses::SavedResponse(res) => res,
-            PublishNewsletterResponses::Redirect(res) => res.into_response(),
-        }
-    }
-}
-
 pub struct Form<T>(pub T);
 
 #[async_trait]
@@ -160,24 +162,23 @@ where
     S: Send + Sync,
     Key: FromRef<S>,
 {
-    type Rejection = (SignedCookieJar, Redirect);
+    type Rejection = (Flash, Redirect);
 
     async fn from_request(
         req: Request,
         state: &S,
     ) -> Result<Self, Self::Rejection> {
         let (mut parts, body) = req.into_parts();
-        let jar = SignedCookieJar::from_request_parts(&mut parts, state)
+        let flash = Flash::from_request_parts(&mut parts, state)
             .await
-            .expect("Failed to extract the cookie jar.");
+            .expect("Failed to extract the flash messages.");
         let req = Request::from_parts(parts, body);
 
         match axum::Form::<T>::from_request(req, state).await {
             Ok(value) => Ok(Self(value.0)),
-            Err(_) => Err(redirect_with_flash(
-                "/admin/newsletters",
-                anyhow!("Invalid newsletter body."),
-                jar,
+            Err(_) => Err((
+                flash.error("Invalid newsletter body."),
+                Redirect::to("/admin/newsletters"),
             )),
         }
     }
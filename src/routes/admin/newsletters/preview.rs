@@ -0,0 +1,86 @@
+use anyhow::Context;
+use axum::{
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    Form, Json,
+};
+use tracing::instrument;
+
+use crate::{
+    domain::{InvalidIssueContent, IssueContent},
+    email_client::send::render_newsletter_email,
+    personalization::{
+        InvalidIssueTemplate, IssueTemplates, PersonalizationContext,
+    },
+};
+
+#[derive(serde::Deserialize)]
+pub struct PreviewForm {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content_markdown: String,
+    #[serde(default)]
+    content_text: String,
+    #[serde(default)]
+    content_html: String,
+}
+
+/// The email as subscribers will receive it.
+#[derive(serde::Serialize)]
+pub struct NewsletterPreview {
+    text: String,
+    html: String,
+}
+
+#[instrument(name = "Preview newsletter", skip(form))]
+pub async fn preview_newsletter(
+    Form(form): Form<PreviewForm>,
+) -> Result<Json<NewsletterPreview>, PreviewError> {
+    let content = IssueContent::parse(
+        &form.content_markdown,
+        &form.content_text,
+        &form.content_html,
+    )?;
+    let context = PersonalizationContext::sample();
+    let (text, html) = IssueTemplates::parse(content.text(), content.html())?
+        .render(&context)
+        .context("Could not personalize the newsletter.")?;
+    let (text, html) = render_newsletter_email(
+        &form.title,
+        &text,
+        &html,
+        Some(context.archive_url),
+        None,
+    )
+    .context("Could not render the newsletter email.")?;
+    Ok(Json(NewsletterPreview { text, html }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PreviewError {
+    #[error(transparent)]
+    InvalidContent(#[from] InvalidIssueContent),
+    #[error(transparent)]
+    InvalidTemplate(#[from] InvalidIssueTemplate),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for PreviewError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::InvalidContent(_) | Self::InvalidTemplate(_) => {
+                Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(self.to_string().into())
+                    .unwrap()
+            }
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
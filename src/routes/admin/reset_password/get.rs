@@ -4,23 +4,25 @@ use axum::{
     http::{Response, StatusCode},
     response::IntoResponse,
 };
-use axum_extra::extract::SignedCookieJar;
 use tracing::instrument;
 
-use crate::{utils::get_flash_error, TEMPLATES};
+use crate::{flash::Flash, session_state::TypedSession, TEMPLATES};
 
-#[instrument(name = "Requesting reset_password page", skip(jar))]
+#[instrument(name = "Requesting reset_password page", skip(session, flash))]
 pub async fn reset_password_form(
-    jar: SignedCookieJar,
-) -> Result<(SignedCookieJar, Response<Body>), PasswordFormError> {
-    let (jar, html_error) = get_flash_error(jar);
-    let mut tera_context = tera::Context::new();
-    tera_context.insert("error", &html_error);
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), PasswordFormError> {
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
     let html_body = TEMPLATES
         .render("pages/reset_password.html", &tera_context)
         .context("Could not render login page.")?;
     Ok((
-        jar,
+        flash,
         Response::builder()
             .status(StatusCode::OK)
             .header(axum::http::header::CONTENT_TYPE, "text/html")
@@ -1,11 +1,10 @@
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use axum::{
     extract::State,
     http::{Response, StatusCode},
     response::{IntoResponse, Redirect},
     Extension, Form,
 };
-use axum_extra::extract::SignedCookieJar;
 use secrecy::{ExposeSecret, Secret};
 use tracing::instrument;
 
@@ -13,8 +12,8 @@ use crate::{
     authentication::{validate_credentials, AuthError, Credentials, UserId},
     database::queries::get_username,
     domain::Password,
+    flash::Flash,
     startup::ApplicationState,
-    utils::redirect_with_flash,
 };
 
 #[derive(serde::Deserialize)]
@@ -23,13 +22,13 @@ pub struct FormData {
     new_password: Secret<String>,
     new_password_check: Secret<String>,
 }
-#[instrument(skip(app_state, form, jar))]
+#[instrument(skip(app_state, form, flash))]
 pub async fn change_pasword(
     State(app_state): State<ApplicationState>,
-    jar: SignedCookieJar,
+    flash: Flash,
     Extension(user_id): Extension<UserId>,
     Form(form): Form<FormData>,
-) -> Result<(SignedCookieJar, Redirect), PasswordResetError> {
+) -> Result<(Flash, Redirect), PasswordResetError> {
     let mut connection =
         crate::database::get_connection(app_state.database_pool)
             .await
@@ -39,23 +38,21 @@ pub async fn change_pasword(
         {
             Ok(pass) => pass,
             Err(err) => {
-                return Ok(redirect_with_flash(
-                    "/admin/password",
-                    anyhow!(err),
-                    jar,
+                return Ok((
+                    flash.error(err.to_string()),
+                    Redirect::to("/admin/password"),
                 ))
             }
         };
     if form.new_password_check.expose_secret()
         != form.new_password.expose_secret()
     {
-        return Ok(redirect_with_flash(
-            "/admin/password",
-            anyhow!(
+        return Ok((
+            flash.error(
                 "You entered two different new passwords - \
-                the field values must match."
+                the field values must match.",
             ),
-            jar,
+            Redirect::to("/admin/password"),
         ));
     }
     let username = get_username(&mut connection, *user_id)
@@ -66,10 +63,9 @@ pub async fn change_pasword(
     ) {
         Ok(pass) => pass,
         Err(_) => {
-            return Ok(redirect_with_flash(
-                "/admin/password",
-                anyhow!("The current password is incorrect."),
-                jar,
+            return Ok((
+                flash.error("The current password is incorrect."),
+                Redirect::to("/admin/password"),
             ));
         }
     };
@@ -79,10 +75,9 @@ pub async fn change_pasword(
             AuthError::UnexpectedError(_) => {
                 Err(PasswordResetError::AuthError(e.into()))
             }
-            AuthError::InvalidCredentials(_) => Ok(redirect_with_flash(
-                "/admin/password",
-                anyhow!("The current password is incorrect."),
-                jar,
+            AuthError::InvalidCredentials(_) => Ok((
+                flash.error("The current password is incorrect."),
+                Redirect::to("/admin/password"),
             )),
         };
     } else {
@@ -92,10 +87,9 @@ pub async fn change_pasword(
             &mut connection,
         )
         .await?;
-        Ok(redirect_with_flash(
-            "/admin/password",
-            anyhow!("Your password has been changed."),
-            jar,
+        Ok((
+            flash.success("Your password has been changed."),
+            Redirect::to("/admin/password"),
         ))
     }
 }
@@ -0,0 +1,87 @@
+mod actions;
+mod detail;
+mod export;
+mod import;
+mod list;
+
+pub use actions::*;
+pub use detail::*;
+pub use export::*;
+pub use import::*;
+pub use list::*;
+
+use axum::{
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::{database::queries::SubscriberFilter, domain::SubscriptionStatus};
+
+/// Filters shared by the subscriber list and the CSV export.
+#[derive(serde::Deserialize, Default)]
+pub struct SubscribersQuery {
+    page: Option<i64>,
+    search: Option<String>,
+    status: Option<String>,
+}
+
+impl SubscribersQuery {
+    fn filter(&self) -> Result<SubscriberFilter, SubscribersError> {
+        let search = self
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|search| !search.is_empty())
+            .map(str::to_string);
+        let status = match self.status.as_deref() {
+            None | Some("") => None,
+            Some(status) => Some(
+                SubscriptionStatus::try_from(status)
+                    .map_err(SubscribersError::InvalidFilter)?,
+            ),
+        };
+        Ok(SubscriberFilter { search, status })
+    }
+
+    /// Query string for the same filters, without the page.
+    fn filter_query_string(&self) -> String {
+        let mut parameters = Vec::new();
+        if let Some(search) = self.search.as_deref().filter(|s| !s.is_empty()) {
+            parameters.push(format!("search={}", urlencoding::encode(search)));
+        }
+        if let Some(status) = self.status.as_deref().filter(|s| !s.is_empty()) {
+            parameters.push(format!("status={}", urlencoding::encode(status)));
+        }
+        parameters.join("&")
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SubscribersError {
+    #[error("{0}")]
+    InvalidFilter(String),
+    #[error("There is no such subscriber.")]
+    UnknownSubscriber,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for SubscribersError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::InvalidFilter(message) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(message.into())
+                .unwrap(),
+            Self::UnknownSubscriber => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("There is no such subscriber.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::SubscribersError;
+use crate::{
+    database::queries::{
+        change_subscriber_status, delete_subscriber, StatusChangeError,
+    },
+    domain::SubscriptionStatus,
+    flash::Flash,
+    startup::ApplicationState,
+};
+
+#[instrument(name = "Manually confirm subscriber", skip(app_state, flash))]
+pub async fn confirm_subscriber(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), SubscribersError> {
+    set_status(
+        app_state,
+        flash,
+        subscriber_id,
+        SubscriptionStatus::Confirmed,
+    )
+    .await
+}
+
+#[instrument(name = "Manually unsubscribe subscriber", skip(app_state, flash))]
+pub async fn unsubscribe_subscriber(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), SubscribersError> {
+    set_status(
+        app_state,
+        flash,
+        subscriber_id,
+        SubscriptionStatus::Unsubscribed,
+    )
+    .await
+}
+
+async fn set_status(
+    app_state: ApplicationState,
+    flash: Flash,
+    subscriber_id: Uuid,
+    status: SubscriptionStatus,
+) -> Result<(Flash, Redirect), SubscribersError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let flash =
+        match change_subscriber_status(&mut connection, &subscriber_id, status)
+            .await
+        {
+            Ok(()) => {
+                flash.success(format!("The subscriber is now {}.", status))
+            }
+            Err(StatusChangeError::UnknownSubscriber) => {
+                return Err(SubscribersError::UnknownSubscriber)
+            }
+            Err(e @ StatusChangeError::InvalidTransition(_))
+            | Err(e @ StatusChangeError::ConcurrentChange) => {
+                flash.error(e.to_string())
+            }
+            Err(e) => {
+                return Err(anyhow!(e)
+                    .context("Failed to change the subscriber status")
+                    .into())
+            }
+        };
+    Ok((
+        flash,
+        Redirect::to(&format!("/admin/subscribers/{}", subscriber_id)),
+    ))
+}
+
+#[instrument(name = "Delete subscriber", skip(app_state, flash))]
+pub async fn remove_subscriber(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), SubscribersError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let deleted = delete_subscriber(&mut connection, &subscriber_id)
+        .await
+        .context("Failed to delete subscriber")?;
+    if !deleted {
+        return Err(SubscribersError::UnknownSubscriber);
+    }
+    Ok((
+        flash.success("The subscriber has been deleted."),
+        Redirect::to("/admin/subscribers"),
+    ))
+}
@@ -0,0 +1,96 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{Response, StatusCode},
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::SubscribersError;
+use crate::{
+    database::queries::{
+        get_latest_token_generated_at, get_status_history, get_subscriber,
+        get_subscriber_email_events,
+    },
+    flash::Flash,
+    routes::admin::email_events::EmailEventRow,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+#[derive(serde::Serialize)]
+struct StatusChangeRow {
+    from_status: String,
+    to_status: String,
+    changed_at: String,
+}
+
+#[instrument(
+    name = "Requesting subscriber page",
+    skip(app_state, session, flash)
+)]
+pub async fn subscriber_page(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<(Flash, Response<Body>), SubscribersError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let subscriber = get_subscriber(&mut connection, &subscriber_id)
+        .await
+        .context("Could not get subscriber")?
+        .ok_or(SubscribersError::UnknownSubscriber)?;
+    let history: Vec<StatusChangeRow> =
+        get_status_history(&mut connection, &subscriber_id)
+            .await
+            .context("Could not get status history")?
+            .into_iter()
+            .map(|change| StatusChangeRow {
+                from_status: change.from_status.to_string(),
+                to_status: change.to_status.to_string(),
+                changed_at: change.changed_at.to_rfc2822(),
+            })
+            .collect();
+    let events: Vec<EmailEventRow> =
+        get_subscriber_email_events(&mut connection, &subscriber_id)
+            .await
+            .context("Could not get email events")?
+            .into_iter()
+            .map(EmailEventRow::from)
+            .collect();
+    let confirmation_sent_at =
+        get_latest_token_generated_at(&mut connection, &subscriber_id)
+            .await
+            .context("Could not get the latest confirmation email")?
+            .map(|sent_at| sent_at.to_rfc2822());
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("id", &subscriber.id.to_string());
+    tera_context.insert("email", &subscriber.email);
+    tera_context.insert("name", &subscriber.name);
+    tera_context.insert("status", &subscriber.status.to_string());
+    tera_context
+        .insert("subscribed_at", &subscriber.subscribed_at.to_rfc2822());
+    tera_context.insert("confirmation_sent_at", &confirmation_sent_at);
+    tera_context.insert("history", &history);
+    tera_context.insert("events", &events);
+    let html_body = TEMPLATES
+        .render("pages/subscriber.html", &tera_context)
+        .context("Could not render subscriber page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
@@ -0,0 +1,155 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+};
+use futures_util::{stream, TryStreamExt};
+use tracing::instrument;
+
+use super::{SubscribersError, SubscribersQuery};
+use crate::{
+    database::{
+        queries::{
+            search_subscribers_after, SubscriberCursor, SubscriberFilter,
+        },
+        DatabaseConnection,
+    },
+    models::Subscriptions,
+    startup::ApplicationState,
+};
+
+/// Subscribers read from the database for each chunk of the export.
+const EXPORT_CHUNK_SIZE: i64 = 500;
+
+struct ExportScan {
+    connection: DatabaseConnection,
+    filter: SubscriberFilter,
+    cursor: Option<SubscriberCursor>,
+    finished: bool,
+}
+
+/// Exports every subscriber matching the filters, ignoring pagination.
+/// The CSV is streamed one chunk of subscribers at a time.
+#[instrument(name = "Exporting subscribers", skip(app_state, query))]
+pub async fn export_subscribers(
+    State(app_state): State<ApplicationState>,
+    Query(query): Query<SubscribersQuery>,
+) -> Result<Response<Body>, SubscribersError> {
+    let filter = query.filter()?;
+    let connection = crate::database::get_connection(app_state.database_pool)
+        .await
+        .context("Could not get database pool")?;
+    let scan = ExportScan {
+        connection,
+        filter,
+        cursor: None,
+        finished: false,
+    };
+    let rows = stream::try_unfold(scan, |mut scan| async move {
+        if scan.finished {
+            return Ok(None);
+        }
+        let subscribers = search_subscribers_after(
+            &mut scan.connection,
+            &scan.filter,
+            scan.cursor,
+            EXPORT_CHUNK_SIZE,
+        )
+        .await
+        .context("Could not get subscribers")?;
+        // Only the first chunk starts without a cursor.
+        let chunk = csv_chunk(&subscribers, scan.cursor.is_none())?;
+        scan.finished = (subscribers.len() as i64) < EXPORT_CHUNK_SIZE;
+        scan.cursor = subscribers.last().map(SubscriberCursor::from);
+        Ok::<_, anyhow::Error>(Some((chunk, scan)))
+    })
+    .inspect_err(|e| tracing::error!("{} Reason {:?}", e, e));
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(
+            axum::http::header::CONTENT_DISPOSITION,
+            r#"attachment; filename="subscribers.csv""#,
+        )
+        .body(Body::from_stream(rows))
+        .context("Could not create response.")?)
+}
+
+fn csv_chunk(
+    subscribers: &[Subscriptions],
+    header: bool,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if header {
+        writer
+            .write_record(["id", "email", "name", "status", "subscribed_at"])
+            .context("Could not write CSV header")?;
+    }
+    for subscriber in subscribers {
+        writer
+            .write_record([
+                subscriber.id.to_string(),
+                escape_formula(&subscriber.email),
+                escape_formula(&subscriber.name),
+                subscriber.status.to_string(),
+                subscriber.subscribed_at.to_rfc3339(),
+            ])
+            .context("Could not write CSV record")?;
+    }
+    writer.into_inner().context("Could not finish CSV export")
+}
+
+/// Spreadsheets run cells starting with these characters as formulas.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Quotes values subscribers typed themselves, so opening the export in a
+/// spreadsheet shows them as text instead of running them.
+fn escape_formula(value: &str) -> String {
+    if value.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_that_look_like_formulas_are_quoted() {
+        let subscribers: Vec<Subscriptions> = [
+            "=HYPERLINK(\"http://evil.example\")",
+            "+1",
+            "-1",
+            "@SUM(A1)",
+        ]
+        .into_iter()
+        .map(|name| Subscriptions::new("ana@example.com".into(), name.into()))
+        .collect();
+
+        let csv =
+            String::from_utf8(csv_chunk(&subscribers, false).unwrap()).unwrap();
+
+        let names: Vec<&str> = csv
+            .lines()
+            .map(|line| line.split(',').nth(2).unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "\"'=HYPERLINK(\"\"http://evil.example\"\")\"",
+                "'+1",
+                "'-1",
+                "'@SUM(A1)"
+            ]
+        );
+    }
+
+    #[test]
+    fn ordinary_names_are_left_alone() {
+        assert_eq!(escape_formula("Ana Souza"), "Ana Souza");
+        assert_eq!(escape_formula("Ana=Souza"), "Ana=Souza");
+    }
+}
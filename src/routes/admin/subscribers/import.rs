@@ -0,0 +1,327 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{multipart::MultipartError, Multipart, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use std::collections::HashSet;
+use tracing::instrument;
+
+use crate::{
+    database::queries::{
+        change_subscriber_status, enqueue_confirmation_email,
+        get_existing_emails, insert_subscriber, store_token,
+    },
+    domain::{NewSubscriber, SubscriptionStatus, SubscriptionToken},
+    routes::Subscriber,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+/// Rows inserted per transaction.
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/// What happens to imported subscribers once they are stored.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportConfirmation {
+    /// Send the usual confirmation email, as `/subscriptions` does. The
+    /// emails are queued for the delivery worker.
+    #[default]
+    SendEmail,
+    /// The list was already confirmed elsewhere.
+    PreConfirmed,
+    /// Store them as pending without contacting anyone.
+    LeavePending,
+}
+
+#[derive(serde::Serialize, Default)]
+pub struct ImportReport {
+    accepted: usize,
+    duplicates: usize,
+    rejected: usize,
+    rows: Vec<ImportRowReport>,
+}
+
+#[derive(serde::Serialize)]
+struct ImportRowReport {
+    row: usize,
+    email: String,
+    outcome: RowOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum RowOutcome {
+    Accepted,
+    Duplicate,
+    Rejected,
+}
+
+#[instrument(name = "Requesting subscriber import page", skip(session))]
+pub async fn import_subscribers_form(
+    session: TypedSession,
+) -> Result<Response<Body>, ImportError> {
+    let tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    let html_body = TEMPLATES
+        .render("pages/import_subscribers.html", &tera_context)
+        .context("Could not render import page.")?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/html")
+        .body(html_body.into())
+        .context("Could not create response.")?)
+}
+
+/// Imports a CSV (with `email` and `name` columns) or JSON (an array of
+/// `{"email", "name"}` objects) upload sent as the `file` field.
+#[instrument(name = "Importing subscribers", skip(app_state, multipart))]
+pub async fn import_subscribers(
+    State(app_state): State<ApplicationState>,
+    mut multipart: Multipart,
+) -> Result<Json<ImportReport>, ImportError> {
+    let mut upload = None;
+    let mut confirmation = ImportConfirmation::default();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(ImportError::from_multipart)?
+    {
+        match field.name() {
+            Some("file") => {
+                let is_json = field
+                    .content_type()
+                    .is_some_and(|content_type| content_type.contains("json"))
+                    || field
+                        .file_name()
+                        .is_some_and(|name| name.ends_with(".json"));
+                let bytes =
+                    field.bytes().await.map_err(ImportError::from_multipart)?;
+                upload = Some((is_json, bytes));
+            }
+            Some("confirmation") => {
+                let value =
+                    field.text().await.map_err(ImportError::from_multipart)?;
+                confirmation =
+                    serde_json::from_value(value.into()).map_err(|_| {
+                        ImportError::InvalidUpload(
+                            "Unknown confirmation option.".to_string(),
+                        )
+                    })?;
+            }
+            _ => {}
+        }
+    }
+    let (is_json, bytes) = upload.ok_or_else(|| {
+        ImportError::InvalidUpload("The upload has no file field.".to_string())
+    })?;
+    let rows = if is_json {
+        parse_json(&bytes)?
+    } else {
+        parse_csv(&bytes)?
+    };
+
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool.clone())
+            .await
+            .context("Could not get database pool")?;
+    let mut report = ImportReport::default();
+    let mut candidates = Vec::new();
+    for (row, parsed) in rows {
+        let email = parsed
+            .as_ref()
+            .map(|subscriber| subscriber.email.clone())
+            .unwrap_or_default();
+        let subscriber = parsed.and_then(|subscriber| {
+            NewSubscriber::try_from(subscriber).map_err(|e| e.to_string())
+        });
+        match subscriber {
+            Ok(subscriber) => candidates.push((row, subscriber)),
+            Err(reason) => {
+                report.push(row, email, RowOutcome::Rejected, Some(reason))
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for batch in candidates.chunks(IMPORT_BATCH_SIZE) {
+        let emails: Vec<&str> = batch
+            .iter()
+            .map(|(_, subscriber)| subscriber.email.as_ref())
+            .collect();
+        let existing = get_existing_emails(&mut connection, &emails)
+            .await
+            .context("Failed to look up existing subscribers")?;
+        let mut new_subscribers = Vec::with_capacity(batch.len());
+        for (row, subscriber) in batch {
+            let email = subscriber.email.as_ref();
+            if existing.contains(email) || !seen.insert(email.to_string()) {
+                report.push(*row, email.into(), RowOutcome::Duplicate, None);
+            } else {
+                new_subscribers.push((*row, subscriber));
+            }
+        }
+        connection
+            .transaction::<_, anyhow::Error, _>(|conn| {
+                let new_subscribers = &new_subscribers;
+                async move {
+                    for (_, subscriber) in new_subscribers {
+                        let subscriber_id = insert_subscriber(conn, subscriber)
+                            .await
+                            .context("Failed to insert subscriber.")?;
+                        match confirmation {
+                            ImportConfirmation::PreConfirmed => {
+                                change_subscriber_status(
+                                    conn,
+                                    &subscriber_id,
+                                    SubscriptionStatus::Confirmed,
+                                )
+                                .await
+                                .context("Failed to confirm subscriber.")?;
+                            }
+                            ImportConfirmation::SendEmail => {
+                                let token = SubscriptionToken::generate();
+                                store_token(conn, &token, &subscriber_id)
+                                    .await
+                                    .context("Failed to store token.")?;
+                                enqueue_confirmation_email(conn, &token)
+                                    .await
+                                    .context(
+                                        "Failed to queue confirmation email.",
+                                    )?;
+                            }
+                            ImportConfirmation::LeavePending => {}
+                        }
+                    }
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await?;
+        for (row, subscriber) in new_subscribers {
+            report.push(
+                row,
+                subscriber.email.as_ref().into(),
+                RowOutcome::Accepted,
+                None,
+            );
+        }
+    }
+    report.rows.sort_by_key(|row| row.row);
+    tracing::info!(
+        accepted = report.accepted,
+        duplicates = report.duplicates,
+        rejected = report.rejected,
+        "Subscriber import finished."
+    );
+    Ok(Json(report))
+}
+
+impl ImportReport {
+    fn push(
+        &mut self,
+        row: usize,
+        email: String,
+        outcome: RowOutcome,
+        reason: Option<String>,
+    ) {
+        match outcome {
+            RowOutcome::Accepted => self.accepted += 1,
+            RowOutcome::Duplicate => self.duplicates += 1,
+            RowOutcome::Rejected => self.rejected += 1,
+        }
+        self.rows.push(ImportRowReport {
+            row,
+            email,
+            outcome,
+            reason,
+        });
+    }
+}
+
+type ParsedRows = Vec<(usize, Result<Subscriber, String>)>;
+
+fn parse_csv(bytes: &[u8]) -> Result<ParsedRows, ImportError> {
+    let mut reader = csv::Reader::from_reader(bytes);
+    let headers = reader
+        .headers()
+        .map_err(|e| ImportError::InvalidUpload(e.to_string()))?;
+    if !["email", "name"]
+        .iter()
+        .all(|column| headers.iter().any(|header| header == *column))
+    {
+        return Err(ImportError::InvalidUpload(
+            "The CSV header must have email and name columns.".to_string(),
+        ));
+    }
+    Ok(reader
+        .deserialize::<Subscriber>()
+        .enumerate()
+        .map(|(index, row)| (index + 1, row.map_err(|e| e.to_string())))
+        .collect())
+}
+
+fn parse_json(bytes: &[u8]) -> Result<ParsedRows, ImportError> {
+    let values: Vec<serde_json::Value> = serde_json::from_slice(bytes)
+        .map_err(|e| ImportError::InvalidUpload(e.to_string()))?;
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            (
+                index + 1,
+                serde_json::from_value(value).map_err(|e| e.to_string()),
+            )
+        })
+        .collect())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("{0}")]
+    InvalidUpload(String),
+    #[error("The upload is larger than imports allow.")]
+    TooLarge,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ImportError {
+    fn from_multipart(e: MultipartError) -> Self {
+        if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            Self::TooLarge
+        } else {
+            Self::InvalidUpload(e.body_text())
+        }
+    }
+}
+
+impl IntoResponse for ImportError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::InvalidUpload(message) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(message.into())
+                .unwrap(),
+            Self::TooLarge => Response::builder()
+                .status(StatusCode::PAYLOAD_TOO_LARGE)
+                .body(self.to_string().into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
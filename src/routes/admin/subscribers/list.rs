@@ -0,0 +1,103 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+};
+use tracing::instrument;
+
+use super::{SubscribersError, SubscribersQuery};
+use crate::{
+    database::queries::{count_subscribers, search_subscribers},
+    domain::SubscriptionStatus,
+    flash::Flash,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+const PAGE_SIZE: i64 = 50;
+
+#[derive(serde::Serialize)]
+struct SubscriberRow {
+    id: String,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: String,
+}
+
+#[instrument(
+    name = "Requesting subscribers page",
+    skip(app_state, flash, query)
+)]
+pub async fn subscribers_page(
+    State(app_state): State<ApplicationState>,
+    mut flash: Flash,
+    Query(query): Query<SubscribersQuery>,
+) -> Result<(Flash, Response<Body>), SubscribersError> {
+    let filter = query.filter()?;
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let total = count_subscribers(&mut connection, &filter)
+        .await
+        .context("Could not count subscribers")?;
+    let total_pages = ((total + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, total_pages);
+    let subscribers: Vec<SubscriberRow> = search_subscribers(
+        &mut connection,
+        &filter,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE,
+    )
+    .await
+    .context("Could not get subscribers")?
+    .into_iter()
+    .map(|subscriber| SubscriberRow {
+        id: subscriber.id.to_string(),
+        email: subscriber.email,
+        name: subscriber.name,
+        status: subscriber.status.to_string(),
+        subscribed_at: subscriber.subscribed_at.to_rfc2822(),
+    })
+    .collect();
+    let filter_query = query.filter_query_string();
+    let page_link = |page: i64| {
+        if filter_query.is_empty() {
+            format!("/admin/subscribers?page={}", page)
+        } else {
+            format!("/admin/subscribers?{}&page={}", filter_query, page)
+        }
+    };
+    let mut tera_context = tera::Context::new();
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("subscribers", &subscribers);
+    tera_context.insert("total", &total);
+    tera_context.insert("page", &page);
+    tera_context.insert("total_pages", &total_pages);
+    tera_context
+        .insert("previous_page", &(page > 1).then(|| page_link(page - 1)));
+    tera_context.insert(
+        "next_page",
+        &(page < total_pages).then(|| page_link(page + 1)),
+    );
+    tera_context.insert("search", &query.search.as_deref().unwrap_or(""));
+    tera_context.insert("status", &query.status.as_deref().unwrap_or(""));
+    tera_context.insert("statuses", &SubscriptionStatus::ALL);
+    tera_context.insert(
+        "export_link",
+        &format!("/admin/subscribers/export?{}", filter_query),
+    );
+    let html_body = TEMPLATES
+        .render("pages/subscribers.html", &tera_context)
+        .context("Could not render subscribers page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use secrecy::{ExposeSecret, Secret};
+use tracing::instrument;
+
+use crate::{
+    authentication::{
+        encrypt_totp_secret, generate_recovery_codes, hash_recovery_codes,
+        validate_credentials, AuthError, Credentials, UserId,
+    },
+    database::queries::{
+        get_totp_secret, get_unused_recovery_codes, get_username,
+        record_totp_step, remove_two_factor, store_two_factor,
+    },
+    domain::{Password, TotpSecret},
+    flash::Flash,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+/// Shown by authenticator apps next to the account name.
+const TOTP_ISSUER: &str = "reingma's newsletter";
+
+/// Shows the two-factor status, or the secret to enroll while it is off.
+#[instrument(
+    name = "Requesting two-factor settings",
+    skip(app_state, session, flash)
+)]
+pub async fn two_factor_settings(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+    Extension(user_id): Extension<UserId>,
+) -> Result<(Flash, Response<Body>), TwoFactorError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    let enabled = get_totp_secret(&mut connection, *user_id)
+        .await
+        .context("Could not get the TOTP secret")?
+        .is_some();
+    tera_context.insert("enabled", &enabled);
+    if enabled {
+        let remaining = get_unused_recovery_codes(&mut connection, *user_id)
+            .await
+            .context("Could not get the recovery codes")?
+            .len();
+        tera_context.insert("remaining_recovery_codes", &remaining);
+    } else {
+        // The same secret is offered until it is confirmed.
+        let secret = match session
+            .get_totp_enrollment()
+            .await
+            .context("Could not read the pending enrollment")?
+            .and_then(|secret| TotpSecret::from_base32(&secret).ok())
+        {
+            Some(secret) => secret,
+            None => {
+                let secret = TotpSecret::generate();
+                session
+                    .insert_totp_enrollment(secret.to_base32())
+                    .await
+                    .context("Could not store the pending enrollment")?;
+                secret
+            }
+        };
+        let username = get_username(&mut connection, *user_id).await?;
+        tera_context.insert("secret", &secret.to_base32());
+        tera_context
+            .insert("otpauth_uri", &secret.otpauth_uri(TOTP_ISSUER, &username));
+    }
+    flash.insert_into(&mut tera_context);
+    let html_body = TEMPLATES
+        .render("pages/two_factor.html", &tera_context)
+        .context("Could not render two-factor page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct EnableTwoFactorForm {
+    code: String,
+}
+
+/// Confirms the enrolled secret with a first code, answering with the
+/// recovery codes. They are shown this once only.
+#[instrument(
+    name = "Enable two-factor authentication",
+    skip(app_state, session, flash, form)
+)]
+pub async fn enable_two_factor(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    flash: Flash,
+    Extension(user_id): Extension<UserId>,
+    Form(form): Form<EnableTwoFactorForm>,
+) -> Result<Response<Body>, TwoFactorError> {
+    let Some(secret) = session
+        .get_totp_enrollment()
+        .await
+        .context("Could not read the pending enrollment")?
+        .and_then(|secret| TotpSecret::from_base32(&secret).ok())
+    else {
+        return Ok((
+            flash.warning("Please scan the new secret and try again."),
+            Redirect::to("/admin/two-factor"),
+        )
+            .into_response());
+    };
+    let Some(step) = secret.verify(&form.code, chrono::Utc::now().timestamp())
+    else {
+        return Ok((
+            flash.error("Invalid authentication code."),
+            Redirect::to("/admin/two-factor"),
+        )
+            .into_response());
+    };
+    let encrypted =
+        encrypt_totp_secret(&secret, *user_id, &app_state.hmac_secret.0)?;
+    let codes = generate_recovery_codes();
+    let hashed = hash_recovery_codes(*user_id, codes.clone()).await?;
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    store_two_factor(&mut connection, *user_id, &encrypted, &hashed)
+        .await
+        .context("Could not store the TOTP secret")?;
+    // The confirmation code cannot be used to log in again.
+    record_totp_step(&mut connection, *user_id, step)
+        .await
+        .context("Could not record the TOTP step")?;
+    session
+        .remove_totp_enrollment()
+        .await
+        .context("Could not clear the pending enrollment")?;
+    let codes: Vec<&str> = codes
+        .iter()
+        .map(|code| code.expose_secret().as_str())
+        .collect();
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("codes", &codes);
+    let html_body = TEMPLATES
+        .render("pages/recovery_codes.html", &tera_context)
+        .context("Could not render recovery codes page.")?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/html")
+        .header(axum::http::header::CACHE_CONTROL, "no-store")
+        .body(html_body.into())
+        .context("Could not create response.")?)
+}
+
+#[derive(serde::Deserialize)]
+pub struct DisableTwoFactorForm {
+    current_password: Secret<String>,
+}
+
+#[instrument(
+    name = "Disable two-factor authentication",
+    skip(app_state, flash, form)
+)]
+pub async fn disable_two_factor(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Extension(user_id): Extension<UserId>,
+    Form(form): Form<DisableTwoFactorForm>,
+) -> Result<(Flash, Redirect), TwoFactorError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let Ok(password) =
+        Password::try_from(form.current_password.expose_secret().to_string())
+    else {
+        return Ok((
+            flash.error("The current password is incorrect."),
+            Redirect::to("/admin/two-factor"),
+        ));
+    };
+    let username = get_username(&mut connection, *user_id).await?;
+    let credentials = Credentials { username, password };
+    match validate_credentials(credentials, &mut connection).await {
+        Ok(_) => {}
+        Err(AuthError::InvalidCredentials(_)) => {
+            return Ok((
+                flash.error("The current password is incorrect."),
+                Redirect::to("/admin/two-factor"),
+            ))
+        }
+        Err(e) => return Err(anyhow!(e).into()),
+    }
+    remove_two_factor(&mut connection, *user_id)
+        .await
+        .context("Could not disable two-factor authentication")?;
+    Ok((
+        flash.success("Two-factor authentication has been disabled."),
+        Redirect::to("/admin/two-factor"),
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TwoFactorError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for TwoFactorError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+mod actions;
+mod list;
+
+pub use actions::*;
+pub use list::*;
+
+use axum::{
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum UsersError {
+    #[error("There is no such user.")]
+    UnknownUser,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for UsersError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnknownUser => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("There is no such user.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
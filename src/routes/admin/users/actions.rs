@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+    Extension, Form,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::UsersError;
+use crate::{
+    authentication::UserId,
+    database::queries::{
+        delete_user, revoke_invitation, set_user_disabled, store_invitation,
+        UserChangeError,
+    },
+    domain::{InvitationToken, SubscriberEmail, UserRole},
+    email_client::send::send_invitation_email,
+    flash::Flash,
+    models::UserInvitations,
+    startup::ApplicationState,
+};
+
+#[derive(serde::Deserialize)]
+pub struct InvitationForm {
+    email: String,
+    role: String,
+}
+
+#[instrument(
+    name = "Invite admin user",
+    skip(app_state, flash, form),
+    fields(role = %form.role)
+)]
+pub async fn invite_user(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Extension(current_user): Extension<UserId>,
+    Form(form): Form<InvitationForm>,
+) -> Result<(Flash, Redirect), UsersError> {
+    let email = match SubscriberEmail::try_from(form.email) {
+        Ok(email) => email,
+        Err(e) => {
+            return Ok((
+                flash.error(e.to_string()),
+                Redirect::to("/admin/users"),
+            ))
+        }
+    };
+    let role = match UserRole::try_from(form.role.as_str()) {
+        Ok(role) => role,
+        Err(e) => {
+            return Ok((
+                flash.error(e.to_string()),
+                Redirect::to("/admin/users"),
+            ))
+        }
+    };
+    let token = InvitationToken::generate();
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    store_invitation(
+        &mut connection,
+        &UserInvitations::new(&token, &email, role, *current_user),
+    )
+    .await
+    .context("Failed to store the invitation")?;
+    send_invitation_email(
+        &app_state.email_client,
+        &email,
+        &app_state.base_url,
+        &token,
+        role,
+    )
+    .await
+    .context("Failed to send the invitation email")?;
+    Ok((
+        flash.success(format!("An invitation has been sent to {}.", email)),
+        Redirect::to("/admin/users"),
+    ))
+}
+
+#[instrument(name = "Revoke user invitation", skip(app_state, flash))]
+pub async fn revoke_user_invitation(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(token_hash): Path<String>,
+) -> Result<(Flash, Redirect), UsersError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    revoke_invitation(&mut connection, &token_hash)
+        .await
+        .context("Failed to revoke the invitation")?;
+    Ok((
+        flash.success("The invitation has been revoked."),
+        Redirect::to("/admin/users"),
+    ))
+}
+
+#[instrument(name = "Disable admin user", skip(app_state, flash))]
+pub async fn disable_user(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Extension(current_user): Extension<UserId>,
+    Path(user_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), UsersError> {
+    if user_id == *current_user {
+        return Ok((
+            flash.error("You cannot disable your own account."),
+            Redirect::to("/admin/users"),
+        ));
+    }
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let result = set_user_disabled(&mut connection, user_id, true).await;
+    change_outcome(result, "The user has been disabled.", flash)
+}
+
+#[instrument(name = "Enable admin user", skip(app_state, flash))]
+pub async fn enable_user(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Path(user_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), UsersError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let result = set_user_disabled(&mut connection, user_id, false).await;
+    change_outcome(result, "The user has been enabled.", flash)
+}
+
+#[instrument(name = "Delete admin user", skip(app_state, flash))]
+pub async fn remove_user(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Extension(current_user): Extension<UserId>,
+    Path(user_id): Path<Uuid>,
+) -> Result<(Flash, Redirect), UsersError> {
+    if user_id == *current_user {
+        return Ok((
+            flash.error("You cannot delete your own account."),
+            Redirect::to("/admin/users"),
+        ));
+    }
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let result = delete_user(&mut connection, user_id).await;
+    change_outcome(result, "The user has been deleted.", flash)
+}
+
+fn change_outcome(
+    result: Result<(), UserChangeError>,
+    success: &'static str,
+    flash: Flash,
+) -> Result<(Flash, Redirect), UsersError> {
+    let flash = match result {
+        Ok(()) => flash.success(success),
+        Err(UserChangeError::UnknownUser) => {
+            return Err(UsersError::UnknownUser)
+        }
+        Err(e @ UserChangeError::LastOwner) => flash.error(e.to_string()),
+        Err(e) => {
+            return Err(anyhow!(e).context("Failed to change the user").into())
+        }
+    };
+    Ok((flash, Redirect::to("/admin/users")))
+}
@@ -0,0 +1,94 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    Extension,
+};
+use tracing::instrument;
+
+use super::UsersError;
+use crate::{
+    authentication::UserId,
+    database::queries::{get_admin_users, get_pending_invitations},
+    domain::UserRole,
+    flash::Flash,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+#[derive(serde::Serialize)]
+struct UserRow {
+    id: String,
+    username: String,
+    email: String,
+    role: String,
+    disabled: bool,
+    is_current: bool,
+}
+
+#[derive(serde::Serialize)]
+struct InvitationRow {
+    token_hash: String,
+    email: String,
+    role: String,
+    expires_at: String,
+}
+
+#[instrument(name = "Requesting users page", skip(app_state, session, flash))]
+pub async fn users_page(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+    Extension(current_user): Extension<UserId>,
+) -> Result<(Flash, Response<Body>), UsersError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let users: Vec<UserRow> = get_admin_users(&mut connection)
+        .await
+        .context("Could not get users")?
+        .into_iter()
+        .map(|user| UserRow {
+            id: user.user_id.to_string(),
+            username: user.username,
+            email: user.email.unwrap_or_default(),
+            role: user.role.to_string(),
+            disabled: user.disabled_at.is_some(),
+            is_current: user.user_id == *current_user,
+        })
+        .collect();
+    let invitations: Vec<InvitationRow> =
+        get_pending_invitations(&mut connection)
+            .await
+            .context("Could not get pending invitations")?
+            .into_iter()
+            .map(|invitation| InvitationRow {
+                token_hash: invitation.token_hash,
+                email: invitation.email,
+                role: invitation.role.to_string(),
+                expires_at: invitation.expires_at.to_rfc2822(),
+            })
+            .collect();
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("users", &users);
+    tera_context.insert("invitations", &invitations);
+    tera_context.insert("roles", &UserRole::ALL.map(|role| role.as_str()));
+    let html_body = TEMPLATES
+        .render("pages/users.html", &tera_context)
+        .context("Could not render users page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
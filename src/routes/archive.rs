@@ -0,0 +1,62 @@
+mod feeds;
+mod pages;
+
+pub use feeds::*;
+pub use pages::*;
+
+use anyhow::Context;
+use axum::{
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::{
+    models::NewsletterIssues,
+    personalization::{IssueTemplates, PersonalizationContext},
+};
+
+/// How many of the latest issues the feeds carry.
+const FEED_SIZE: i64 = 20;
+
+fn issue_url(base_url: &str, slug: &str) -> String {
+    format!("{}/issues/{}", base_url, slug)
+}
+
+/// Fills personalized bodies in with neutral values for the public.
+///
+/// The HTML comes out of `IssueTemplates::render` sanitized. An issue that
+/// cannot be rendered is an error: its stored template is never served.
+fn public_html(
+    issue: &NewsletterIssues,
+    archive_url: &str,
+) -> Result<String, anyhow::Error> {
+    let (_, html) =
+        IssueTemplates::parse(&issue.text_content, &issue.html_content)?
+            .render(&PersonalizationContext::public(archive_url))
+            .context("Could not render the issue for the archive.")?;
+    Ok(html)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArchiveError {
+    #[error("There is no such newsletter issue.")]
+    UnknownIssue,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for ArchiveError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnknownIssue => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("There is no such newsletter issue.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Context;
+use atom_syndication::{
+    ContentBuilder, EntryBuilder, FeedBuilder, FixedDateTime, LinkBuilder,
+};
+use axum::{
+    extract::State,
+    http::{Response, StatusCode},
+};
+use chrono::Utc;
+use rss::{ChannelBuilder, GuidBuilder, ItemBuilder};
+use tracing::instrument;
+
+use super::{issue_url, public_html, ArchiveError, FEED_SIZE};
+use crate::{
+    database::queries::get_published_issues, models::NewsletterIssues,
+    startup::ApplicationState,
+};
+
+const FEED_TITLE: &str = "Newsletter";
+const FEED_DESCRIPTION: &str = "Every issue of our newsletter.";
+
+#[instrument(name = "Requesting RSS feed", skip(app_state))]
+pub async fn rss_feed(
+    State(app_state): State<ApplicationState>,
+) -> Result<Response<String>, ArchiveError> {
+    let base_url = &app_state.base_url;
+    let items: Vec<rss::Item> = latest_issues(&app_state)
+        .await?
+        .into_iter()
+        .filter_map(|issue| {
+            let url = issue_url(base_url, issue.slug.as_deref()?);
+            let html = public_html(&issue, &url)
+                .inspect_err(|e| tracing::error!("{} Reason {:?}", e, e))
+                .ok()?;
+            Some(
+                ItemBuilder::default()
+                    .title(issue.title)
+                    .link(url.clone())
+                    .guid(
+                        GuidBuilder::default()
+                            .value(url)
+                            .permalink(true)
+                            .build(),
+                    )
+                    .pub_date(issue.published_at?.to_rfc2822())
+                    .description(html)
+                    .build(),
+            )
+        })
+        .collect();
+    let channel = ChannelBuilder::default()
+        .title(FEED_TITLE)
+        .link(format!("{}/issues", base_url))
+        .description(FEED_DESCRIPTION)
+        .items(items)
+        .build();
+    feed_response("application/rss+xml; charset=utf-8", channel.to_string())
+}
+
+#[instrument(name = "Requesting Atom feed", skip(app_state))]
+pub async fn atom_feed(
+    State(app_state): State<ApplicationState>,
+) -> Result<Response<String>, ArchiveError> {
+    let base_url = &app_state.base_url;
+    let issues = latest_issues(&app_state).await?;
+    let updated: FixedDateTime = issues
+        .iter()
+        .find_map(|issue| issue.published_at)
+        .unwrap_or_else(Utc::now)
+        .into();
+    let entries: Vec<atom_syndication::Entry> = issues
+        .into_iter()
+        .filter_map(|issue| {
+            let url = issue_url(base_url, issue.slug.as_deref()?);
+            let published: FixedDateTime = issue.published_at?.into();
+            let html = public_html(&issue, &url)
+                .inspect_err(|e| tracing::error!("{} Reason {:?}", e, e))
+                .ok()?;
+            Some(
+                EntryBuilder::default()
+                    .id(url.clone())
+                    .title(issue.title)
+                    .updated(published)
+                    .published(Some(published))
+                    .link(LinkBuilder::default().href(url).build())
+                    .content(Some(
+                        ContentBuilder::default()
+                            .content_type(Some("html".to_string()))
+                            .value(Some(html))
+                            .build(),
+                    ))
+                    .build(),
+            )
+        })
+        .collect();
+    let feed = FeedBuilder::default()
+        .id(format!("{}/issues", base_url))
+        .title(FEED_TITLE)
+        .updated(updated)
+        .link(
+            LinkBuilder::default()
+                .href(format!("{}/feed.atom", base_url))
+                .rel("self")
+                .build(),
+        )
+        .link(
+            LinkBuilder::default()
+                .href(format!("{}/issues", base_url))
+                .build(),
+        )
+        .entries(entries)
+        .build();
+    feed_response("application/atom+xml; charset=utf-8", feed.to_string())
+}
+
+async fn latest_issues(
+    app_state: &ApplicationState,
+) -> Result<Vec<NewsletterIssues>, ArchiveError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool.clone())
+            .await
+            .context("Could not get database pool")?;
+    Ok(get_published_issues(&mut connection, Some(FEED_SIZE))
+        .await
+        .context("Could not get published issues")?)
+}
+
+fn feed_response(
+    content_type: &str,
+    body: String,
+) -> Result<Response<String>, ArchiveError> {
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, content_type)
+        .body(body)
+        .context("Could not create response.")?)
+}
@@ -0,0 +1,86 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    http::{Response, StatusCode},
+};
+use diesel::OptionalExtension;
+use tracing::instrument;
+
+use super::{issue_url, public_html, ArchiveError};
+use crate::{
+    database::queries::{get_published_issue, get_published_issues},
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+#[derive(serde::Serialize)]
+struct ArchivedIssue {
+    slug: String,
+    title: String,
+    published_at: String,
+}
+
+#[instrument(name = "Requesting issue archive", skip(app_state))]
+pub async fn archive_page(
+    State(app_state): State<ApplicationState>,
+) -> Result<Response<String>, ArchiveError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let issues: Vec<ArchivedIssue> =
+        get_published_issues(&mut connection, None)
+            .await
+            .context("Could not get published issues")?
+            .into_iter()
+            .filter_map(|issue| {
+                Some(ArchivedIssue {
+                    slug: issue.slug?,
+                    title: issue.title,
+                    published_at: issue.published_at?.to_rfc2822(),
+                })
+            })
+            .collect();
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("issues", &issues);
+    let html_body = TEMPLATES
+        .render("pages/archive.html", &tera_context)
+        .context("Could not render issue archive.")?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/html")
+        .body(html_body)
+        .context("Could not create response.")?)
+}
+
+#[instrument(name = "Requesting archived issue", skip(app_state))]
+pub async fn archived_issue_page(
+    State(app_state): State<ApplicationState>,
+    Path(slug): Path<String>,
+) -> Result<Response<String>, ArchiveError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let issue = get_published_issue(&mut connection, &slug)
+        .await
+        .optional()
+        .context("Could not get newsletter issue")?
+        .ok_or(ArchiveError::UnknownIssue)?;
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("title", &issue.title);
+    let archive_url = issue_url(&app_state.base_url, &slug);
+    tera_context.insert("content_html", &public_html(&issue, &archive_url)?);
+    tera_context.insert(
+        "published_at",
+        &issue.published_at.map(|at| at.to_rfc2822()),
+    );
+    let html_body = TEMPLATES
+        .render("pages/archived_issue.html", &tera_context)
+        .context("Could not render archived issue.")?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/html")
+        .body(html_body)
+        .context("Could not create response.")?)
+}
@@ -7,9 +7,13 @@ use axum::{
 use serde::Serialize;
 
 use crate::database::{
-    queries::{confirm_subscriber, get_subscriber_id_for_token},
+    queries::{
+        change_subscriber_status, get_subscriber_id_for_token,
+        StatusChangeError,
+    },
     DatabaseConnectionPool,
 };
+use crate::domain::SubscriptionStatus;
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
@@ -38,10 +42,21 @@ pub async fn confirm(
             "Invalid token.".to_string(),
         )),
         Some(id) => {
-            confirm_subscriber(&mut connection, &id)
-                .await
-                .context("Could not confirm subscriber.")?;
-            Ok(StatusCode::OK)
+            match change_subscriber_status(
+                &mut connection,
+                &id,
+                SubscriptionStatus::Confirmed,
+            )
+            .await
+            {
+                Ok(()) => Ok(StatusCode::OK),
+                Err(StatusChangeError::InvalidTransition(e)) => {
+                    Err(ConfirmationError::InvalidTransition(e.to_string()))
+                }
+                Err(e) => Err(anyhow::Error::from(e)
+                    .context("Could not confirm subscriber.")
+                    .into()),
+            }
         }
     }
 }
@@ -50,6 +65,8 @@ pub async fn confirm(
 pub enum ConfirmationError {
     #[error("{0}")]
     InvalidToken(String),
+    #[error("{0}")]
+    InvalidTransition(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -65,6 +82,9 @@ impl IntoResponse for ConfirmationError {
             Self::InvalidToken(error_message) => {
                 (StatusCode::UNAUTHORIZED, error_message)
             }
+            Self::InvalidTransition(error_message) => {
+                (StatusCode::CONFLICT, error_message)
+            }
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Something went wrong".to_string(),
@@ -0,0 +1,124 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use tracing::instrument;
+
+use crate::{
+    database::queries::{get_user_id_by_email, store_password_reset_token},
+    domain::{PasswordResetToken, SubscriberEmail},
+    email_client::send::send_password_reset_email,
+    flash::Flash,
+    models::PasswordResetTokens,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+#[instrument(name = "Requesting forgot password page", skip(session, flash))]
+pub async fn forgot_password_form(
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), ForgotPasswordError> {
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    let html_body = TEMPLATES
+        .render("pages/forgot_password.html", &tera_context)
+        .context("Could not render forgot password page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ForgotPasswordForm {
+    email: String,
+}
+
+/// Emails a reset link if the address belongs to an enabled user. The
+/// answer is the same either way, so that it does not reveal who has an
+/// account.
+#[instrument(name = "Request a password reset", skip(app_state, flash, form))]
+pub async fn forgot_password(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Form(form): Form<ForgotPasswordForm>,
+) -> Result<(Flash, Redirect), ForgotPasswordError> {
+    let email = match SubscriberEmail::try_from(form.email) {
+        Ok(email) => email,
+        Err(e) => {
+            return Ok((
+                flash.error(e.to_string()),
+                Redirect::to("/login/forgot"),
+            ))
+        }
+    };
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    if let Some(user_id) = get_user_id_by_email(&mut connection, email.as_ref())
+        .await
+        .context("Could not look up the user")?
+    {
+        let token = PasswordResetToken::generate();
+        store_password_reset_token(
+            &mut connection,
+            &PasswordResetTokens::new(&token, user_id),
+        )
+        .await
+        .context("Failed to store the password reset token")?;
+        // Failing here must not tell the account apart either.
+        if let Err(e) = send_password_reset_email(
+            &app_state.email_client,
+            &email,
+            &app_state.base_url,
+            &token,
+        )
+        .await
+        {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send the password reset email."
+            );
+        }
+    }
+    Ok((
+        flash.info(
+            "If an account uses this address, \
+            a reset link has been sent to it.",
+        ),
+        Redirect::to("/login/forgot"),
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ForgotPasswordError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for ForgotPasswordError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -1,26 +1,45 @@
 use anyhow::Context;
 use axum::{
     body::Body,
+    extract::State,
     http::{Response, StatusCode},
-    response::IntoResponse,
+    response::{IntoResponse, Redirect},
 };
-use axum_extra::extract::SignedCookieJar;
 use tracing::instrument;
 
-use crate::{utils::get_flash_error, TEMPLATES};
+use crate::{
+    database::queries::count_users, flash::Flash, session_state::TypedSession,
+    startup::ApplicationState, TEMPLATES,
+};
 
-#[instrument(name = "Requesting login page", skip(jar))]
+#[instrument(name = "Requesting login page", skip(app_state, session, flash))]
 pub async fn login_form(
-    jar: SignedCookieJar,
-) -> Result<(SignedCookieJar, Response<Body>), LoginFormError> {
-    let mut tera_context = tera::Context::new();
-    let (jar, error_html) = get_flash_error(jar);
-    tera_context.insert("error", &error_html);
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), LoginFormError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    // Nobody can log in before the first owner has been set up.
+    if count_users(&mut connection)
+        .await
+        .context("Could not count users")?
+        == 0
+    {
+        return Ok((flash, Redirect::to("/setup").into_response()));
+    }
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
     let html_body = TEMPLATES
         .render("pages/login.html", &tera_context)
         .context("Could not render login page.")?;
     Ok((
-        jar,
+        flash,
         Response::builder()
             .status(StatusCode::OK)
             .header(axum::http::header::CONTENT_TYPE, "text/html")
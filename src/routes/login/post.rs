@@ -1,17 +1,24 @@
-use crate::{domain::Password, utils::redirect_with_flash};
-use anyhow::{anyhow, Context};
+use crate::domain::Password;
+use anyhow::Context;
 use axum::{
     extract::State,
     http::{Response, StatusCode},
     response::{IntoResponse, Redirect},
     Form,
 };
-use axum_extra::extract::SignedCookieJar;
 use secrecy::{ExposeSecret, Secret};
+use std::net::IpAddr;
+use std::time::Duration;
 use tracing::instrument;
+use uuid::Uuid;
 
 use crate::{
-    authentication::{validate_credentials, AuthError, Credentials},
+    authentication::{
+        validate_credentials, AuthError, Credentials, LoginAttempt,
+    },
+    client_ip::ClientIp,
+    database::queries::get_totp_secret,
+    flash::Flash,
     session_state::TypedSession,
     startup::ApplicationState,
 };
@@ -21,32 +28,40 @@ pub struct FormData {
     username: String,
     password: Secret<String>,
 }
-#[instrument(skip(app_state,form,jar, session), fields(username=tracing::field::Empty, user_id=tracing::field::Empty))]
+#[instrument(skip(app_state,form,flash, session), fields(username=tracing::field::Empty, user_id=tracing::field::Empty))]
 pub async fn login(
     State(app_state): State<ApplicationState>,
+    ClientIp(client): ClientIp,
     session: TypedSession,
-    jar: SignedCookieJar,
+    flash: Flash,
     Form(form): Form<FormData>,
-) -> Result<(SignedCookieJar, Redirect), LoginError> {
+) -> Result<(Flash, Redirect), LoginError> {
+    if let Some(retry_after) =
+        throttle_attempt(&app_state, &form.username, client).await?
+    {
+        return Ok((
+            flash.error(locked_out(retry_after)),
+            Redirect::to("/login"),
+        ));
+    }
     let password =
         match Password::try_from(form.password.expose_secret().to_string()) {
             Ok(pass) => pass,
             Err(_) => {
-                return Ok(redirect_with_flash(
-                    "/login",
-                    anyhow!("Invalid password."),
-                    jar,
+                return Ok((
+                    flash.error("Invalid password."),
+                    Redirect::to("/login"),
                 ))
             }
         };
     let credentials = Credentials {
-        username: form.username,
+        username: form.username.clone(),
         password,
     };
     tracing::Span::current()
         .record("username", &tracing::field::display(&credentials.username));
     let mut connection =
-        crate::database::get_connection(app_state.database_pool)
+        crate::database::get_connection(app_state.database_pool.clone())
             .await
             .context("Could not get database pool")?;
     match validate_credentials(credentials, &mut connection).await {
@@ -54,14 +69,33 @@ pub async fn login(
             tracing::Span::current()
                 .record("user_id", &tracing::field::display(&user_id));
             session.cycle_id().await.context("Session failure")?;
-            if let Err(e) = session
-                .insert_user_id(user_id)
+            let two_factor = get_totp_secret(&mut connection, user_id)
                 .await
-                .context("Could not store user_id")
+                .context("Could not check for two-factor authentication")?
+                .is_some();
+            if two_factor {
+                // The failures are only reset once the second step passes.
+                session
+                    .insert_two_factor_pending(user_id)
+                    .await
+                    .context("Could not store the pending login")?;
+                return Ok((flash, Redirect::to("/login/two-factor")));
+            }
+            if let Err(e) =
+                start_user_session(&session, &app_state, user_id).await
             {
-                return Ok(redirect_with_flash("/login", e, jar));
+                tracing::error!("{} Reason {:?}", e, e);
+                return Ok((
+                    flash.error(e.to_string()),
+                    Redirect::to("/login"),
+                ));
             };
-            Ok((jar, Redirect::to("/admin/dashboard")))
+            app_state
+                .login_throttle
+                .record_success(&form.username)
+                .await
+                .context("Could not reset the failed logins")?;
+            Ok((flash, Redirect::to("/admin/dashboard")))
         }
         Err(e) => {
             let e = match e {
@@ -72,9 +106,68 @@ pub async fn login(
                     LoginError::AuthError(e.into())
                 }
             };
-            Ok(redirect_with_flash("/login", e.into(), jar))
+            app_state
+                .login_throttle
+                .record_failure(&form.username, client)
+                .await
+                .context("Could not record the failed login")?;
+            Ok((flash.error(e.to_string()), Redirect::to("/login")))
+        }
+    }
+}
+
+/// Waits out a slowdown, returning how long the login is locked for
+/// instead when it is.
+pub(super) async fn throttle_attempt(
+    app_state: &ApplicationState,
+    username: &str,
+    client: IpAddr,
+) -> Result<Option<Duration>, anyhow::Error> {
+    let attempt = app_state
+        .login_throttle
+        .check(username, client)
+        .await
+        .context("Could not check the failed logins")?;
+    match attempt {
+        LoginAttempt::Allowed => Ok(None),
+        LoginAttempt::Delayed(delay) => {
+            tokio::time::sleep(delay).await;
+            Ok(None)
         }
+        LoginAttempt::Locked { retry_after } => Ok(Some(retry_after)),
+    }
+}
+
+pub(super) fn locked_out(retry_after: Duration) -> String {
+    let minutes = retry_after.as_secs().div_ceil(60);
+    format!(
+        "Too many failed login attempts. Try again in {} minute{}.",
+        minutes,
+        if minutes == 1 { "" } else { "s" }
+    )
+}
+
+/// Grants `user_id` access to the admin area from this session.
+pub(super) async fn start_user_session(
+    session: &TypedSession,
+    app_state: &ApplicationState,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    session
+        .insert_user_id(user_id)
+        .await
+        .context("Could not store user_id")?;
+    // Remembered so that a password reset can log the user out.
+    if let Some(session_id) =
+        session.save().await.context("Could not save session")?
+    {
+        app_state
+            .user_sessions
+            .register(user_id, session_id)
+            .await
+            .context("Could not register session")?;
     }
+    Ok(())
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -0,0 +1,147 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use secrecy::{ExposeSecret, Secret};
+use tracing::instrument;
+
+use crate::{
+    authentication::hash_password,
+    database::queries::{get_password_reset_user, reset_password_with_token},
+    domain::{Password, PasswordResetToken},
+    flash::Flash,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+#[derive(serde::Deserialize)]
+pub struct PasswordResetQuery {
+    token: String,
+}
+
+#[instrument(
+    name = "Requesting password reset page",
+    skip(app_state, session, flash, query)
+)]
+pub async fn password_reset_form(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+    Query(query): Query<PasswordResetQuery>,
+) -> Result<(Flash, Response<Body>), PasswordResetLinkError> {
+    let token = PasswordResetToken::try_from(query.token)
+        .map_err(|_| PasswordResetLinkError::UnknownToken)?;
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    get_password_reset_user(&mut connection, &token.hash())
+        .await
+        .context("Could not get the password reset token")?
+        .ok_or(PasswordResetLinkError::UnknownToken)?;
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("token", token.as_ref());
+    let html_body = TEMPLATES
+        .render("pages/password_reset.html", &tera_context)
+        .context("Could not render password reset page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct PasswordResetForm {
+    token: String,
+    new_password: Secret<String>,
+    new_password_check: Secret<String>,
+}
+
+/// Sets the new password and logs the user out everywhere.
+#[instrument(name = "Reset a forgotten password", skip(app_state, flash, form))]
+pub async fn reset_forgotten_password(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Form(form): Form<PasswordResetForm>,
+) -> Result<(Flash, Redirect), PasswordResetLinkError> {
+    let token = PasswordResetToken::try_from(form.token)
+        .map_err(|_| PasswordResetLinkError::UnknownToken)?;
+    let reset_page = format!("/login/reset?token={}", token.as_ref());
+    if form.new_password.expose_secret()
+        != form.new_password_check.expose_secret()
+    {
+        return Ok((
+            flash.error(
+                "You entered two different new passwords - \
+                the field values must match.",
+            ),
+            Redirect::to(&reset_page),
+        ));
+    }
+    let password =
+        match Password::try_from(form.new_password.expose_secret().to_string())
+        {
+            Ok(password) => password,
+            Err(e) => {
+                return Ok((
+                    flash.error(e.to_string()),
+                    Redirect::to(&reset_page),
+                ))
+            }
+        };
+    let password_hash = hash_password(password).await?;
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let user_id = reset_password_with_token(
+        &mut connection,
+        &token.hash(),
+        password_hash,
+    )
+    .await
+    .context("Could not reset the password")?
+    .ok_or(PasswordResetLinkError::UnknownToken)?;
+    app_state.user_sessions.revoke_all(user_id).await?;
+    Ok((
+        flash.success("Your password has been reset. You can now log in."),
+        Redirect::to("/login"),
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PasswordResetLinkError {
+    #[error("This reset link is invalid or has expired.")]
+    UnknownToken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for PasswordResetLinkError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnknownToken => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("This reset link is invalid or has expired.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
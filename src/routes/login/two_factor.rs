@@ -0,0 +1,174 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use secrecy::{ExposeSecret, Secret};
+use tracing::instrument;
+
+use super::post::{locked_out, start_user_session, throttle_attempt};
+use crate::{
+    authentication::{decrypt_totp_secret, find_recovery_code},
+    client_ip::ClientIp,
+    database::queries::{
+        get_totp_secret, get_unused_recovery_codes, get_username,
+        record_totp_step, use_recovery_code,
+    },
+    flash::Flash,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+#[instrument(name = "Requesting two-factor login page", skip(session, flash))]
+pub async fn two_factor_form(
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), TwoFactorLoginError> {
+    if session
+        .get_two_factor_pending()
+        .await
+        .context("Could not read the pending login")?
+        .is_none()
+    {
+        return Ok((flash, Redirect::to("/login").into_response()));
+    }
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    let html_body = TEMPLATES
+        .render("pages/two_factor_login.html", &tera_context)
+        .context("Could not render two-factor login page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct TwoFactorForm {
+    code: Secret<String>,
+}
+
+/// Second login step, accepting either a TOTP code or a recovery code.
+#[instrument(
+    name = "Verify second factor",
+    skip(app_state, session, flash, form),
+    fields(user_id = tracing::field::Empty)
+)]
+pub async fn verify_two_factor(
+    State(app_state): State<ApplicationState>,
+    ClientIp(client): ClientIp,
+    session: TypedSession,
+    flash: Flash,
+    Form(form): Form<TwoFactorForm>,
+) -> Result<(Flash, Redirect), TwoFactorLoginError> {
+    let Some(user_id) = session
+        .get_two_factor_pending()
+        .await
+        .context("Could not read the pending login")?
+    else {
+        return Ok((flash, Redirect::to("/login")));
+    };
+    tracing::Span::current()
+        .record("user_id", &tracing::field::display(&user_id));
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool.clone())
+            .await
+            .context("Could not get database pool")?;
+    // Wrong codes count as failed logins of the same username.
+    let username = get_username(&mut connection, user_id).await?;
+    if let Some(retry_after) =
+        throttle_attempt(&app_state, &username, client).await?
+    {
+        session
+            .remove_two_factor_pending()
+            .await
+            .context("Could not clear the pending login")?;
+        return Ok((
+            flash.error(locked_out(retry_after)),
+            Redirect::to("/login"),
+        ));
+    }
+    let Some(stored_secret) = get_totp_secret(&mut connection, user_id)
+        .await
+        .context("Could not get the TOTP secret")?
+    else {
+        // Two-factor authentication was turned off in the meantime.
+        session
+            .remove_two_factor_pending()
+            .await
+            .context("Could not clear the pending login")?;
+        return Ok((flash, Redirect::to("/login")));
+    };
+    let secret =
+        decrypt_totp_secret(&stored_secret, user_id, &app_state.hmac_secret.0)?;
+    let verified = match secret
+        .verify(form.code.expose_secret(), chrono::Utc::now().timestamp())
+    {
+        Some(step) => record_totp_step(&mut connection, user_id, step)
+            .await
+            .context("Could not record the TOTP step")?,
+        None => {
+            let codes = get_unused_recovery_codes(&mut connection, user_id)
+                .await
+                .context("Could not get the recovery codes")?;
+            match find_recovery_code(form.code, codes).await? {
+                Some(code_id) => use_recovery_code(&mut connection, code_id)
+                    .await
+                    .context("Could not use the recovery code")?,
+                None => false,
+            }
+        }
+    };
+    if !verified {
+        app_state
+            .login_throttle
+            .record_failure(&username, client)
+            .await
+            .context("Could not record the failed login")?;
+        return Ok((
+            flash.error("Invalid authentication code."),
+            Redirect::to("/login/two-factor"),
+        ));
+    }
+    session
+        .remove_two_factor_pending()
+        .await
+        .context("Could not clear the pending login")?;
+    session.cycle_id().await.context("Session failure")?;
+    start_user_session(&session, &app_state, user_id).await?;
+    app_state
+        .login_throttle
+        .record_success(&username)
+        .await
+        .context("Could not reset the failed logins")?;
+    Ok((flash, Redirect::to("/admin/dashboard")))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TwoFactorLoginError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for TwoFactorLoginError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
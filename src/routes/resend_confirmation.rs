@@ -0,0 +1,143 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Form,
+};
+use chrono::Utc;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::{
+    database::queries::{
+        delete_tokens_for_subscriber, get_latest_token_generated_at,
+        get_subscriber_by_email, store_token,
+    },
+    domain::{SubscriberEmail, SubscriptionStatus, SubscriptionToken},
+    email_client::send::send_confirmation_email,
+    startup::ApplicationState,
+};
+
+/// Minimum time between two confirmation emails sent to the same address.
+const RESEND_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+#[derive(serde::Deserialize)]
+pub struct ResendConfirmationForm {
+    email: String,
+}
+
+/// Unknown, already confirmed and recently emailed addresses get the same
+/// response as pending ones, so the endpoint cannot be used to find out who
+/// is subscribed.
+#[tracing::instrument(
+    name = "Resending a confirmation email",
+    skip(form, app_state),
+    fields(subscriber_email = %form.email)
+)]
+pub async fn resend_confirmation(
+    State(app_state): State<ApplicationState>,
+    Form(form): Form<ResendConfirmationForm>,
+) -> Result<StatusCode, ResendConfirmationError> {
+    let subscriber_email = SubscriberEmail::try_from(form.email)
+        .map_err(|e| ResendConfirmationError::InvalidEmail(e.to_string()))?;
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get connection from pool.")?;
+    let cooldown = chrono::Duration::from_std(RESEND_COOLDOWN)
+        .context("Resend cooldown is out of range")?;
+    connection
+        .transaction::<_, ResendConfirmationError, _>(|conn| {
+            async move {
+                // Locks the subscriber row until the email is sent, so that
+                // concurrent resends wait here and then see the new token.
+                let subscriber =
+                    get_subscriber_by_email(conn, subscriber_email.as_ref())
+                        .await
+                        .context("Failed to look up the subscriber.")?;
+                let subscriber = match subscriber {
+                    Some(subscriber)
+                        if subscriber.status == SubscriptionStatus::Pending =>
+                    {
+                        subscriber
+                    }
+                    _ => {
+                        tracing::info!(
+                            "No pending subscriber for this address, \
+                             nothing to resend."
+                        );
+                        return Ok(());
+                    }
+                };
+                let last_sent_at =
+                    get_latest_token_generated_at(conn, &subscriber.id)
+                        .await
+                        .context("Failed to read the previous token.")?;
+                if last_sent_at
+                    .is_some_and(|sent_at| sent_at + cooldown > Utc::now())
+                {
+                    tracing::info!(
+                        "A confirmation email was sent recently, \
+                         not resending yet."
+                    );
+                    return Ok(());
+                }
+                delete_tokens_for_subscriber(conn, &subscriber.id)
+                    .await
+                    .context("Failed to invalidate previous tokens.")?;
+                let subscription_token = SubscriptionToken::generate();
+                store_token(conn, &subscription_token, &subscriber.id)
+                    .await
+                    .context("Failed to store token.")?;
+                send_confirmation_email(
+                    &app_state.email_client,
+                    &subscriber_email,
+                    &app_state.base_url,
+                    &subscription_token,
+                )
+                .await
+                .context("Failed to send confirmation email.")?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResendConfirmationError {
+    #[error("{0}")]
+    InvalidEmail(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("Unkown database error.")]
+    DatabaseError(#[from] diesel::result::Error),
+}
+
+impl IntoResponse for ResendConfirmationError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct ResendErrorResponse {
+            message: String,
+        }
+        tracing::error!("{} Reason: {:?}", self, self);
+        match self {
+            Self::InvalidEmail(message) => (
+                StatusCode::BAD_REQUEST,
+                axum::Json(ResendErrorResponse { message }),
+            )
+                .into_response(),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(ResendErrorResponse {
+                    message: "Something went wrong".to_string(),
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use tracing::instrument;
+
+use super::{NewAccountError, NewAccountForm};
+use crate::{
+    database::queries::{count_users, create_first_owner},
+    domain::{SubscriberEmail, UserRole},
+    flash::Flash,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+/// Lets the first visitor create the owner account. Once any user exists the
+/// page sends everyone to the login form instead.
+#[instrument(name = "Requesting setup page", skip(app_state, session, flash))]
+pub async fn setup_form(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+) -> Result<(Flash, Response<Body>), SetupError> {
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    if count_users(&mut connection)
+        .await
+        .context("Could not count users")?
+        > 0
+    {
+        return Ok((flash, Redirect::to("/login").into_response()));
+    }
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    let html_body = TEMPLATES
+        .render("pages/setup.html", &tera_context)
+        .context("Could not render setup page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SetupForm {
+    email: String,
+    #[serde(flatten)]
+    account: NewAccountForm,
+}
+
+#[instrument(name = "Create the first owner", skip(app_state, flash, form))]
+pub async fn setup(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Form(form): Form<SetupForm>,
+) -> Result<(Flash, Redirect), SetupError> {
+    let email = match SubscriberEmail::try_from(form.email) {
+        Ok(email) => email,
+        Err(e) => {
+            return Ok((flash.error(e.to_string()), Redirect::to("/setup")))
+        }
+    };
+    let user = match form.account.into_user(UserRole::Owner).await {
+        Ok(user) => user,
+        Err(NewAccountError::Invalid(message)) => {
+            return Ok((flash.error(message), Redirect::to("/setup")))
+        }
+        Err(NewAccountError::UnexpectedError(e)) => return Err(e.into()),
+    };
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let created = create_first_owner(&mut connection, &user, email.as_ref())
+        .await
+        .context("Failed to create the first owner")?;
+    if !created {
+        return Ok((flash, Redirect::to("/login")));
+    }
+    Ok((
+        flash.success("Your account has been created. You can now log in."),
+        Redirect::to("/login"),
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SetupError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for SetupError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use secrecy::{ExposeSecret, Secret};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    authentication::hash_password,
+    database::queries::{
+        accept_invitation, get_invitation, AcceptInvitationError,
+    },
+    domain::{InvitationToken, Password, UserRole, Username},
+    flash::Flash,
+    models::Users,
+    session_state::TypedSession,
+    startup::ApplicationState,
+    TEMPLATES,
+};
+
+/// The account details asked for on signup and on first-run setup.
+#[derive(serde::Deserialize)]
+pub struct NewAccountForm {
+    username: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+}
+
+impl NewAccountForm {
+    /// Validates the form, hashing the password into a user with `role`.
+    pub(crate) async fn into_user(
+        self,
+        role: UserRole,
+    ) -> Result<Users, NewAccountError> {
+        let username = Username::try_from(self.username)
+            .map_err(|e| NewAccountError::Invalid(e.to_string()))?;
+        if self.password.expose_secret() != self.password_check.expose_secret()
+        {
+            return Err(NewAccountError::Invalid(
+                "You entered two different passwords - \
+                the field values must match."
+                    .to_string(),
+            ));
+        }
+        let password =
+            Password::try_from(self.password.expose_secret().to_string())
+                .map_err(|e| NewAccountError::Invalid(e.to_string()))?;
+        let password_hash = hash_password(password).await?;
+        Ok(Users::new(
+            Uuid::new_v4(),
+            username.as_ref(),
+            password_hash.expose_secret(),
+            role,
+        ))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NewAccountError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+#[derive(serde::Deserialize)]
+pub struct SignupQuery {
+    token: String,
+}
+
+#[instrument(
+    name = "Requesting signup page",
+    skip(app_state, session, flash, query)
+)]
+pub async fn signup_form(
+    State(app_state): State<ApplicationState>,
+    session: TypedSession,
+    mut flash: Flash,
+    Query(query): Query<SignupQuery>,
+) -> Result<(Flash, Response<Body>), SignupError> {
+    let token = InvitationToken::try_from(query.token)
+        .map_err(|_| SignupError::UnknownInvitation)?;
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let invitation = get_invitation(&mut connection, &token.hash())
+        .await
+        .context("Could not get the invitation")?
+        .ok_or(SignupError::UnknownInvitation)?;
+    let mut tera_context = session
+        .form_context()
+        .await
+        .context("Could not get the CSRF token")?;
+    flash.insert_into(&mut tera_context);
+    tera_context.insert("token", token.as_ref());
+    tera_context.insert("email", &invitation.email);
+    tera_context.insert("role", invitation.role.as_str());
+    let html_body = TEMPLATES
+        .render("pages/signup.html", &tera_context)
+        .context("Could not render signup page.")?;
+    Ok((
+        flash,
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "text/html")
+            .body(html_body.into())
+            .context("Could not create response.")?,
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SignupForm {
+    token: String,
+    #[serde(flatten)]
+    account: NewAccountForm,
+}
+
+#[instrument(name = "Sign up with an invitation", skip(app_state, flash, form))]
+pub async fn signup(
+    State(app_state): State<ApplicationState>,
+    flash: Flash,
+    Form(form): Form<SignupForm>,
+) -> Result<(Flash, Redirect), SignupError> {
+    let token = InvitationToken::try_from(form.token)
+        .map_err(|_| SignupError::UnknownInvitation)?;
+    let signup_page = format!("/signup?token={}", token.as_ref());
+    let mut connection =
+        crate::database::get_connection(app_state.database_pool)
+            .await
+            .context("Could not get database pool")?;
+    let invitation = get_invitation(&mut connection, &token.hash())
+        .await
+        .context("Could not get the invitation")?
+        .ok_or(SignupError::UnknownInvitation)?;
+    let user = match form.account.into_user(invitation.role).await {
+        Ok(user) => user,
+        Err(NewAccountError::Invalid(message)) => {
+            return Ok((flash.error(message), Redirect::to(&signup_page)))
+        }
+        Err(NewAccountError::UnexpectedError(e)) => return Err(e.into()),
+    };
+    match accept_invitation(&mut connection, &token.hash(), user).await {
+        Ok(()) => Ok((
+            flash.success("Your account has been created. You can now log in."),
+            Redirect::to("/login"),
+        )),
+        Err(AcceptInvitationError::UnknownInvitation) => {
+            Err(SignupError::UnknownInvitation)
+        }
+        Err(e @ AcceptInvitationError::AccountTaken) => {
+            Ok((flash.error(e.to_string()), Redirect::to(&signup_page)))
+        }
+        Err(e) => {
+            Err(anyhow!(e).context("Failed to accept the invitation").into())
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SignupError {
+    #[error("This invitation is invalid or has expired.")]
+    UnknownInvitation,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for SignupError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnknownInvitation => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("This invitation is invalid or has expired.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use std::sync::Arc;
 
-use crate::domain::SubscriptionToken;
+use crate::domain::{SubscriptionStatus, SubscriptionToken};
 use crate::{
-    database::queries, database::queries::insert_subscriber,
-    domain::NewSubscriber, email_client::send::send_confirmation_email,
+    database::queries,
+    database::queries::{
+        change_subscriber_status, get_subscriber_by_email, insert_subscriber,
+    },
+    domain::NewSubscriber,
+    email_client::send::send_confirmation_email,
     startup::ApplicationState,
 };
 use anyhow::Context;
@@ -54,13 +58,44 @@ pub async fn subscriptions(
                 let subscriber_id = insert_subscriber(conn, &new_subscriber)
                     .await
                     .context("Failed to insert subscriber.")?;
+                let status = get_subscriber_by_email(
+                    conn,
+                    new_subscriber.email.as_ref(),
+                )
+                .await
+                .context("Failed to look up subscriber.")?
+                .context("The subscriber vanished after being saved.")?
+                .status;
+                match status {
+                    // Someone reported us as spam, so we never email them
+                    // again. Answer as usual to not reveal who complained.
+                    SubscriptionStatus::Complained => {
+                        tracing::info!(
+                            "Not emailing a subscriber who complained."
+                        );
+                        return Ok(());
+                    }
+                    // Coming back requires confirming the address again.
+                    SubscriptionStatus::Unsubscribed
+                    | SubscriptionStatus::Bounced => {
+                        change_subscriber_status(
+                            conn,
+                            &subscriber_id,
+                            SubscriptionStatus::Pending,
+                        )
+                        .await
+                        .context("Failed to resubscribe subscriber.")?;
+                    }
+                    SubscriptionStatus::Pending
+                    | SubscriptionStatus::Confirmed => {}
+                }
 
                 queries::store_token(conn, &subscription_token, &subscriber_id)
                     .await
                     .context("Failed to store token.")?;
                 send_confirmation_email(
                     &app_state.email_client,
-                    new_subscriber,
+                    &new_subscriber.email,
                     &app_state.base_url,
                     &subscription_token,
                 )
@@ -0,0 +1,125 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, Response, StatusCode},
+    response::{IntoResponse, Redirect},
+};
+use diesel::OptionalExtension;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        queries::{get_issue_link, record_click, record_open},
+        DatabaseConnectionPool,
+    },
+    domain::TrackingToken,
+    startup::HmacSecret,
+};
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00,
+    0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00,
+    0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+#[derive(serde::Deserialize)]
+pub struct ClickParameters {
+    #[serde(default)]
+    token: String,
+}
+
+#[tracing::instrument(
+    name = "Tracking an issue open",
+    skip(token, hmac_secret, database_pool)
+)]
+pub async fn track_open(
+    Path(token): Path<String>,
+    State(hmac_secret): State<HmacSecret>,
+    State(database_pool): State<DatabaseConnectionPool>,
+) -> Result<Response<axum::body::Body>, TrackingError> {
+    let (issue_id, subscriber_id) =
+        TrackingToken::verify(&token, &hmac_secret.0)
+            .map_err(|_| TrackingError::UnknownTarget)?;
+    let mut connection = crate::database::get_connection(database_pool)
+        .await
+        .context("Failed to get database pool.")?;
+    if let Err(e) = record_open(&mut connection, issue_id, subscriber_id).await
+    {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to record an issue open."
+        );
+    }
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "image/gif")
+        .header(header::CACHE_CONTROL, "no-store, max-age=0")
+        .body(PIXEL.into())
+        .context("Could not create response.")?)
+}
+
+/// Redirects to the original link, counting the click when the token was
+/// issued for the link's issue. Forwarded emails still get the redirect.
+#[tracing::instrument(
+    name = "Tracking a link click",
+    skip(parameters, hmac_secret, database_pool)
+)]
+pub async fn track_click(
+    Path(link_id): Path<Uuid>,
+    parameters: Query<ClickParameters>,
+    State(hmac_secret): State<HmacSecret>,
+    State(database_pool): State<DatabaseConnectionPool>,
+) -> Result<Redirect, TrackingError> {
+    let mut connection = crate::database::get_connection(database_pool)
+        .await
+        .context("Failed to get database pool.")?;
+    let link = get_issue_link(&mut connection, link_id)
+        .await
+        .optional()
+        .context("Could not get the link.")?
+        .ok_or(TrackingError::UnknownTarget)?;
+    match TrackingToken::verify(&parameters.token, &hmac_secret.0) {
+        Ok((issue_id, subscriber_id))
+            if issue_id == link.newsletter_issue_id =>
+        {
+            if let Err(e) =
+                record_click(&mut connection, link.id, subscriber_id).await
+            {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to record a link click."
+                );
+            }
+        }
+        _ => tracing::info!("Not counting a click without a valid token."),
+    }
+    Ok(Redirect::to(&link.url))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TrackingError {
+    #[error("There is nothing to track here.")]
+    UnknownTarget,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for TrackingError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::UnknownTarget => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Not found.".into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+use serde::Serialize;
+
+use crate::{
+    database::{
+        queries::{change_subscriber_status, StatusChangeError},
+        DatabaseConnectionPool,
+    },
+    domain::{SubscriptionStatus, UnsubscribeToken},
+    startup::HmacSecret,
+    TEMPLATES,
+};
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    token: String,
+}
+
+#[tracing::instrument(
+    name = "Requesting unsubscribe page",
+    skip(parameters, hmac_secret)
+)]
+pub async fn unsubscribe_form(
+    parameters: Query<UnsubscribeParameters>,
+    State(hmac_secret): State<HmacSecret>,
+) -> Result<Response<String>, UnsubscribeError> {
+    UnsubscribeToken::verify(&parameters.token, &hmac_secret.0)?;
+    render_page(&parameters.token, false)
+}
+
+/// Also serves RFC 8058 one-click requests, which POST
+/// `List-Unsubscribe=One-Click` to the URL in the `List-Unsubscribe` header.
+#[tracing::instrument(
+    name = "Unsubscribing subscriber",
+    skip(parameters, hmac_secret, database_pool),
+    fields(subscriber_id = tracing::field::Empty)
+)]
+pub async fn unsubscribe(
+    parameters: Query<UnsubscribeParameters>,
+    State(hmac_secret): State<HmacSecret>,
+    State(database_pool): State<DatabaseConnectionPool>,
+) -> Result<Response<String>, UnsubscribeError> {
+    let subscriber_id =
+        UnsubscribeToken::verify(&parameters.token, &hmac_secret.0)?;
+    tracing::Span::current()
+        .record("subscriber_id", &tracing::field::display(&subscriber_id));
+    let mut connection = crate::database::get_connection(database_pool)
+        .await
+        .context("Failed to get database pool.")?;
+    match change_subscriber_status(
+        &mut connection,
+        &subscriber_id,
+        SubscriptionStatus::Unsubscribed,
+    )
+    .await
+    {
+        // A deleted subscriber is as unsubscribed as it gets.
+        Ok(()) | Err(StatusChangeError::UnknownSubscriber) => {}
+        Err(e) => {
+            return Err(anyhow::Error::from(e)
+                .context("Could not unsubscribe subscriber.")
+                .into())
+        }
+    }
+    render_page(&parameters.token, true)
+}
+
+fn render_page(
+    token: &str,
+    unsubscribed: bool,
+) -> Result<Response<String>, UnsubscribeError> {
+    let mut tera_context = tera::Context::new();
+    tera_context.insert("token", token);
+    tera_context.insert("unsubscribed", &unsubscribed);
+    let html_body = TEMPLATES
+        .render("pages/unsubscribe.html", &tera_context)
+        .context("Could not render unsubscribe page.")?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "text/html")
+        .body(html_body)
+        .context("Could not create response.")?)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum UnsubscribeError {
+    #[error("Invalid token.")]
+    InvalidToken(#[from] crate::domain::InvalidUnsubscribeToken),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for UnsubscribeError {
+    fn into_response(self) -> axum::response::Response {
+        #[derive(Serialize)]
+        struct UnsubscribeResponse {
+            message: String,
+        }
+        tracing::error!("{} Reason: {:?}", self, self);
+        let (status, message) = match self {
+            Self::InvalidToken(_) => {
+                (StatusCode::UNAUTHORIZED, "Invalid token.".to_string())
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Something went wrong".to_string(),
+            ),
+        };
+        (status, axum::Json(UnsubscribeResponse { message })).into_response()
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::Context;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, Response, StatusCode},
+    response::IntoResponse,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    configuration::WebhookSettings,
+    database::{queries::record_email_event, DatabaseConnectionPool},
+    domain::EmailEventKind,
+};
+
+/// Bounce types after which an address will not accept mail again.
+const HARD_BOUNCES: [&str; 3] =
+    ["HardBounce", "BadEmailAddress", "ManuallyDeactivated"];
+
+/// The fields of Postmark's bounce and spam complaint webhooks we rely on.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkEvent {
+    record_type: String,
+    #[serde(rename = "Type", default)]
+    bounce_type: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    bounced_at: String,
+    /// Set when Postmark stopped sending to the address.
+    #[serde(default)]
+    inactive: bool,
+}
+
+impl PostmarkEvent {
+    /// `None` for the record types we do not act on, like deliveries.
+    fn kind(&self) -> Option<EmailEventKind> {
+        match self.record_type.as_str() {
+            "SpamComplaint" => Some(EmailEventKind::SpamComplaint),
+            "Bounce"
+                if self.inactive
+                    || HARD_BOUNCES.contains(&self.bounce_type.as_str()) =>
+            {
+                Some(EmailEventKind::HardBounce)
+            }
+            "Bounce" => Some(EmailEventKind::SoftBounce),
+            _ => None,
+        }
+    }
+
+    /// When the bounce or complaint happened, or now if Postmark did not say.
+    fn occurred_at(&self) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&self.bounced_at)
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now())
+    }
+}
+
+#[tracing::instrument(
+    name = "Receiving an email webhook",
+    skip(webhook, database_pool, headers, body),
+    fields(record_type = tracing::field::Empty)
+)]
+pub async fn email_webhook(
+    State(webhook): State<WebhookSettings>,
+    State(database_pool): State<DatabaseConnectionPool>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, WebhookError> {
+    if !is_authorized(&webhook, &headers) {
+        return Err(WebhookError::Unauthorized {
+            basic_auth: matches!(webhook, WebhookSettings::BasicAuth { .. }),
+        });
+    }
+    let event: PostmarkEvent = serde_json::from_slice(&body)
+        .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+    tracing::Span::current().record("record_type", &event.record_type);
+    let Some(kind) = event.kind() else {
+        return Ok(StatusCode::OK);
+    };
+    if event.email.is_empty() {
+        return Err(WebhookError::InvalidPayload(
+            "The event has no email address.".to_string(),
+        ));
+    }
+    let mut connection = crate::database::get_connection(database_pool)
+        .await
+        .context("Failed to get database pool.")?;
+    let recorded = record_email_event(
+        &mut connection,
+        &event.email,
+        kind,
+        &event.description,
+        event.occurred_at(),
+    )
+    .await
+    .context("Failed to record the email event.")?;
+    tracing::info!(
+        kind = %kind,
+        subscriber_id = ?recorded.subscriber_id,
+        status_changed = recorded.status_changed,
+        "Email event recorded."
+    );
+    Ok(StatusCode::OK)
+}
+
+fn is_authorized(webhook: &WebhookSettings, headers: &HeaderMap) -> bool {
+    match webhook {
+        WebhookSettings::SharedSecret { secret } => headers
+            .get("X-Webhook-Secret")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| same_secret(value, secret.expose_secret())),
+        WebhookSettings::BasicAuth { username, password } => {
+            let Some(credentials) = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Basic "))
+                .and_then(|encoded| STANDARD.decode(encoded).ok())
+                .and_then(|decoded| String::from_utf8(decoded).ok())
+            else {
+                return false;
+            };
+            let expected = format!("{}:{}", username, password.expose_secret());
+            same_secret(&credentials, &expected)
+        }
+    }
+}
+
+/// Compares digests so that the time taken says nothing about the secret.
+fn same_secret(candidate: &str, expected: &str) -> bool {
+    Sha256::digest(candidate) == Sha256::digest(expected)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookError {
+    /// `basic_auth` asks the caller for credentials in the response.
+    #[error("The webhook call is not authorized.")]
+    Unauthorized { basic_auth: bool },
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{} Reason {:?}", self, self);
+        match self {
+            Self::Unauthorized { basic_auth: true } => Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(header::WWW_AUTHENTICATE, r#"Basic realm="webhooks""#)
+                .body("Unauthorized Access".into())
+                .unwrap(),
+            Self::Unauthorized { basic_auth: false } => Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body("Unauthorized Access".into())
+                .unwrap(),
+            Self::InvalidPayload(message) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(message.into())
+                .unwrap(),
+            Self::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body("Something went wrong.".into())
+                .unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_authorized, PostmarkEvent};
+    use crate::{configuration::WebhookSettings, domain::EmailEventKind};
+    use axum::http::{header, HeaderMap, HeaderValue};
+    use secrecy::Secret;
+
+    fn event(json: serde_json::Value) -> PostmarkEvent {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn hard_bounces_and_deactivations_are_hard_bounces() {
+        let hard = event(serde_json::json!({
+            "RecordType": "Bounce", "Type": "HardBounce", "Email": "a@b.c"
+        }));
+        let deactivated = event(serde_json::json!({
+            "RecordType": "Bounce", "Type": "Transient", "Inactive": true,
+            "Email": "a@b.c"
+        }));
+        assert_eq!(hard.kind(), Some(EmailEventKind::HardBounce));
+        assert_eq!(deactivated.kind(), Some(EmailEventKind::HardBounce));
+    }
+
+    #[test]
+    fn other_bounces_are_soft_bounces() {
+        let soft = event(serde_json::json!({
+            "RecordType": "Bounce", "Type": "SoftBounce", "Email": "a@b.c"
+        }));
+        assert_eq!(soft.kind(), Some(EmailEventKind::SoftBounce));
+    }
+
+    #[test]
+    fn deliveries_are_ignored() {
+        let delivery = event(serde_json::json!({
+            "RecordType": "Delivery", "Recipient": "a@b.c"
+        }));
+        assert_eq!(delivery.kind(), None);
+    }
+
+    #[test]
+    fn shared_secrets_must_match() {
+        let webhook = WebhookSettings::SharedSecret {
+            secret: Secret::new("secret".to_string()),
+        };
+        assert!(is_authorized(
+            &webhook,
+            &headers("x-webhook-secret", "secret")
+        ));
+        assert!(!is_authorized(
+            &webhook,
+            &headers("x-webhook-secret", "nope")
+        ));
+        assert!(!is_authorized(&webhook, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn basic_auth_credentials_must_match() {
+        let webhook = WebhookSettings::BasicAuth {
+            username: "postmark".to_string(),
+            password: Secret::new("secret".to_string()),
+        };
+        // "postmark:secret" and "postmark:nope".
+        let valid = headers(
+            header::AUTHORIZATION.as_str(),
+            "Basic cG9zdG1hcms6c2VjcmV0",
+        );
+        let invalid = headers(
+            header::AUTHORIZATION.as_str(),
+            "Basic cG9zdG1hcms6bm9wZQ==",
+        );
+        assert!(is_authorized(&webhook, &valid));
+        assert!(!is_authorized(&webhook, &invalid));
+    }
+}
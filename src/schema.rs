@@ -1,9 +1,46 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "header_pair"))]
+    pub struct HeaderPair;
+
     #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "http_request"))]
     pub struct HttpRequest;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "newsletter_issue_status"))]
+    pub struct NewsletterIssueStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "subscription_status"))]
+    pub struct SubscriptionStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "user_role"))]
+    pub struct UserRole;
+}
+
+diesel::table! {
+    confirmation_email_queue (subscription_token) {
+        subscription_token -> Text,
+        attempts -> Int2,
+        next_attempt_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    email_events (id) {
+        id -> Uuid,
+        subscriber_id -> Nullable<Uuid>,
+        email -> Text,
+        kind -> Text,
+        description -> Text,
+        status_changed -> Bool,
+        occurred_at -> Timestamptz,
+        received_at -> Timestamptz,
+    }
 }
 
 diesel::table! {
@@ -13,11 +50,92 @@ diesel::table! {
     idempotency (user_id, idempotency_key) {
         user_id -> Uuid,
         idempotency_key -> Text,
-        request -> HttpRequest,
+        request -> Nullable<HttpRequest>,
         created_at -> Timestamptz,
     }
 }
 
+diesel::table! {
+    issue_delivery_dead_letters (newsletter_issue_id, subscriber_email) {
+        newsletter_issue_id -> Uuid,
+        subscriber_email -> Text,
+        attempts -> Int2,
+        last_error -> Text,
+        failed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    issue_clicks (id) {
+        id -> Uuid,
+        link_id -> Uuid,
+        subscriber_id -> Uuid,
+        clicked_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    issue_delivery_queue (newsletter_issue_id, subscriber_email) {
+        newsletter_issue_id -> Uuid,
+        subscriber_email -> Text,
+        attempts -> Int2,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    issue_links (id) {
+        id -> Uuid,
+        newsletter_issue_id -> Uuid,
+        url -> Text,
+    }
+}
+
+diesel::table! {
+    issue_opens (id) {
+        id -> Uuid,
+        newsletter_issue_id -> Uuid,
+        subscriber_id -> Uuid,
+        opened_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::NewsletterIssueStatus;
+
+    newsletter_issues (newsletter_issue_id) {
+        newsletter_issue_id -> Uuid,
+        title -> Text,
+        text_content -> Text,
+        html_content -> Text,
+        published_at -> Nullable<Timestamptz>,
+        author_id -> Nullable<Uuid>,
+        status -> NewsletterIssueStatus,
+        created_at -> Timestamptz,
+        scheduled_for -> Nullable<Timestamptz>,
+        recipients_count -> Int4,
+        delivered_count -> Int4,
+        slug -> Nullable<Text>,
+        markdown_content -> Nullable<Text>,
+        tracking_enabled -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::SubscriptionStatus;
+
+    subscription_status_changes (id) {
+        id -> Uuid,
+        subscriber_id -> Uuid,
+        from_status -> SubscriptionStatus,
+        to_status -> SubscriptionStatus,
+        changed_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     subscription_tokens (subscription_token) {
         subscription_token -> Text,
@@ -27,29 +145,98 @@ diesel::table! {
 }
 
 diesel::table! {
+    password_reset_tokens (token_hash) {
+        token_hash -> Text,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::SubscriptionStatus;
+
     subscriptions (id) {
         id -> Uuid,
         email -> Text,
         name -> Text,
         subscribed_at -> Timestamptz,
-        status -> Text,
+        status -> SubscriptionStatus,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::UserRole;
+
+    user_invitations (token_hash) {
+        token_hash -> Text,
+        email -> Text,
+        role -> UserRole,
+        invited_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::UserRole;
+
     users (user_id) {
         user_id -> Uuid,
         username -> Text,
         password_hash -> Text,
+        role -> UserRole,
+        disabled_at -> Nullable<Timestamptz>,
+        email -> Nullable<Text>,
+        totp_secret -> Nullable<Text>,
+        totp_last_step -> Nullable<Int8>,
     }
 }
 
+diesel::joinable!(confirmation_email_queue -> subscription_tokens (subscription_token));
+diesel::joinable!(email_events -> subscriptions (subscriber_id));
 diesel::joinable!(idempotency -> users (user_id));
+diesel::joinable!(issue_clicks -> issue_links (link_id));
+diesel::joinable!(issue_clicks -> subscriptions (subscriber_id));
+diesel::joinable!(issue_delivery_dead_letters -> newsletter_issues (newsletter_issue_id));
+diesel::joinable!(issue_delivery_queue -> newsletter_issues (newsletter_issue_id));
+diesel::joinable!(issue_links -> newsletter_issues (newsletter_issue_id));
+diesel::joinable!(issue_opens -> newsletter_issues (newsletter_issue_id));
+diesel::joinable!(issue_opens -> subscriptions (subscriber_id));
+diesel::joinable!(newsletter_issues -> users (author_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(subscription_status_changes -> subscriptions (subscriber_id));
 diesel::joinable!(subscription_tokens -> subscriptions (subscriber_id));
+diesel::joinable!(user_invitations -> users (invited_by));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    confirmation_email_queue,
+    email_events,
     idempotency,
+    issue_clicks,
+    issue_delivery_dead_letters,
+    issue_delivery_queue,
+    issue_links,
+    issue_opens,
+    newsletter_issues,
+    password_reset_tokens,
+    recovery_codes,
+    subscription_status_changes,
     subscription_tokens,
     subscriptions,
+    user_invitations,
     users,
 );
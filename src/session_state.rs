@@ -3,13 +3,31 @@ use axum::{
     extract::FromRequestParts,
     http::{request::Parts, StatusCode},
 };
-use tower_sessions::Session;
+use rand::{distributions::Alphanumeric, Rng};
+use std::str::FromStr;
+use tower_sessions::{session::Id, Session, SessionStore};
+use tower_sessions_redis_store::{fred::prelude::*, RedisStore};
 use uuid::Uuid;
 
+use crate::utils::constant_time_eq;
+
 pub struct TypedSession(Session);
 
+/// A user who entered the right password but still owes a second factor.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PendingTwoFactor {
+    user_id: Uuid,
+    expires_at: i64,
+}
+
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const TWO_FACTOR_PENDING_KEY: &'static str = "two_factor_pending";
+    const TOTP_ENROLLMENT_KEY: &'static str = "totp_enrollment";
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
+    const CSRF_TOKEN_LENGTH: usize = 32;
+    /// How long the second login step may take.
+    const TWO_FACTOR_TIMEOUT_SECONDS: i64 = 5 * 60;
 
     pub async fn cycle_id(&self) -> Result<(), tower_sessions::session::Error> {
         self.0.cycle_id().await
@@ -31,6 +49,155 @@ impl TypedSession {
     pub async fn logout(&self) -> Result<(), tower_sessions::session::Error> {
         self.0.delete().await
     }
+
+    /// Records that `user_id` passed the password check. Unlike
+    /// `insert_user_id`, this grants no access to the admin area.
+    pub async fn insert_two_factor_pending(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(), tower_sessions::session::Error> {
+        let pending = PendingTwoFactor {
+            user_id,
+            expires_at: chrono::Utc::now().timestamp()
+                + Self::TWO_FACTOR_TIMEOUT_SECONDS,
+        };
+        self.0.insert(Self::TWO_FACTOR_PENDING_KEY, pending).await
+    }
+
+    /// The user waiting for the second login step, unless it timed out.
+    pub async fn get_two_factor_pending(
+        &self,
+    ) -> Result<Option<Uuid>, tower_sessions::session::Error> {
+        let pending: Option<PendingTwoFactor> =
+            self.0.get(Self::TWO_FACTOR_PENDING_KEY).await?;
+        Ok(pending
+            .filter(|pending| {
+                pending.expires_at > chrono::Utc::now().timestamp()
+            })
+            .map(|pending| pending.user_id))
+    }
+
+    pub async fn remove_two_factor_pending(
+        &self,
+    ) -> Result<(), tower_sessions::session::Error> {
+        self.0
+            .remove_value(Self::TWO_FACTOR_PENDING_KEY)
+            .await
+            .map(|_| ())
+    }
+
+    /// Keeps the TOTP secret being enrolled, in base32, until the user
+    /// confirms it with a first code.
+    pub async fn insert_totp_enrollment(
+        &self,
+        secret: String,
+    ) -> Result<(), tower_sessions::session::Error> {
+        self.0.insert(Self::TOTP_ENROLLMENT_KEY, secret).await
+    }
+
+    pub async fn get_totp_enrollment(
+        &self,
+    ) -> Result<Option<String>, tower_sessions::session::Error> {
+        self.0.get(Self::TOTP_ENROLLMENT_KEY).await
+    }
+
+    pub async fn remove_totp_enrollment(
+        &self,
+    ) -> Result<(), tower_sessions::session::Error> {
+        self.0
+            .remove_value(Self::TOTP_ENROLLMENT_KEY)
+            .await
+            .map(|_| ())
+    }
+
+    /// The token forms must post back, created the first time it is needed.
+    pub async fn csrf_token(
+        &self,
+    ) -> Result<String, tower_sessions::session::Error> {
+        if let Some(token) = self.0.get(Self::CSRF_TOKEN_KEY).await? {
+            return Ok(token);
+        }
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::CSRF_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        self.0.insert(Self::CSRF_TOKEN_KEY, &token).await?;
+        Ok(token)
+    }
+
+    pub async fn verify_csrf_token(
+        &self,
+        candidate: &str,
+    ) -> Result<bool, tower_sessions::session::Error> {
+        let token: Option<String> = self.0.get(Self::CSRF_TOKEN_KEY).await?;
+        Ok(token.is_some_and(|token| constant_time_eq(&token, candidate)))
+    }
+
+    /// A template context holding the CSRF token for the page's forms.
+    pub async fn form_context(
+        &self,
+    ) -> Result<tera::Context, tower_sessions::session::Error> {
+        let mut context = tera::Context::new();
+        context.insert("csrf_token", &self.csrf_token().await?);
+        Ok(context)
+    }
+
+    /// Stores the session right away, returning the id it is stored under.
+    pub async fn save(
+        &self,
+    ) -> Result<Option<Id>, tower_sessions::session::Error> {
+        self.0.save().await?;
+        Ok(self.0.id())
+    }
+}
+
+/// Keeps track of the sessions each user logged in with, so that all of them
+/// can be revoked at once.
+#[derive(Clone)]
+pub struct UserSessions {
+    redis: RedisPool,
+    store: RedisStore<RedisPool>,
+}
+
+impl UserSessions {
+    /// Matches how long tower-sessions keeps a session by default.
+    const TTL_SECONDS: i64 = 14 * 24 * 60 * 60;
+
+    pub fn new(redis: RedisPool, store: RedisStore<RedisPool>) -> Self {
+        Self { redis, store }
+    }
+
+    fn key(user_id: Uuid) -> String {
+        format!("user_sessions:{}", user_id)
+    }
+
+    pub async fn register(
+        &self,
+        user_id: Uuid,
+        session_id: Id,
+    ) -> Result<(), RedisError> {
+        let key = Self::key(user_id);
+        let _: () =
+            self.redis.sadd(key.clone(), session_id.to_string()).await?;
+        let _: () = self.redis.expire(key, Self::TTL_SECONDS).await?;
+        Ok(())
+    }
+
+    /// Deletes every session of the user from the store.
+    #[tracing::instrument(name = "Revoke user sessions", skip(self))]
+    pub async fn revoke_all(&self, user_id: Uuid) -> Result<(), anyhow::Error> {
+        let key = Self::key(user_id);
+        let session_ids: Vec<String> = self.redis.smembers(key.clone()).await?;
+        for session_id in session_ids {
+            let Ok(session_id) = Id::from_str(&session_id) else {
+                continue;
+            };
+            self.store.delete(&session_id).await?;
+        }
+        let _: () = self.redis.del(key).await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -1,14 +1,30 @@
-use crate::authentication::check_credentials;
+use crate::authentication::{
+    check_credentials, require_csrf_token, require_role, CsrfProtection,
+    LoginThrottle,
+};
+use crate::client_ip::TrustedProxies;
 use crate::database::{create_connection_pool, DatabaseConnectionPool};
-use crate::{configuration::Settings, email_client::EmailClient, routes};
-use axum::extract::FromRef;
-use axum::middleware;
+use crate::issue_delivery_worker::run_worker_until_stopped;
+use crate::issue_scheduler::run_scheduler_until_stopped;
+use crate::rate_limit::{rate_limit, RateLimiters};
+use crate::session_state::UserSessions;
+use crate::{
+    configuration::{ApplicationSettings, Settings, WebhookSettings},
+    domain::UserRole,
+    email_client::EmailClient,
+    routes,
+};
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, FromRef};
+use axum::middleware::{self, AddExtension};
 use axum::response::Response;
 use axum::{extract::Request, routing, serve::Serve, Router};
 use axum_extra::extract::cookie::Key;
 use diesel_async::{pooled_connection::deadpool::Pool, AsyncPgConnection};
 use secrecy::ExposeSecret;
 use secrecy::Secret;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpListener;
@@ -24,18 +40,12 @@ pub async fn run(
     listener: TcpListener,
     connection_pool: Pool<AsyncPgConnection>,
     email_client: Arc<EmailClient>,
-    base_url: String,
-    key: Key,
-    redis_uri: Secret<String>,
-) -> Result<(Serve<Router, Router>, RedisConnection), anyhow::Error> {
-    let app_state = ApplicationState {
-        database_pool: connection_pool,
-        email_client,
-        base_url,
-        key,
-    };
+    settings: ApplicationSettings,
+    email_webhook: WebhookSettings,
+) -> Result<(Server, RedisConnection), anyhow::Error> {
+    let key = Key::from(settings.hmac_secret.expose_secret().as_bytes());
     let redis_pool = RedisPool::new(
-        RedisConfig::from_url(redis_uri.expose_secret())?,
+        RedisConfig::from_url(settings.redis_uri.expose_secret())?,
         None,
         None,
         None,
@@ -43,8 +53,42 @@ pub async fn run(
     )?;
     let redis_connection = redis_pool.connect();
     redis_pool.wait_for_connect().await?;
-    let session_store = RedisStore::new(redis_pool);
+    let trusted_proxies = TrustedProxies::new(settings.trusted_proxies);
+    let rate_limiters = RateLimiters::new(
+        settings.rate_limits.backend,
+        redis_pool.clone(),
+        trusted_proxies.clone(),
+    );
+    let limits = settings.rate_limits;
+    let import_body_limit = settings.import_body_limit_bytes;
+    let limited = |scope, limit| {
+        middleware::from_fn_with_state(
+            rate_limiters.limiter(scope, limit),
+            rate_limit,
+        )
+    };
+    let csrf_protected = |redirect_to| {
+        middleware::from_fn_with_state(
+            CsrfProtection::new(key.clone(), redirect_to),
+            require_csrf_token,
+        )
+    };
+    let session_store = RedisStore::new(redis_pool.clone());
+    let login_throttle =
+        LoginThrottle::new(redis_pool.clone(), settings.login_throttle);
+    let user_sessions = UserSessions::new(redis_pool, session_store.clone());
     let session_layer = SessionManagerLayer::new(session_store);
+    let app_state = ApplicationState {
+        database_pool: connection_pool,
+        email_client,
+        base_url: settings.base_url,
+        hmac_secret: HmacSecret(settings.hmac_secret),
+        email_webhook,
+        user_sessions,
+        login_throttle,
+        key: key.clone(),
+        trusted_proxies,
+    };
     let tracing_layer = TraceLayer::new_for_http().make_span_with(
             |request: &Request<_>| {
                 let request_id = Uuid::now_v7();
@@ -53,31 +97,222 @@ pub async fn run(
         ).on_response(|response: &Response, _latency: Duration, span: &Span|{
                 span.record("response_code", response.status().as_str());
             });
-    let admin_routes = Router::new()
+    // Pages every admin user can read.
+    let viewer_routes = Router::new()
         .route("/admin/dashboard", routing::get(routes::admin_dashboard))
         .route("/admin/password", routing::get(routes::reset_password_form))
         .route("/admin/password", routing::post(routes::change_pasword))
+        .route(
+            "/admin/two-factor",
+            routing::get(routes::two_factor_settings),
+        )
+        .route(
+            "/admin/two-factor/enable",
+            routing::post(routes::enable_two_factor),
+        )
+        .route(
+            "/admin/two-factor/disable",
+            routing::post(routes::disable_two_factor),
+        )
         .route("/admin/logout", routing::post(routes::logout))
+        .route(
+            "/admin/deliveries/events",
+            routing::get(routes::email_events_page),
+        )
+        .route(
+            "/admin/deliveries/failed",
+            routing::get(routes::failed_deliveries_page),
+        )
+        .route("/admin/issues", routing::get(routes::issues_page))
+        .route("/admin/issues/:issue_id", routing::get(routes::issue_page))
+        .route("/admin/subscribers", routing::get(routes::subscribers_page))
+        .route(
+            "/admin/subscribers/:subscriber_id",
+            routing::get(routes::subscriber_page),
+        );
+    // Writing and publishing issues, and managing subscribers.
+    let editor_routes = Router::new()
         .route(
             "/admin/newsletters",
             routing::post(routes::publish_newsletter),
         )
         .route("/admin/newsletters", routing::get(routes::newsletters_form))
-        .layer(
-            ServiceBuilder::new()
-                .layer(session_layer.clone())
-                .layer(middleware::from_fn(check_credentials)),
+        .route(
+            "/admin/newsletters/preview",
+            routing::post(routes::preview_newsletter),
+        )
+        .route(
+            "/admin/deliveries/failed/requeue",
+            routing::post(routes::requeue_failed_delivery),
+        )
+        .route("/admin/issues", routing::post(routes::create_issue))
+        .route("/admin/issues/new", routing::get(routes::new_issue_form))
+        .route(
+            "/admin/issues/:issue_id",
+            routing::post(routes::update_issue),
+        )
+        .route(
+            "/admin/issues/:issue_id/schedule",
+            routing::post(routes::schedule_issue_delivery),
+        )
+        .route(
+            "/admin/issues/:issue_id/unschedule",
+            routing::post(routes::unschedule_issue_delivery),
+        )
+        .route(
+            "/admin/issues/:issue_id/publish",
+            routing::post(routes::publish_draft),
+        )
+        .route(
+            "/admin/subscribers/export",
+            routing::get(routes::export_subscribers),
+        )
+        .route(
+            "/admin/subscribers/:subscriber_id/confirm",
+            routing::post(routes::confirm_subscriber),
+        )
+        .route(
+            "/admin/subscribers/:subscriber_id/unsubscribe",
+            routing::post(routes::unsubscribe_subscriber),
+        )
+        .route(
+            "/admin/subscribers/:subscriber_id/delete",
+            routing::post(routes::remove_subscriber),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            UserRole::Editor,
+            require_role,
+        ));
+    // Uploads are larger than any form, so the import checks its CSRF token
+    // itself instead of under the limit shared by the other admin routes.
+    let import_routes = Router::new()
+        .route(
+            "/admin/subscribers/import",
+            routing::post(routes::import_subscribers)
+                .layer(DefaultBodyLimit::max(import_body_limit)),
         )
+        .route_layer(middleware::from_fn_with_state(
+            CsrfProtection::new(key.clone(), "/admin/dashboard")
+                .with_body_limit(import_body_limit),
+            require_csrf_token,
+        ))
+        .route(
+            "/admin/subscribers/import",
+            routing::get(routes::import_subscribers_form),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            UserRole::Editor,
+            require_role,
+        ));
+    // Managing the admin users themselves.
+    let owner_routes = Router::new()
+        .route("/admin/users", routing::get(routes::users_page))
+        .route("/admin/users/invite", routing::post(routes::invite_user))
+        .route(
+            "/admin/users/invitations/:token_hash/revoke",
+            routing::post(routes::revoke_user_invitation),
+        )
+        .route(
+            "/admin/users/:user_id/disable",
+            routing::post(routes::disable_user),
+        )
+        .route(
+            "/admin/users/:user_id/enable",
+            routing::post(routes::enable_user),
+        )
+        .route(
+            "/admin/users/:user_id/delete",
+            routing::post(routes::remove_user),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            UserRole::Owner,
+            require_role,
+        ));
+    let admin_routes = viewer_routes
+        .merge(editor_routes)
+        .merge(owner_routes)
+        .route_layer(csrf_protected("/admin/dashboard"))
+        .merge(import_routes)
+        .layer(ServiceBuilder::new().layer(session_layer.clone()).layer(
+            middleware::from_fn_with_state(
+                app_state.clone(),
+                check_credentials,
+            ),
+        ))
         .layer(tracing_layer.clone())
         .with_state(app_state.clone());
 
     let basic_routes: Router = Router::new()
         .route("/", routing::get(routes::home))
         .route("/health_check", routing::get(routes::health_check))
-        .route("/subscriptions", routing::post(routes::subscriptions))
-        .route("/subscriptions/confirm", routing::get(routes::confirm))
+        .route(
+            "/subscriptions",
+            routing::post(routes::subscriptions)
+                .layer(limited("subscriptions", limits.subscriptions)),
+        )
+        .route(
+            "/subscriptions/confirm",
+            routing::get(routes::confirm).layer(limited(
+                "subscription_confirm",
+                limits.subscription_confirm,
+            )),
+        )
+        // Shares the bucket of `/subscriptions`, as it sends email too.
+        .route(
+            "/subscriptions/resend",
+            routing::post(routes::resend_confirmation)
+                .layer(limited("subscriptions", limits.subscriptions)),
+        )
+        .route(
+            "/subscriptions/unsubscribe",
+            routing::get(routes::unsubscribe_form),
+        )
+        .route(
+            "/subscriptions/unsubscribe",
+            routing::post(routes::unsubscribe),
+        )
+        .route("/issues", routing::get(routes::archive_page))
+        .route("/issues/:slug", routing::get(routes::archived_issue_page))
+        .route("/feed.rss", routing::get(routes::rss_feed))
+        .route("/feed.atom", routing::get(routes::atom_feed))
+        .route("/t/o/:token", routing::get(routes::track_open))
+        .route("/t/c/:link_id", routing::get(routes::track_click))
+        .route("/webhooks/email", routing::post(routes::email_webhook))
         .route("/login", routing::get(routes::login_form))
-        .route("/login", routing::post(routes::login))
+        .route(
+            "/login",
+            routing::post(routes::login)
+                .layer(csrf_protected("/login"))
+                .layer(limited("login", limits.login)),
+        )
+        .route("/login/forgot", routing::get(routes::forgot_password_form))
+        .route(
+            "/login/forgot",
+            routing::post(routes::forgot_password)
+                .layer(csrf_protected("/login/forgot")),
+        )
+        .route("/login/two-factor", routing::get(routes::two_factor_form))
+        .route(
+            "/login/two-factor",
+            routing::post(routes::verify_two_factor)
+                .layer(csrf_protected("/login/two-factor")),
+        )
+        .route("/login/reset", routing::get(routes::password_reset_form))
+        .route(
+            "/login/reset",
+            routing::post(routes::reset_forgotten_password)
+                .layer(csrf_protected("/login/forgot")),
+        )
+        .route("/signup", routing::get(routes::signup_form))
+        .route(
+            "/signup",
+            routing::post(routes::signup).layer(csrf_protected("/login")),
+        )
+        .route("/setup", routing::get(routes::setup_form))
+        .route(
+            "/setup",
+            routing::post(routes::setup).layer(csrf_protected("/setup")),
+        )
         .layer(session_layer)
         .layer(tracing_layer)
         .with_state(app_state);
@@ -85,7 +320,12 @@ pub async fn run(
     let app = basic_routes.merge(admin_routes);
 
     //    redis_connection.await??;
-    Ok((axum::serve(listener, app), redis_connection))
+    // The peer address is the client, unless it is a trusted proxy.
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    );
+    Ok((server, redis_connection))
 }
 
 #[derive(Clone)]
@@ -93,20 +333,47 @@ pub struct ApplicationState {
     pub database_pool: DatabaseConnectionPool,
     pub email_client: Arc<EmailClient>,
     pub base_url: String,
+    pub hmac_secret: HmacSecret,
+    pub email_webhook: WebhookSettings,
+    pub user_sessions: UserSessions,
+    pub login_throttle: LoginThrottle,
     key: Key,
+    trusted_proxies: TrustedProxies,
 }
 impl FromRef<ApplicationState> for Key {
     fn from_ref(state: &ApplicationState) -> Self {
         state.key.clone()
     }
 }
+impl FromRef<ApplicationState> for WebhookSettings {
+    fn from_ref(state: &ApplicationState) -> Self {
+        state.email_webhook.clone()
+    }
+}
+impl FromRef<ApplicationState> for TrustedProxies {
+    fn from_ref(state: &ApplicationState) -> Self {
+        state.trusted_proxies.clone()
+    }
+}
+impl FromRef<ApplicationState> for HmacSecret {
+    fn from_ref(state: &ApplicationState) -> Self {
+        state.hmac_secret.clone()
+    }
+}
 
+type Server = Serve<
+    IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    AddExtension<Router, ConnectInfo<SocketAddr>>,
+>;
 type RedisConnection = JoinHandle<Result<(), RedisError>>;
+type BackgroundTask = JoinHandle<Result<(), anyhow::Error>>;
 pub struct Application {
     port: u16,
     pool: Pool<AsyncPgConnection>,
-    server: Serve<Router, Router>,
+    server: Server,
     redis_connection_handle: RedisConnection,
+    delivery_worker_handle: Option<BackgroundTask>,
+    scheduler_handle: Option<BackgroundTask>,
 }
 
 #[derive(Clone)]
@@ -120,16 +387,8 @@ impl Application {
             "{}:{}",
             configuration.application.host, configuration.application.port
         );
-        let timeout = configuration.email_client.timeout();
-        let email_client = EmailClient::new(
-            &configuration.email_client.base_url,
-            configuration
-                .email_client
-                .sender()
-                .expect("Sender email invalid"),
-            configuration.email_client.api_token,
-            timeout,
-        );
+        let email_client = configuration.email_client.client()?;
+        let email_webhook = configuration.email_client.webhook;
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         tracing::info!(
@@ -140,21 +399,30 @@ impl Application {
             configuration.database.connection_string().expose_secret(),
         );
         let port = listener.local_addr().unwrap().port();
-        let key = Key::from(
-            configuration
-                .application
-                .hmac_secret
-                .expose_secret()
-                .as_bytes(),
-        );
+        let hmac_secret =
+            HmacSecret(configuration.application.hmac_secret.clone());
         let pool_clone = pool.clone();
+        let email_client = Arc::new(email_client);
+        let (delivery_worker_handle, scheduler_handle) =
+            if configuration.application.background_workers {
+                let delivery_worker = tokio::spawn(run_worker_until_stopped(
+                    pool.clone(),
+                    email_client.clone(),
+                    configuration.application.base_url.clone(),
+                    hmac_secret,
+                ));
+                let scheduler =
+                    tokio::spawn(run_scheduler_until_stopped(pool.clone()));
+                (Some(delivery_worker), Some(scheduler))
+            } else {
+                (None, None)
+            };
         let (server, redis_connection_handle) = run(
             listener,
             pool,
-            Arc::new(email_client),
-            configuration.application.base_url,
-            key,
-            configuration.application.redis_uri,
+            email_client,
+            configuration.application,
+            email_webhook,
         )
         .await?;
 
@@ -163,6 +431,8 @@ impl Application {
             server,
             port,
             redis_connection_handle,
+            delivery_worker_handle,
+            scheduler_handle,
         })
     }
 
@@ -175,8 +445,22 @@ impl Application {
     }
 
     pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
-        self.server.await?;
+        tokio::select! {
+            outcome = self.server.into_future() => outcome?,
+            outcome = join_background_task(self.delivery_worker_handle) => outcome?,
+            outcome = join_background_task(self.scheduler_handle) => outcome?,
+        };
         self.redis_connection_handle.await??;
         Ok(())
     }
 }
+
+/// Waits for a background task, or forever when it was not started.
+async fn join_background_task(
+    task: Option<BackgroundTask>,
+) -> Result<(), anyhow::Error> {
+    match task {
+        Some(task) => task.await?,
+        None => std::future::pending().await,
+    }
+}
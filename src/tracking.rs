@@ -0,0 +1,113 @@
+use crate::domain::TrackingToken;
+use linkify::{LinkFinder, LinkKind};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// The absolute URLs the `href` attributes of an issue point to.
+pub fn find_links(html: &str) -> Vec<String> {
+    href_links(html)
+        .into_iter()
+        .map(|(_, _, url)| url)
+        .collect()
+}
+
+/// Sends the links found by `find_links` through the click redirect.
+///
+/// `links` maps each URL to its id; anything else is left untouched.
+pub fn rewrite_links(
+    html: &str,
+    links: &HashMap<String, Uuid>,
+    base_url: &str,
+    token: &TrackingToken,
+) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut last = 0;
+    for (start, end, url) in href_links(html) {
+        if let Some(link_id) = links.get(&url) {
+            rewritten.push_str(&html[last..start]);
+            rewritten.push_str(&click_url(base_url, *link_id, token));
+            last = end;
+        }
+    }
+    rewritten.push_str(&html[last..]);
+    rewritten
+}
+
+pub fn click_url(
+    base_url: &str,
+    link_id: Uuid,
+    token: &TrackingToken,
+) -> String {
+    format!("{}/t/c/{}?token={}", base_url, link_id, token.as_ref())
+}
+
+pub fn open_pixel_url(base_url: &str, token: &TrackingToken) -> String {
+    format!("{}/t/o/{}", base_url, token.as_ref())
+}
+
+/// Finds the URLs making up a whole `href` value, along with where they
+/// start and end.
+fn href_links(html: &str) -> Vec<(usize, usize, String)> {
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url]);
+    finder
+        .links(html)
+        .filter_map(|link| {
+            let before = &html[..link.start()];
+            let quote = if before.ends_with("href=\"") {
+                '"'
+            } else if before.ends_with("href='") {
+                '\''
+            } else {
+                return None;
+            };
+            if !html[link.end()..].starts_with(quote) {
+                return None;
+            }
+            let url = link.as_str().replace("&amp;", "&");
+            Some((link.start(), link.end(), url))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_links, rewrite_links};
+    use crate::domain::TrackingToken;
+    use secrecy::Secret;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    const HTML: &str = "<p>Read <a href=\"https://example.com/a?x=1&amp;y=2\">\
+        this</a> at https://example.com/text or <a href='/relative'>here</a>\
+        </p>";
+
+    #[test]
+    fn only_absolute_hrefs_are_links() {
+        assert_eq!(find_links(HTML), vec!["https://example.com/a?x=1&y=2"]);
+    }
+
+    #[test]
+    fn links_go_through_the_click_redirect() {
+        let link_id = Uuid::now_v7();
+        let links = HashMap::from([(
+            "https://example.com/a?x=1&y=2".to_string(),
+            link_id,
+        )]);
+        let token = TrackingToken::generate(
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            &Secret::new("secret".to_string()),
+        );
+
+        let html = rewrite_links(HTML, &links, "http://localhost", &token);
+
+        assert!(html.contains(&format!(
+            "<a href=\"http://localhost/t/c/{}?token={}\">this</a>",
+            link_id,
+            token.as_ref()
+        )));
+        assert!(html.contains("at https://example.com/text or"));
+        assert!(html.contains("<a href='/relative'>"));
+    }
+}
@@ -0,0 +1,117 @@
+use axum_newsletter::domain::UserRole;
+use axum_newsletter::schema::{newsletter_issues, users};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.request_client
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+async fn issue_count(app: &TestApp) -> i64 {
+    let mut connection = app.pool.get().await.unwrap();
+    newsletter_issues::table
+        .count()
+        .get_result(&mut connection)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn viewers_can_read_the_admin_area() {
+    let app = spawn_app(None).await;
+    app.login_with_role(UserRole::Viewer).await;
+
+    for path in [
+        "/admin/dashboard",
+        "/admin/issues",
+        "/admin/subscribers",
+        "/admin/deliveries/failed",
+        "/admin/deliveries/events",
+        "/admin/password",
+    ] {
+        assert_eq!(get(&app, path).await.status().as_u16(), 200, "{}", path);
+    }
+    let dashboard = app.get_admin_dashboard_html().await;
+    assert!(dashboard.contains("(viewer)"));
+    assert!(!dashboard.contains("Issue newsletter"));
+}
+
+#[tokio::test]
+async fn viewers_cannot_publish_newsletters() {
+    let app = spawn_app(None).await;
+    app.login_with_role(UserRole::Viewer).await;
+
+    let form = get(&app, "/admin/newsletters").await;
+    let response = app
+        .post_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "content_markdown": "Newsletter body",
+            "idempotency_key": uuid::Uuid::now_v7().to_string()
+        }))
+        .await;
+
+    assert_eq!(form.status().as_u16(), 403);
+    assert_eq!(response.status().as_u16(), 403);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Access denied"));
+    assert!(html.contains("It needs the editor role."));
+    assert_eq!(issue_count(&app).await, 0);
+}
+
+#[tokio::test]
+async fn viewers_cannot_change_subscribers_or_drafts() {
+    let app = spawn_app(None).await;
+    app.login_with_role(UserRole::Viewer).await;
+
+    let draft = app
+        .post_issue("", &serde_json::json!({"title": "Draft"}))
+        .await;
+    let export = get(&app, "/admin/subscribers/export").await;
+
+    assert_eq!(draft.status().as_u16(), 403);
+    assert_eq!(export.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn editors_can_write_drafts() {
+    let app = spawn_app(None).await;
+    app.login_with_role(UserRole::Editor).await;
+
+    let form = get(&app, "/admin/newsletters").await;
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": "Draft",
+                "content_markdown": "Draft body",
+            }),
+        )
+        .await;
+
+    assert_eq!(form.status().as_u16(), 200);
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(issue_count(&app).await, 1);
+    let dashboard = app.get_admin_dashboard_html().await;
+    assert!(dashboard.contains("Issue newsletter"));
+}
+
+#[tokio::test]
+async fn deleted_users_are_logged_out() {
+    let app = spawn_app(None).await;
+    let user = app.login_with_role(UserRole::Editor).await;
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::delete(users::table.find(user.user_id))
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+    let response = app.get_admin_dashboard().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
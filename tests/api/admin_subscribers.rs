@@ -0,0 +1,232 @@
+use axum_newsletter::domain::SubscriptionStatus;
+use axum_newsletter::models::Subscriptions;
+use axum_newsletter::schema::subscriptions;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::helpers::{
+    assert_is_redirect_to, check_subscriber_existance, spawn_app, TestApp,
+};
+
+async fn insert_subscriber(
+    app: &TestApp,
+    email: &str,
+    name: &str,
+    status: SubscriptionStatus,
+) -> Uuid {
+    let mut subscriber = Subscriptions::new(email.into(), name.into());
+    subscriber.status = status;
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::insert_into(subscriptions::table)
+        .values(&subscriber)
+        .execute(&mut connection)
+        .await
+        .unwrap();
+    subscriber.id
+}
+
+async fn subscriber_status(app: &TestApp, email: &str) -> SubscriptionStatus {
+    let mut connection = app.pool.get().await.unwrap();
+    check_subscriber_existance(&mut connection, email)
+        .await
+        .pop()
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_subscribers() {
+    let app = spawn_app(None).await;
+
+    let response = app.get_subscribers("").await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_subscribers_export("").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_and_filtered_by_status() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "Ursula Le Guin",
+        SubscriptionStatus::Confirmed,
+    )
+    .await;
+    insert_subscriber(
+        &app,
+        "octavia@example.com",
+        "Octavia Butler",
+        SubscriptionStatus::Pending,
+    )
+    .await;
+
+    let html_page = app.get_subscribers_html("").await;
+    assert!(html_page.contains("ursula@example.com"));
+    assert!(html_page.contains("octavia@example.com"));
+
+    let html_page = app.get_subscribers_html("search=le%20guin").await;
+    assert!(html_page.contains("ursula@example.com"));
+    assert!(!html_page.contains("octavia@example.com"));
+
+    let html_page = app.get_subscribers_html("status=pending").await;
+    assert!(!html_page.contains("ursula@example.com"));
+    assert!(html_page.contains("octavia@example.com"));
+
+    let response = app.get_subscribers("status=sleeping").await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    for i in 0..55 {
+        insert_subscriber(
+            &app,
+            &format!("subscriber{:02}@example.com", i),
+            "Subscriber",
+            SubscriptionStatus::Confirmed,
+        )
+        .await;
+    }
+
+    let first_page = app.get_subscribers_html("").await;
+    assert!(first_page.contains("Page 1 of 2"));
+    assert!(first_page.contains(r#"page=2">Next</a>"#));
+    let second_page = app.get_subscribers_html("page=2").await;
+    assert!(second_page.contains("Page 2 of 2"));
+    let rows = |page: &str| page.matches("<td><a href=").count();
+    assert_eq!(rows(&first_page), 50);
+    assert_eq!(rows(&second_page), 5);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_confirmed_and_unsubscribed_manually() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let email = "ursula@example.com";
+    let subscriber_id = insert_subscriber(
+        &app,
+        email,
+        "Ursula Le Guin",
+        SubscriptionStatus::Pending,
+    )
+    .await;
+    let detail_page = format!("/admin/subscribers/{}", subscriber_id);
+
+    let response = app.post_subscriber_action(&subscriber_id, "confirm").await;
+    assert_is_redirect_to(&response, &detail_page);
+    assert_eq!(
+        subscriber_status(&app, email).await,
+        SubscriptionStatus::Confirmed
+    );
+
+    let response = app
+        .post_subscriber_action(&subscriber_id, "unsubscribe")
+        .await;
+    assert_is_redirect_to(&response, &detail_page);
+    let html_page = app.get_subscriber_html(&subscriber_id).await;
+    assert!(html_page.contains("The subscriber is now unsubscribed."));
+    assert!(html_page.contains("<td>pending</td>"));
+    assert!(html_page.contains("<td>confirmed</td>"));
+    assert!(html_page.contains("<td>unsubscribed</td>"));
+
+    // Coming back requires the subscriber to confirm their address again.
+    app.post_subscriber_action(&subscriber_id, "confirm").await;
+    let html_page = app.get_subscriber_html(&subscriber_id).await;
+    assert!(html_page
+        .contains("A subscriber cannot go from unsubscribed to confirmed."));
+    assert_eq!(
+        subscriber_status(&app, email).await,
+        SubscriptionStatus::Unsubscribed
+    );
+}
+
+#[tokio::test]
+async fn subscribers_can_be_deleted() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let subscriber_id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "Ursula Le Guin",
+        SubscriptionStatus::Confirmed,
+    )
+    .await;
+
+    let response = app.post_subscriber_action(&subscriber_id, "delete").await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html_page = app.get_subscribers_html("").await;
+    assert!(html_page.contains("The subscriber has been deleted."));
+    assert!(!html_page.contains("ursula@example.com"));
+
+    let response = app.post_subscriber_action(&subscriber_id, "delete").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_exported_as_csv() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "Le Guin, Ursula",
+        SubscriptionStatus::Confirmed,
+    )
+    .await;
+    insert_subscriber(
+        &app,
+        "octavia@example.com",
+        "Octavia Butler",
+        SubscriptionStatus::Pending,
+    )
+    .await;
+
+    let response = app.get_subscribers_export("status=confirmed").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    let mut lines = body.lines();
+    assert_eq!(lines.next(), Some("id,email,name,status,subscribed_at"));
+    let row = lines.next().unwrap();
+    assert!(row.contains(r#"ursula@example.com,"Le Guin, Ursula",confirmed"#));
+    assert_eq!(lines.next(), None);
+}
+
+#[tokio::test]
+async fn large_exports_are_complete_across_chunks() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    // More than one chunk, all sharing a timestamp so the cursor ties.
+    let subscribed_at = chrono::Utc::now();
+    let subscribers: Vec<Subscriptions> = (0..1_201)
+        .map(|i| {
+            let mut subscriber = Subscriptions::new(
+                format!("reader{}@example.com", i),
+                format!("Reader {}", i),
+            );
+            subscriber.subscribed_at = subscribed_at;
+            subscriber
+        })
+        .collect();
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::insert_into(subscriptions::table)
+        .values(&subscribers)
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+    let response = app.get_subscribers_export("").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    let rows: std::collections::HashSet<&str> = body.lines().skip(1).collect();
+    assert_eq!(rows.len(), 1_201);
+    assert_eq!(body.lines().count(), 1_202);
+}
@@ -0,0 +1,199 @@
+use axum_newsletter::domain::SubscriptionStatus;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, check_subscriber_existance, spawn_app, TestApp,
+    IMPORT_BODY_LIMIT,
+};
+
+async fn subscriber_status(
+    app: &TestApp,
+    email: &str,
+) -> Option<SubscriptionStatus> {
+    let mut connection = app.pool.get().await.unwrap();
+    check_subscriber_existance(&mut connection, email)
+        .await
+        .pop()
+        .map(|subscriber| subscriber.status)
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .post_subscriber_import("list.csv", "email,name\n", "leave_pending")
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn csv_imports_report_accepted_duplicate_and_rejected_rows() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let csv = "email,name\n\
+               ursula@example.com,Ursula Le Guin\n\
+               not-an-email,Someone\n\
+               octavia@example.com,Octavia Butler\n\
+               ursula@example.com,Ursula Again\n";
+    let response = app
+        .post_subscriber_import("list.csv", csv, "pre_confirmed")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 2);
+    assert_eq!(report["duplicates"], 1);
+    assert_eq!(report["rejected"], 1);
+    let outcomes: Vec<&str> = report["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row["outcome"].as_str().unwrap())
+        .collect();
+    assert_eq!(outcomes, ["accepted", "rejected", "accepted", "duplicate"]);
+    assert_eq!(report["rows"][1]["row"], 2);
+    assert_eq!(
+        subscriber_status(&app, "ursula@example.com").await,
+        Some(SubscriptionStatus::Confirmed)
+    );
+    assert_eq!(subscriber_status(&app, "not-an-email").await, None);
+}
+
+#[tokio::test]
+async fn existing_subscribers_are_reported_as_duplicates() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let json = r#"[{"email": "ursula@example.com", "name": "Ursula"}]"#;
+    app.post_subscriber_import("list.json", json, "leave_pending")
+        .await;
+
+    let response = app
+        .post_subscriber_import("list.json", json, "pre_confirmed")
+        .await;
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 0);
+    assert_eq!(report["duplicates"], 1);
+    assert_eq!(
+        subscriber_status(&app, "ursula@example.com").await,
+        Some(SubscriptionStatus::Pending)
+    );
+}
+
+#[tokio::test]
+async fn json_imports_can_send_confirmation_emails() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let json = r#"[
+        {"email": "ursula@example.com", "name": "Ursula Le Guin"},
+        {"email": "octavia@example.com", "name": "Octavia Butler"},
+        {"email": "nameless@example.com"}
+    ]"#;
+    let response = app
+        .post_subscriber_import("list.json", json, "send_email")
+        .await;
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 2);
+    assert_eq!(report["rejected"], 1);
+    assert!(report["rows"][2]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("name"));
+    // The emails are left to the delivery worker.
+    assert!(app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty());
+
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    assert_eq!(
+        subscriber_status(&app, "ursula@example.com").await,
+        Some(SubscriptionStatus::Confirmed)
+    );
+}
+
+#[tokio::test]
+async fn uploads_without_the_expected_columns_are_rejected_with_a_400() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_subscriber_import(
+            "list.csv",
+            "address\nursula@example.com\n",
+            "leave_pending",
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_subscriber_import("list.json", "{}", "leave_pending")
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn uploads_over_the_configured_limit_are_rejected_with_a_413() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let mut csv = String::from("email,name\n");
+    while csv.len() <= IMPORT_BODY_LIMIT {
+        csv.push_str("someone@example.com,Someone\n");
+    }
+
+    let response = app
+        .post_subscriber_import("list.csv", &csv, "leave_pending")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 413);
+    assert_eq!(subscriber_status(&app, "someone@example.com").await, None);
+}
+
+#[tokio::test]
+async fn imports_without_a_csrf_token_are_rejected() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let file =
+        reqwest::multipart::Part::text("email,name\nana@example.com,Ana\n")
+            .file_name("list.csv");
+    let form = reqwest::multipart::Form::new()
+        .part("file", file)
+        .text("confirmation", "leave_pending");
+
+    let response = app
+        .request_client
+        .post(&format!("{}/admin/subscribers/import", &app.address))
+        .multipart(form)
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert_eq!(subscriber_status(&app, "ana@example.com").await, None);
+}
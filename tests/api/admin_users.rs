@@ -0,0 +1,402 @@
+use axum_newsletter::domain::UserRole;
+use axum_newsletter::schema::{user_invitations, users};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{
+    assert_is_redirect_to, csrf_token, spawn_app, TestApp, TestUser,
+};
+
+async fn post<Body>(app: &TestApp, path: &str, body: &Body) -> reqwest::Response
+where
+    Body: serde::Serialize,
+{
+    app.request_client
+        .post(&format!("{}{}", &app.address, path))
+        .form(&app.with_csrf_token(body).await)
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+/// Logs `user` in from `client`, a browser other than the app's own.
+async fn login(
+    app: &TestApp,
+    client: &reqwest::Client,
+    user: &TestUser,
+) -> reqwest::Response {
+    client
+        .post(&format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &user.username,
+            "password": &user.password,
+            "csrf_token": csrf_token(client, &app.address).await
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.request_client
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+/// Invites `email` as `role`, returning the signup link from the email.
+async fn invite(app: &TestApp, email: &str, role: UserRole) -> reqwest::Url {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = post(
+        app,
+        "/admin/users/invite",
+        &serde_json::json!({"email": email, "role": role.as_str()}),
+    )
+    .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(&email_request).await.html
+}
+
+/// Another browser, logged in as a freshly stored user with `role`.
+async fn second_session(
+    app: &TestApp,
+    role: UserRole,
+) -> (TestUser, reqwest::Client) {
+    let user = TestUser::with_role(role);
+    let mut connection = app.pool.get().await.unwrap();
+    user.store(&mut connection).await;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    login(app, &client, &user).await;
+    (user, client)
+}
+
+#[tokio::test]
+async fn only_owners_can_manage_users() {
+    let app = spawn_app(None).await;
+    app.login_with_role(UserRole::Editor).await;
+
+    let page = get(&app, "/admin/users").await;
+    let invite = post(
+        &app,
+        "/admin/users/invite",
+        &serde_json::json!({"email": "new@example.com", "role": "owner"}),
+    )
+    .await;
+
+    assert_eq!(page.status().as_u16(), 403);
+    assert_eq!(invite.status().as_u16(), 403);
+    assert!(!app
+        .get_admin_dashboard_html()
+        .await
+        .contains("/admin/users"));
+}
+
+#[tokio::test]
+async fn invited_users_can_sign_up_with_the_invited_role() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let signup_link =
+        invite(&app, "editor@example.com", UserRole::Editor).await;
+    assert!(get(&app, "/admin/users")
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("editor@example.com"));
+    app.post_logout().await;
+
+    let form = app
+        .request_client
+        .get(signup_link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(form.contains("editor@example.com"));
+    let token = signup_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string();
+    let response = post(
+        &app,
+        "/signup",
+        &serde_json::json!({
+            "token": token,
+            "username": "new-editor",
+            "password": "a-long-password",
+            "password_check": "a-long-password"
+        }),
+    )
+    .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "new-editor",
+            "password": "a-long-password"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert!(app.get_admin_dashboard_html().await.contains("(editor)"));
+}
+
+#[tokio::test]
+async fn invitations_can_only_be_used_once() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let signup_link =
+        invite(&app, "viewer@example.com", UserRole::Viewer).await;
+    let token = signup_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string();
+    let signup = |username: &'static str| {
+        serde_json::json!({
+            "token": token,
+            "username": username,
+            "password": "a-long-password",
+            "password_check": "a-long-password"
+        })
+    };
+
+    let first = post(&app, "/signup", &signup("first")).await;
+    let second = post(&app, "/signup", &signup("second")).await;
+    let form = app.request_client.get(signup_link).send().await.unwrap();
+
+    assert_is_redirect_to(&first, "/login");
+    assert_eq!(second.status().as_u16(), 404);
+    assert_eq!(form.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn only_the_hash_of_the_invitation_token_is_stored() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let signup_link =
+        invite(&app, "viewer@example.com", UserRole::Viewer).await;
+    let token = signup_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string();
+
+    let mut connection = app.pool.get().await.unwrap();
+    let stored: Vec<String> = user_invitations::table
+        .select(user_invitations::token_hash)
+        .load(&mut connection)
+        .await
+        .unwrap();
+
+    assert_eq!(stored.len(), 1);
+    assert_ne!(stored[0], token);
+}
+
+#[tokio::test]
+async fn revoked_invitations_can_no_longer_be_used() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let signup_link =
+        invite(&app, "viewer@example.com", UserRole::Viewer).await;
+    let users_page = get(&app, "/admin/users").await.text().await.unwrap();
+    let revoke_path = users_page
+        .split('"')
+        .find(|part| part.starts_with("/admin/users/invitations/"))
+        .unwrap()
+        .to_string();
+
+    let response = post(&app, &revoke_path, &serde_json::json!({})).await;
+
+    assert_is_redirect_to(&response, "/admin/users");
+    let form = app.request_client.get(signup_link).send().await.unwrap();
+    assert_eq!(form.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn signup_rejects_mismatched_passwords() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let signup_link =
+        invite(&app, "viewer@example.com", UserRole::Viewer).await;
+    let token = signup_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string();
+
+    let response = post(
+        &app,
+        "/signup",
+        &serde_json::json!({
+            "token": token,
+            "username": "viewer",
+            "password": "a-long-password",
+            "password_check": "another-password"
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let html = app
+        .request_client
+        .get(signup_link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("two different passwords"));
+}
+
+#[tokio::test]
+async fn disabled_users_lose_access_until_enabled() {
+    let app = spawn_app(None).await;
+    let (user, client) = second_session(&app, UserRole::Editor).await;
+    let dashboard = format!("{}/admin/dashboard", &app.address);
+    assert_eq!(client.get(&dashboard).send().await.unwrap().status(), 200);
+    app.login_test_user().await;
+
+    let response =
+        post(&app, &format!("/admin/users/{}/disable", user.user_id), &())
+            .await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let revoked = client.get(&dashboard).send().await.unwrap();
+    assert_eq!(revoked.status().as_u16(), 303);
+    assert_eq!(revoked.headers().get("Location").unwrap(), "/login");
+    let rejected = login(&app, &client, &user).await;
+    assert_eq!(rejected.headers().get("Location").unwrap(), "/login");
+
+    post(&app, &format!("/admin/users/{}/enable", user.user_id), &()).await;
+    let accepted = login(&app, &client, &user).await;
+    assert_eq!(
+        accepted.headers().get("Location").unwrap(),
+        "/admin/dashboard"
+    );
+}
+
+#[tokio::test]
+async fn users_cannot_disable_or_delete_themselves() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let own_id = app.test_user.user_id;
+
+    post(&app, &format!("/admin/users/{}/disable", own_id), &()).await;
+    post(&app, &format!("/admin/users/{}/delete", own_id), &()).await;
+
+    let html = get(&app, "/admin/users").await.text().await.unwrap();
+    assert!(html.contains("You cannot delete your own account."));
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn owners_can_delete_other_users() {
+    let app = spawn_app(None).await;
+    let (user, client) = second_session(&app, UserRole::Viewer).await;
+    app.login_test_user().await;
+
+    let response =
+        post(&app, &format!("/admin/users/{}/delete", user.user_id), &()).await;
+
+    assert_is_redirect_to(&response, "/admin/users");
+    let html = get(&app, "/admin/users").await.text().await.unwrap();
+    assert!(html.contains("The user has been deleted."));
+    assert!(!html.contains(&user.username));
+    let revoked = client
+        .get(&format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoked.headers().get("Location").unwrap(), "/login");
+}
+
+#[tokio::test]
+async fn the_first_owner_is_created_through_setup() {
+    let app = spawn_app(None).await;
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::delete(users::table)
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&get(&app, "/login").await, "/setup");
+    let response = post(
+        &app,
+        "/setup",
+        &serde_json::json!({
+            "email": "owner@example.com",
+            "username": "owner",
+            "password": "a-long-password",
+            "password_check": "a-long-password"
+        }),
+    )
+    .await;
+    assert_is_redirect_to(&response, "/login");
+
+    assert_is_redirect_to(&get(&app, "/setup").await, "/login");
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "owner",
+            "password": "a-long-password"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert!(app.get_admin_dashboard_html().await.contains("(owner)"));
+    let html = get(&app, "/admin/users").await.text().await.unwrap();
+    assert!(html.contains("owner@example.com"));
+}
+
+#[tokio::test]
+async fn setup_is_closed_once_a_user_exists() {
+    let app = spawn_app(None).await;
+
+    let response = post(
+        &app,
+        "/setup",
+        &serde_json::json!({
+            "email": "intruder@example.com",
+            "username": "intruder",
+            "password": "a-long-password",
+            "password_check": "a-long-password"
+        }),
+    )
+    .await;
+
+    assert_is_redirect_to(&response, "/login");
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "intruder",
+            "password": "a-long-password"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
@@ -0,0 +1,133 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+
+const EXPIRED_FORM: &str = "Your form has expired, please try again.";
+
+fn change_password_body(app: &TestApp) -> serde_json::Value {
+    let new_password = Uuid::now_v7().to_string();
+    serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    })
+}
+
+#[tokio::test]
+async fn forms_carry_the_session_csrf_token() {
+    let app = spawn_app(None).await;
+    let token = app.csrf_token().await;
+    app.login_test_user().await;
+
+    let html = app.get_change_password_html().await;
+
+    assert!(html.contains(&format!(
+        r#"<input type="hidden" name="csrf_token" value="{}">"#,
+        token
+    )));
+}
+
+#[tokio::test]
+async fn admin_posts_without_a_token_are_rejected() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .request_client
+        .post(&format!("{}/admin/password", &app.address))
+        .form(&change_password_body(&app))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // The password is unchanged.
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn rejected_forms_return_to_their_page_with_a_flash_message() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let mut body = change_password_body(&app);
+    body["csrf_token"] = "forged-token".into();
+
+    let response = app
+        .request_client
+        .post(&format!("{}/admin/password", &app.address))
+        .header("Referer", format!("{}/admin/password", &app.address))
+        .form(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html = app.get_change_password_html().await;
+    assert!(html.contains(EXPIRED_FORM));
+}
+
+#[tokio::test]
+async fn other_sites_are_not_returned_to() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .request_client
+        .post(&format!("{}/admin/password", &app.address))
+        .header("Referer", "https://example.com/admin/password")
+        .form(&change_password_body(&app))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn logins_without_a_token_are_rejected() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .request_client
+        .post(&format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+
+    let html = app.get_login_html().await;
+    assert!(html.contains(EXPIRED_FORM));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn scripts_can_send_the_token_in_a_header() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .request_client
+        .post(&format!("{}/admin/newsletters/preview", &app.address))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .form(&serde_json::json!({
+            "title": "Newsletter title",
+            "content_markdown": "Hello there!",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+}
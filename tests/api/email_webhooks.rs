@@ -0,0 +1,239 @@
+use axum_newsletter::domain::SubscriptionStatus;
+use axum_newsletter::schema::{email_events, subscriptions};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app, TestApp, WEBHOOK_SECRET,
+};
+
+const EMAIL: &str = "gabriel.aguiar@gmail.com";
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(body.into())
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn subscriber_status(app: &TestApp) -> SubscriptionStatus {
+    let mut connection = app.pool.get().await.unwrap();
+    subscriptions::table
+        .filter(subscriptions::email.eq(EMAIL))
+        .select(subscriptions::status)
+        .first(&mut connection)
+        .await
+        .unwrap()
+}
+
+async fn email_event_count(app: &TestApp) -> i64 {
+    let mut connection = app.pool.get().await.unwrap();
+    email_events::table
+        .count()
+        .get_result(&mut connection)
+        .await
+        .unwrap()
+}
+
+fn bounce(bounce_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": bounce_type,
+        "Email": EMAIL,
+        "Description": "The server was unable to deliver your message.",
+        "BouncedAt": "2024-07-02T16:33:54.9070259Z",
+        "Inactive": false
+    })
+}
+
+#[tokio::test]
+async fn webhooks_without_the_secret_are_rejected() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+
+    for secret in [None, Some("wrong-secret")] {
+        let response =
+            app.post_email_webhook(&bounce("HardBounce"), secret).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Confirmed);
+    assert_eq!(email_event_count(&app).await, 0);
+}
+
+#[tokio::test]
+async fn hard_bounces_stop_deliveries_to_the_subscriber() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .post_email_webhook(&bounce("HardBounce"), Some(WEBHOOK_SECRET))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Bounced);
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.login_test_user().await;
+    let response = app
+        .post_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "content_markdown": "Newsletter body",
+            "idempotency_key": uuid::Uuid::now_v7().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn spam_complaints_mark_the_subscriber_as_complained() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .post_email_webhook(
+            &serde_json::json!({
+                "RecordType": "SpamComplaint",
+                "Type": "SpamComplaint",
+                "Email": EMAIL,
+                "BouncedAt": "2024-07-02T16:33:54Z"
+            }),
+            Some(WEBHOOK_SECRET),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        subscriber_status(&app).await,
+        SubscriptionStatus::Complained
+    );
+}
+
+#[tokio::test]
+async fn subscribers_who_complained_are_not_emailed_when_subscribing_again() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.post_email_webhook(
+        &serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "Type": "SpamComplaint",
+            "Email": EMAIL,
+            "BouncedAt": "2024-07-02T16:33:54Z"
+        }),
+        Some(WEBHOOK_SECRET),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let response = app.subscribe(body.into()).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        subscriber_status(&app).await,
+        SubscriptionStatus::Complained
+    );
+}
+
+#[tokio::test]
+async fn soft_bounces_are_logged_without_changing_the_subscriber() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .post_email_webhook(&bounce("SoftBounce"), Some(WEBHOOK_SECRET))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Confirmed);
+    assert_eq!(email_event_count(&app).await, 1);
+}
+
+#[tokio::test]
+async fn events_are_listed_in_the_admin_area() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.post_email_webhook(&bounce("HardBounce"), Some(WEBHOOK_SECRET))
+        .await;
+    app.post_email_webhook(
+        &serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "Email": "stranger@example.com"
+        }),
+        Some(WEBHOOK_SECRET),
+    )
+    .await;
+    app.login_test_user().await;
+
+    let html = app.get_email_events_html().await;
+
+    assert!(html.contains(EMAIL));
+    assert!(html.contains("stranger@example.com"));
+    assert!(html.contains("hard_bounce"));
+    assert!(html.contains("The server was unable to deliver your message."));
+}
+
+#[tokio::test]
+async fn other_record_types_are_acknowledged_and_ignored() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .post_email_webhook(
+            &serde_json::json!({
+                "RecordType": "Delivery",
+                "Recipient": EMAIL
+            }),
+            Some(WEBHOOK_SECRET),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(email_event_count(&app).await, 0);
+}
+
+#[tokio::test]
+async fn malformed_payloads_are_rejected() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .post_email_webhook(
+            &serde_json::json!({"Email": EMAIL}),
+            Some(WEBHOOK_SECRET),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,70 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn messages_are_rendered_with_their_level() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let new_password = Uuid::now_v7().to_string();
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html = app.get_change_password_html().await;
+    assert!(html.contains(r#"<div class="flash flash-success" role="status">"#));
+    assert!(!html.contains("flash-error"));
+}
+
+#[tokio::test]
+async fn errors_are_announced_as_alerts() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "random-username",
+            "password": "random-password"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html = app.get_login_html().await;
+    assert!(html.contains(r#"<div class="flash flash-error" role="alert">"#));
+}
+
+#[tokio::test]
+async fn unread_messages_are_kept_alongside_new_ones() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    // Neither response is followed, so nothing renders the first message.
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app
+        .request_client
+        .post(&format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+
+    let html = app.get_login_html().await;
+    let logged_out = html.find("flash-success").unwrap();
+    let expired = html.find("flash-warning").unwrap();
+    assert!(logged_out < expired);
+    assert!(html.contains("You have successfully logged out."));
+    assert!(html.contains("Your form has expired, please try again."));
+
+    let html = app.get_login_html().await;
+    assert!(!html.contains("flash"));
+}
@@ -6,10 +6,20 @@ use argon2::PasswordHasher;
 use argon2::Version;
 use axum_newsletter::configuration::get_configuration;
 use axum_newsletter::configuration::DatabaseSettings;
+use axum_newsletter::configuration::{
+    EmailTransportSettings, LoginThrottleSettings, RateLimit, RateLimitBackend,
+    RateLimitSettings, WebhookSettings,
+};
 use axum_newsletter::database::DatabaseConnection;
+use axum_newsletter::domain::UserRole;
+use axum_newsletter::email_client::EmailClient;
+use axum_newsletter::issue_delivery_worker::{
+    try_execute_task, try_send_confirmation_emails, ExecutionOutcome,
+};
 use axum_newsletter::models::Subscriptions;
 use axum_newsletter::models::Users;
 use axum_newsletter::schema::users;
+use axum_newsletter::startup::HmacSecret;
 use axum_newsletter::telemetry::setup_tracing;
 use diesel::prelude::*;
 use diesel::SelectableHelper;
@@ -24,12 +34,17 @@ use once_cell::sync::Lazy;
 use rand::distributions::Alphanumeric;
 use rand::thread_rng;
 use rand::Rng;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use std::future::IntoFuture;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{MockServer, Request, Respond, ResponseTemplate};
 
 const MIGRATION: EmbeddedMigrations = embed_migrations!();
+pub const WEBHOOK_SECRET: &str = "webhook-secret";
+/// Requests a client can make to a rate limited route in one go.
+pub const RATE_LIMIT_BURST: u32 = 50;
+/// Small enough to exceed without a slow upload.
+pub const IMPORT_BODY_LIMIT: usize = 64 * 1024;
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     let default_filter = "debug";
@@ -44,14 +59,19 @@ pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
+    pub role: UserRole,
 }
 
 impl TestUser {
     pub fn generate() -> Self {
+        Self::with_role(UserRole::Owner)
+    }
+    pub fn with_role(role: UserRole) -> Self {
         Self {
             user_id: Uuid::now_v7(),
             username: Uuid::now_v7().to_string(),
             password: Uuid::now_v7().to_string(),
+            role,
         }
     }
     pub async fn store(&self, connection: &mut DatabaseConnection) {
@@ -65,7 +85,7 @@ impl TestUser {
         .unwrap()
         .to_string();
 
-        let user = Users::new(self.user_id, &self.username, &hash);
+        let user = Users::new(self.user_id, &self.username, &hash, self.role);
         diesel::insert_into(users::table)
             .values(&user)
             .execute(connection)
@@ -86,6 +106,9 @@ pub struct TestApp {
     pub server_port: u16,
     pub test_user: TestUser,
     pub request_client: reqwest::Client,
+    pub email_client: EmailClient,
+    pub base_url: String,
+    pub hmac_secret: HmacSecret,
 }
 
 impl TestApp {
@@ -101,6 +124,18 @@ impl TestApp {
             .await
     }
 
+    pub async fn resend_confirmation(
+        &self,
+        body: String,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        self.request_client
+            .post(&format!("{}/subscriptions/resend", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+    }
+
     pub async fn check_health(
         &self,
     ) -> Result<reqwest::Response, reqwest::Error> {
@@ -134,13 +169,59 @@ impl TestApp {
         ConfirmationLinks { html, plain_text }
     }
 
+    /// Extracts the one-click unsubscribe link from a Postmark batch message.
+    pub fn get_unsubscribe_link(&self, message: &serde_json::Value) -> String {
+        let header = message["Headers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|header| header["Name"] == "List-Unsubscribe")
+            .unwrap();
+        let raw_link = header["Value"]
+            .as_str()
+            .unwrap()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        let mut unsubscribe_link = reqwest::Url::parse(raw_link).unwrap();
+        assert_eq!(unsubscribe_link.host_str().unwrap(), "127.0.0.1");
+        unsubscribe_link.set_port(Some(self.server_port)).unwrap();
+        unsubscribe_link.to_string()
+    }
+
+    /// The CSRF token of this client's session.
+    pub async fn csrf_token(&self) -> String {
+        csrf_token(&self.request_client, &self.address).await
+    }
+
+    /// `body` with the CSRF token added, as the admin forms post it.
+    pub async fn with_csrf_token<Body>(&self, body: &Body) -> serde_json::Value
+    where
+        Body: serde::Serialize,
+    {
+        let mut body = serde_json::to_value(body).unwrap();
+        body["csrf_token"] = self.csrf_token().await.into();
+        body
+    }
+
     pub async fn post_newsletter(
         &self,
         body: &serde_json::Value,
     ) -> reqwest::Response {
         self.request_client
             .post(&format!("{}/admin/newsletters", &self.address))
-            .form(body)
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("Request failed.")
+    }
+
+    pub async fn post_newsletter_preview(
+        &self,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.request_client
+            .post(&format!("{}/admin/newsletters/preview", &self.address))
+            .form(&self.with_csrf_token(body).await)
             .send()
             .await
             .expect("Request failed.")
@@ -171,7 +252,7 @@ impl TestApp {
     {
         self.request_client
             .post(&format!("{}/login", &self.address))
-            .form(body)
+            .form(&self.with_csrf_token(body).await)
             .send()
             .await
             .expect("Failed to send request.")
@@ -230,7 +311,7 @@ impl TestApp {
     {
         self.request_client
             .post(&format!("{}/admin/password", &self.address))
-            .form(body)
+            .form(&self.with_csrf_token(body).await)
             .send()
             .await
             .expect("Failed to send request")
@@ -239,11 +320,230 @@ impl TestApp {
     pub async fn post_logout(&self) -> reqwest::Response {
         self.request_client
             .post(&format!("{}/admin/logout", &self.address))
+            .form(&self.with_csrf_token(&serde_json::json!({})).await)
             .send()
             .await
             .expect("Failed to send request")
     }
 
+    pub async fn get_failed_deliveries(&self) -> reqwest::Response {
+        self.request_client
+            .get(&format!("{}/admin/deliveries/failed", &self.address))
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn get_failed_deliveries_html(&self) -> String {
+        self.get_failed_deliveries().await.text().await.unwrap()
+    }
+
+    pub async fn get_subscribers(&self, query: &str) -> reqwest::Response {
+        self.request_client
+            .get(&format!("{}/admin/subscribers?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn get_subscribers_html(&self, query: &str) -> String {
+        self.get_subscribers(query).await.text().await.unwrap()
+    }
+
+    pub async fn get_subscriber_html(&self, subscriber_id: &Uuid) -> String {
+        self.request_client
+            .get(&format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to send request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_subscriber_action(
+        &self,
+        subscriber_id: &Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        self.request_client
+            .post(&format!(
+                "{}/admin/subscribers/{}/{}",
+                &self.address, subscriber_id, action
+            ))
+            .form(&self.with_csrf_token(&serde_json::json!({})).await)
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn get_subscribers_export(
+        &self,
+        query: &str,
+    ) -> reqwest::Response {
+        self.request_client
+            .get(&format!(
+                "{}/admin/subscribers/export?{}",
+                &self.address, query
+            ))
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn post_subscriber_import(
+        &self,
+        file_name: &str,
+        contents: &str,
+        confirmation: &str,
+    ) -> reqwest::Response {
+        let file = reqwest::multipart::Part::text(contents.to_string())
+            .file_name(file_name.to_string());
+        let form = reqwest::multipart::Form::new()
+            .part("file", file)
+            .text("confirmation", confirmation.to_string())
+            .text("csrf_token", self.csrf_token().await);
+        self.request_client
+            .post(&format!("{}/admin/subscribers/import", &self.address))
+            .multipart(form)
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn get_issues_html(&self) -> String {
+        self.request_client
+            .get(&format!("{}/admin/issues", &self.address))
+            .send()
+            .await
+            .expect("Failed to send request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_issue_html(&self, issue_id: &str) -> String {
+        self.request_client
+            .get(&format!("{}/admin/issues/{}", &self.address, issue_id))
+            .send()
+            .await
+            .expect("Failed to send request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Posts to `/admin/issues` followed by `path`, e.g. `/{id}/publish`.
+    pub async fn post_issue<Body>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.request_client
+            .post(&format!("{}/admin/issues{}", &self.address, path))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    /// Fetches a public archive page or feed, e.g. `/issues` or `/feed.rss`.
+    pub async fn get_archive(&self, path: &str) -> reqwest::Response {
+        self.request_client
+            .get(&format!("{}{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn post_requeue_delivery<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.request_client
+            .post(&format!(
+                "{}/admin/deliveries/failed/requeue",
+                &self.address
+            ))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("Failed to send request")
+    }
+
+    pub async fn post_email_webhook(
+        &self,
+        body: &serde_json::Value,
+        secret: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .request_client
+            .post(&format!("{}/webhooks/email", &self.address))
+            .json(body);
+        if let Some(secret) = secret {
+            request = request.header("X-Webhook-Secret", secret);
+        }
+        request.send().await.expect("Failed to send request")
+    }
+
+    pub async fn get_email_events_html(&self) -> String {
+        self.request_client
+            .get(&format!("{}/admin/deliveries/events", &self.address))
+            .send()
+            .await
+            .expect("Failed to send request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn dispatch_all_pending_emails(&self) {
+        while let ExecutionOutcome::TaskCompleted =
+            try_send_confirmation_emails(
+                &self.pool,
+                &self.email_client,
+                &self.base_url,
+            )
+            .await
+            .unwrap()
+        {}
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.pool,
+                &self.email_client,
+                &self.base_url,
+                &self.hmac_secret,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Stores a new user with the given role and logs in as them.
+    pub async fn login_with_role(&self, role: UserRole) -> TestUser {
+        let user = TestUser::with_role(role);
+        let mut connection = self.pool.get().await.unwrap();
+        user.store(&mut connection).await;
+        self.post_login(&serde_json::json!({
+            "username": &user.username,
+            "password": &user.password
+        }))
+        .await;
+        user
+    }
+
     pub async fn login_test_user(&self) {
         let login_body = serde_json::json!({
             "username": &self.test_user.username,
@@ -260,7 +560,37 @@ pub async fn spawn_app(migration: Option<EmbeddedMigrations>) -> TestApp {
         let mut c = get_configuration().expect("failed to get configuration");
         c.database.database_name = uuid::Uuid::now_v7().to_string();
         c.application.port = 0;
-        c.email_client.base_url = email_server.uri();
+        // Tests dispatch the queue themselves, without racing a worker.
+        c.application.background_workers = false;
+        c.application.import_body_limit_bytes = IMPORT_BODY_LIMIT;
+        // Lets tests pose as clients behind a proxy.
+        c.application.trusted_proxies = vec!["127.0.0.1/32".parse().unwrap()];
+        // Tests share the Redis instance and all log in from 127.0.0.1.
+        c.application.login_throttle = LoginThrottleSettings {
+            window_seconds: 60,
+            slowdown_after: 3,
+            slowdown_delay_ms: 10,
+            lockout_after: 5,
+            ip_lockout_after: 10_000,
+            lockout_seconds: 60,
+        };
+        let limit = RateLimit {
+            burst: RATE_LIMIT_BURST,
+            per_minute: 1,
+        };
+        c.application.rate_limits = RateLimitSettings {
+            backend: RateLimitBackend::Memory,
+            subscriptions: limit,
+            subscription_confirm: limit,
+            login: limit,
+        };
+        c.email_client.transport = EmailTransportSettings::Postmark {
+            base_url: email_server.uri(),
+            api_token: Secret::new("very-secret-token".into()),
+        };
+        c.email_client.webhook = WebhookSettings::SharedSecret {
+            secret: Secret::new(WEBHOOK_SECRET.into()),
+        };
         c
     };
     let request_client = reqwest::Client::builder()
@@ -270,6 +600,9 @@ pub async fn spawn_app(migration: Option<EmbeddedMigrations>) -> TestApp {
         .unwrap();
 
     configure_database(&configuration.database, migration).await;
+    let email_client = configuration.email_client.client().unwrap();
+    let base_url = configuration.application.base_url.clone();
+    let hmac_secret = HmacSecret(configuration.application.hmac_secret.clone());
 
     let application =
         axum_newsletter::startup::Application::build(configuration)
@@ -282,6 +615,9 @@ pub async fn spawn_app(migration: Option<EmbeddedMigrations>) -> TestApp {
         server_port: application.port(),
         test_user: TestUser::generate(),
         request_client,
+        email_client,
+        base_url,
+        hmac_secret,
     };
     let mut connection = testapp
         .pool
@@ -354,7 +690,41 @@ pub fn generate_valid_subscriber_token() -> String {
         .collect()
 }
 
+/// Reads the CSRF token of `client`'s session off the forgotten password
+/// form, which is open before any user exists, unlike the login form.
+pub async fn csrf_token(client: &reqwest::Client, address: &str) -> String {
+    let page = client
+        .get(&format!("{}/login/forgot", address))
+        .send()
+        .await
+        .expect("Failed to send request")
+        .text()
+        .await
+        .unwrap();
+    page.split(r#"name="csrf_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("No CSRF token on the forgotten password page")
+        .to_string()
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location);
 }
+
+/// Stands in for Postmark's `/email/batch` endpoint, accepting every
+/// message of the batch.
+pub struct PostmarkBatchResponder;
+
+impl Respond for PostmarkBatchResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let messages: Vec<serde_json::Value> =
+            serde_json::from_slice(&request.body).unwrap();
+        let results: Vec<serde_json::Value> = messages
+            .iter()
+            .map(|_| serde_json::json!({"ErrorCode": 0, "Message": "OK"}))
+            .collect();
+        ResponseTemplate::new(200).set_body_json(results)
+    }
+}
@@ -0,0 +1,212 @@
+use axum_newsletter::schema::newsletter_issues;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{spawn_app, PostmarkBatchResponder, TestApp};
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(body.into())
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn publish(app: &TestApp, title: &str) {
+    app.post_newsletter(&serde_json::json!({
+        "title": title,
+        "content_text": "Newsletter body as plaintext",
+        "content_html": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    }))
+    .await;
+}
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_archive() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    publish(&app, "Spring news").await;
+    app.post_issue(
+        "",
+        &serde_json::json!({
+            "title": "Unfinished draft",
+            "content_text": "text",
+            "content_html": "html",
+        }),
+    )
+    .await;
+
+    let response = app.get_archive("/issues").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Spring news"));
+    assert!(!html_page.contains("Unfinished draft"));
+}
+
+#[tokio::test]
+async fn published_issues_can_be_read_online() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    publish(&app, "Spring news").await;
+
+    let response = app.get_archive("/issues/spring-news").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Spring news</h1>"));
+    assert!(html_page.contains("<p>Newsletter body as HTML</p>"));
+}
+
+#[tokio::test]
+async fn issues_sharing_a_title_get_distinct_slugs() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    publish(&app, "Weekly").await;
+    publish(&app, "Weekly").await;
+
+    let html_page = app.get_archive("/issues").await.text().await.unwrap();
+
+    assert_eq!(html_page.matches("weekly").count(), 2);
+    assert!(html_page.contains("weekly-"));
+}
+
+#[tokio::test]
+async fn archived_issues_are_sanitized() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    app.post_newsletter(&serde_json::json!({
+        "title": "Spring news",
+        "content_text": "Newsletter body as plaintext",
+        "content_html": "<p>Hi</p>{{ \"<script>steal()</script>\" | safe }}\
+            <a href=\"{{ 'javascript:steal()' }}\">click</a>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    }))
+    .await;
+
+    let html_page = app
+        .get_archive("/issues/spring-news")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(html_page.contains("<p>Hi</p>"));
+    assert!(!html_page.contains("<script"));
+    assert!(!html_page.contains("javascript:"));
+}
+
+#[tokio::test]
+async fn issues_that_cannot_be_rendered_are_not_served_raw() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    publish(&app, "Spring news").await;
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::update(newsletter_issues::table)
+        .set(
+            newsletter_issues::html_content
+                .eq("<script>steal()</script>{{ missing }}"),
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+    let response = app.get_archive("/issues/spring-news").await;
+
+    assert_eq!(response.status().as_u16(), 500);
+    assert!(!response.text().await.unwrap().contains("<script"));
+    let feed = app.get_archive("/feed.rss").await;
+    assert_eq!(feed.status().as_u16(), 200);
+    assert!(!feed.text().await.unwrap().contains("steal()"));
+}
+
+#[tokio::test]
+async fn unknown_issues_are_a_404() {
+    let app = spawn_app(None).await;
+
+    let response = app.get_archive("/issues/not-published").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn feeds_list_published_issues() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    publish(&app, "Spring news").await;
+    let issue_url = format!("{}/issues/spring-news", app.base_url);
+
+    let response = app.get_archive("/feed.rss").await;
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    let rss = response.text().await.unwrap();
+    assert!(rss.contains("<title>Spring news</title>"));
+    assert!(rss.contains(&format!("<link>{}</link>", issue_url)));
+
+    let response = app.get_archive("/feed.atom").await;
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let atom = response.text().await.unwrap();
+    assert!(atom.contains("<title>Spring news</title>"));
+    assert!(atom.contains(&format!("<id>{}</id>", issue_url)));
+}
+
+#[tokio::test]
+async fn sent_emails_link_to_the_online_version() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish(&app, "Spring news").await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    let issue_url = format!("{}/issues/spring-news", app.base_url);
+    let message = &body[0];
+    assert!(message["TextBody"].as_str().unwrap().contains(&issue_url));
+    assert!(message["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!("<a href=\"{}\">", issue_url)));
+}
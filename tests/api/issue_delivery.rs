@@ -0,0 +1,240 @@
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app, PostmarkBatchResponder, TestApp,
+};
+use axum_newsletter::models::{IssueDeliveryDeadLetters, IssueDeliveryQueue};
+use axum_newsletter::schema::{
+    issue_delivery_dead_letters, issue_delivery_queue,
+};
+use diesel::prelude::*;
+use diesel::SelectableHelper;
+use diesel_async::RunQueryDsl;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    confirm_subscriber(app, "gabriel.aguiar@gmail.com").await;
+}
+
+async fn confirm_subscriber(app: &TestApp, email: &str) {
+    let body =
+        format!("name=gabriel%20aguiar&email={}", urlencoding::encode(email));
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(body)
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let body = serde_json::json!({
+        "title":"Newsletter title",
+        "content_text": "Newsletter body as plaintext",
+        "content_html":"<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    });
+    let response = app.post_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+async fn queued_tasks(app: &TestApp) -> Vec<IssueDeliveryQueue> {
+    let mut connection = app.pool.get().await.unwrap();
+    issue_delivery_queue::table
+        .select(IssueDeliveryQueue::as_select())
+        .load(&mut connection)
+        .await
+        .unwrap()
+}
+
+async fn dead_letters(app: &TestApp) -> Vec<IssueDeliveryDeadLetters> {
+    let mut connection = app.pool.get().await.unwrap();
+    issue_delivery_dead_letters::table
+        .select(IssueDeliveryDeadLetters::as_select())
+        .load(&mut connection)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn transient_failures_are_rescheduled_with_backoff() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let tasks = queued_tasks(&app).await;
+    assert_eq!(tasks.len(), 1);
+    let task = &tasks[0];
+    assert_eq!(task.attempts, 1);
+    assert!(task.last_error.is_some());
+    assert!(task.next_attempt_at > chrono::Utc::now());
+    assert!(dead_letters(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn rate_limited_deliveries_are_rescheduled() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(queued_tasks(&app).await.len(), 1);
+    assert!(dead_letters(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn permanent_failures_are_dead_lettered_immediately() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert!(queued_tasks(&app).await.is_empty());
+    let dead_letters = dead_letters(&app).await;
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].attempts, 1);
+
+    let html_page = app.get_failed_deliveries_html().await;
+    assert!(html_page.contains("gabriel.aguiar@gmail.com"));
+    assert!(html_page.contains("Newsletter title"));
+}
+
+#[tokio::test]
+async fn dead_letters_can_be_requeued_from_the_admin_area() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    let failure_guard = Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+    drop(failure_guard);
+
+    let dead_letter = dead_letters(&app).await.pop().unwrap();
+    let response = app
+        .post_requeue_delivery(&serde_json::json!({
+            "newsletter_issue_id": dead_letter.newsletter_issue_id,
+            "subscriber_email": dead_letter.subscriber_email,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/deliveries/failed");
+    let html_page = app.get_failed_deliveries_html().await;
+    assert!(html_page.contains(
+        "<p><i>Delivery to gabriel.aguiar@gmail.com has been requeued.</i></p>"
+    ));
+    assert!(dead_letters(&app).await.is_empty());
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    assert!(queued_tasks(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_failed_deliveries() {
+    let app = spawn_app(None).await;
+    let response = app.get_failed_deliveries().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_batch_is_delivered_with_a_single_request() {
+    let app = spawn_app(None).await;
+    for i in 0..3 {
+        confirm_subscriber(&app, &format!("subscriber{}@gmail.com", i)).await;
+    }
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert!(queued_tasks(&app).await.is_empty());
+    assert!(dead_letters(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn individual_failures_in_a_batch_are_dead_lettered() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!([
+                {"ErrorCode": 406, "Message": "Inactive recipient"}
+            ]),
+        ))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert!(queued_tasks(&app).await.is_empty());
+    let dead_letters = dead_letters(&app).await;
+    assert_eq!(dead_letters.len(), 1);
+    assert!(dead_letters[0].last_error.contains("Inactive recipient"));
+}
@@ -0,0 +1,82 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp, TestUser};
+
+const LOCKED_OUT: &str = "Too many failed login attempts. Try again in";
+
+async fn fail_login(app: &TestApp, username: &str, times: usize) {
+    for _ in 0..times {
+        let response = app
+            .post_login(&serde_json::json!({
+                "username": username,
+                "password": "wrong-password"
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/login");
+    }
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_the_username_out() {
+    let app = spawn_app(None).await;
+    fail_login(&app, &app.test_user.username, 5).await;
+
+    // Even the right password is refused while the lock lasts.
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    let page = app.get_login_html().await;
+    assert!(page.contains(&format!("{} 1 minute.", LOCKED_OUT)));
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn failures_below_the_limit_still_allow_logging_in() {
+    let app = spawn_app(None).await;
+    fail_login(&app, &app.test_user.username, 4).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_successful_login_resets_the_failure_count() {
+    let app = spawn_app(None).await;
+    fail_login(&app, &app.test_user.username, 4).await;
+    app.login_test_user().await;
+
+    fail_login(&app, &app.test_user.username, 4).await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_locked_username_does_not_lock_out_other_users() {
+    let app = spawn_app(None).await;
+    fail_login(&app, &app.test_user.username, 5).await;
+    let other_user = TestUser::generate();
+    let mut connection = app.pool.get().await.unwrap();
+    other_user.store(&mut connection).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &other_user.username,
+            "password": &other_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
@@ -1,8 +1,25 @@
+mod access_control;
 mod admin_dashboard;
+mod admin_subscribers;
+mod admin_subscribers_import;
+mod admin_users;
 mod change_password;
+mod csrf;
+mod email_webhooks;
+mod flash;
 mod health_check;
 mod helpers;
+mod issue_archive;
+mod issue_delivery;
 mod login;
+mod login_throttle;
 mod newsletter;
+mod newsletter_issues;
+mod password_reset;
+mod rate_limit;
 mod subscription;
 mod subscription_confirm;
+mod subscription_resend;
+mod tracking;
+mod two_factor;
+mod unsubscribe;
@@ -1,11 +1,21 @@
 use crate::helpers::{
-    assert_is_redirect_to, spawn_app, ConfirmationLinks, TestApp,
+    assert_is_redirect_to, spawn_app, ConfirmationLinks,
+    PostmarkBatchResponder, TestApp,
 };
+use fake::faker::internet::en::SafeEmail;
+use fake::faker::name::en::Name;
+use fake::Fake;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
 async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+    let body = format!(
+        "name={}&email={}",
+        urlencoding::encode(&name),
+        urlencoding::encode(&email)
+    );
     let _mock_guard = Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -13,7 +23,7 @@ async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
         .expect(1)
         .mount_as_scoped(&app.email_server)
         .await;
-    app.subscribe(body.into())
+    app.subscribe(body)
         .await
         .expect("Request failed.")
         .error_for_status()
@@ -58,11 +68,13 @@ async fn newsletter_are_not_delivered_to_uncofirmed_subscribers() {
     });
     let response = app.post_newsletter(&(newsletter_request_body)).await;
     assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
 
     let html_page = app.get_newsletter_html().await;
-    assert!(
-        html_page.contains("<p><i>Newsletter delivered successfully</i></p>")
-    );
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
 }
 
 #[tokio::test]
@@ -70,9 +82,9 @@ async fn newsletter_are_delivered_to_confirmed_subscribers() {
     let app = spawn_app(None).await;
     create_confirmed_subscriber(&app).await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkBatchResponder)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -88,11 +100,13 @@ async fn newsletter_are_delivered_to_confirmed_subscribers() {
     let response = app.post_newsletter(&(newsletter_request_body)).await;
 
     assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
 
     let html_page = app.get_newsletter_html().await;
-    assert!(
-        html_page.contains("<p><i>Newsletter delivered successfully</i></p>")
-    );
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
 }
 
 #[tokio::test]
@@ -118,7 +132,10 @@ async fn redirect_with_message_on_invalid_data_email_not_delivered() {
     assert_is_redirect_to(&response, "/admin/newsletters");
 
     let html_page = app.get_newsletter_html().await;
-    assert!(html_page.contains("<p><i>Invalid newsletter body.</i></p>"));
+    assert!(html_page.contains(
+        "<p><i>The newsletter needs a Markdown body, or both a text and an \
+        HTML version.</i></p>"
+    ));
 }
 
 #[tokio::test]
@@ -138,9 +155,10 @@ async fn newsletter_succeds_with_message_on_valid_data() {
     assert_is_redirect_to(&response, "/admin/newsletters");
 
     let html_page = app.get_newsletter_html().await;
-    assert!(
-        html_page.contains("<p><i>Newsletter delivered successfully</i></p>")
-    );
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
 }
 
 #[tokio::test]
@@ -169,9 +187,9 @@ async fn newsletter_creation_is_idempotent() {
     create_confirmed_subscriber(&app).await;
     app.login_test_user().await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkBatchResponder)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -187,17 +205,243 @@ async fn newsletter_creation_is_idempotent() {
     assert_is_redirect_to(&response, "/admin/newsletters");
 
     let html_page = app.get_newsletter_html().await;
-    assert!(
-        html_page.contains("<p><i>Newsletter delivered successfully</i></p>")
-    );
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
 
     //Deliver again.
     let response = app.post_newsletter(&body).await;
 
     assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
 
     let html_page = app.get_newsletter_html().await;
-    assert!(
-        html_page.contains("<p><i>Newsletter delivered successfully</i></p>")
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn resubmitting_a_newsletter_delivers_once_per_subscriber() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "title":"Newsletter title",
+        "content_text": "Newsletter body as plaintext",
+        "content_html":"<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    });
+    let first_response = app.post_newsletter(&body).await;
+    assert_is_redirect_to(&first_response, "/admin/newsletters");
+
+    //Double submit with the same idempotency key.
+    let second_response = app.post_newsletter(&body).await;
+    assert_is_redirect_to(&second_response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let batch_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: Vec<serde_json::Value> =
+        serde_json::from_slice(&batch_request.body).unwrap();
+    assert_eq!(messages.len(), 2);
+
+    let html_page = app.get_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn idempotency_keys_are_scoped_per_submission() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..2 {
+        let body = serde_json::json!({
+            "title":"Newsletter title",
+            "content_text": "Newsletter body as plaintext",
+            "content_html":"<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::now_v7().to_string()
+        });
+        let response = app.post_newsletter(&body).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+    }
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn concurrent_form_submission_is_handled_gracefully() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!([
+                    {"ErrorCode": 0, "Message": "OK"}
+                ]))
+                .set_delay(std::time::Duration::from_millis(500)),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "title":"Newsletter title",
+        "content_text": "Newsletter body as plaintext",
+        "content_html":"<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    });
+    let first_response = app.post_newsletter(&body);
+    let second_response = app.post_newsletter(&body);
+    let (first_response, second_response) =
+        tokio::join!(first_response, second_response);
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(first_response.status(), second_response.status());
+    assert_eq!(
+        first_response.headers().get("Location"),
+        second_response.headers().get("Location")
     );
+    assert_eq!(
+        first_response.text().await.unwrap(),
+        second_response.text().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn markdown_newsletters_are_sent_as_html_and_plain_text() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "title": "Newsletter title",
+        "content_markdown": "Hello **there**!\n\n<script>alert(1)</script>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    });
+    let response = app.post_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    let html = body[0]["HtmlBody"].as_str().unwrap();
+    let text = body[0]["TextBody"].as_str().unwrap();
+    assert!(html.contains("<p>Hello <strong>there</strong>!</p>"));
+    assert!(html.contains("<title>Newsletter title</title>"));
+    assert!(!html.contains("<script>"));
+    assert!(text.ends_with("Hello there!"));
+}
+
+#[tokio::test]
+async fn newsletters_can_be_previewed() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_newsletter_preview(&serde_json::json!({
+            "title": "Newsletter title",
+            "content_markdown": "# Hello\n\nA [link](https://example.com)",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let preview: serde_json::Value = response.json().await.unwrap();
+    assert!(preview["html"].as_str().unwrap().contains("<h1>Hello</h1>"));
+    assert!(preview["text"]
+        .as_str()
+        .unwrap()
+        .ends_with("Hello\n\nA link (https://example.com)"));
+}
+
+#[tokio::test]
+async fn previews_drop_markup_let_through_by_the_template() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_newsletter_preview(&serde_json::json!({
+            "title": "Newsletter title",
+            "content_text": "Hello",
+            "content_html": "{{ \"<script>steal()</script>\" | safe }}\
+                {% raw %}<img src=x onerror=\"steal()\">{% endraw %}\
+                <p>Hello</p>",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let preview: serde_json::Value = response.json().await.unwrap();
+    let html = preview["html"].as_str().unwrap();
+    assert!(html.contains("<p>Hello</p>"));
+    assert!(!html.contains("steal()"));
+}
+
+#[tokio::test]
+async fn previews_need_some_content() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_newsletter_preview(&serde_json::json!({
+            "title": "Newsletter title",
+            "content_markdown": "   ",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_preview_newsletters() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .post_newsletter_preview(&serde_json::json!({
+            "content_markdown": "Hello",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
 }
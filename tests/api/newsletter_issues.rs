@@ -0,0 +1,405 @@
+use axum_newsletter::domain::NewsletterIssueStatus;
+use axum_newsletter::issue_scheduler::publish_due_issues;
+use axum_newsletter::schema::{issue_delivery_queue, newsletter_issues};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app, PostmarkBatchResponder, TestApp,
+};
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(body.into())
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Creates a draft and returns its id.
+async fn create_draft(app: &TestApp, title: &str) -> String {
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": title,
+                "content_text": "Newsletter body as plaintext",
+                "content_html": "<p>Newsletter body as HTML</p>",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response.headers().get("Location").unwrap();
+    location
+        .to_str()
+        .unwrap()
+        .strip_prefix("/admin/issues/")
+        .unwrap()
+        .to_string()
+}
+
+async fn issue_status(app: &TestApp, issue_id: &str) -> NewsletterIssueStatus {
+    let mut connection = app.pool.get().await.unwrap();
+    newsletter_issues::table
+        .find(Uuid::parse_str(issue_id).unwrap())
+        .select(newsletter_issues::status)
+        .first(&mut connection)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_newsletter_issues() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": "Title",
+                "content_text": "text",
+                "content_html": "html",
+            }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn drafts_are_stored_without_being_sent() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let issue_id = create_draft(&app, "My first draft").await;
+    let response = app
+        .post_issue(
+            &format!("/{}", issue_id),
+            &serde_json::json!({
+                "title": "My edited draft",
+                "content_text": "Edited plaintext",
+                "content_html": "<p>Edited HTML</p>",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+
+    let html_page = app.get_issue_html(&issue_id).await;
+    assert!(html_page.contains("The draft has been saved."));
+    assert!(html_page.contains("Edited plaintext"));
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains("My edited draft"));
+    assert!(html_page.contains("<td>draft</td>"));
+    assert!(html_page.contains(&format!("<td>{}</td>", app.test_user.username)));
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn published_drafts_are_delivered_and_counted() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let issue_id = create_draft(&app, "Ready to go").await;
+    let response = app.post_issue(&format!("/{}/publish", issue_id), &()).await;
+    assert_is_redirect_to(&response, "/admin/issues");
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Sending
+    );
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Sent
+    );
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains("<td>sent</td>"));
+    assert!(html_page.contains(
+        "<td>1</td>\n\t\t\t\t<td>1</td>\n\t\t\t\t<td>0</td>\n\t\t\t\t<td>0</td>"
+    ));
+
+    // Sent issues cannot be sent again.
+    app.post_issue(&format!("/{}/publish", issue_id), &()).await;
+    let html_page = app.get_issue_html(&issue_id).await;
+    assert!(html_page.contains("An issue cannot go from sent to sending."));
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_sent_once_they_are_due() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    let issue_id = create_draft(&app, "Scheduled").await;
+    let scheduled_for = (Utc::now() + Duration::hours(1)).to_rfc3339();
+    let response = app
+        .post_issue(
+            &format!("/{}/schedule", issue_id),
+            &serde_json::json!({ "scheduled_for": scheduled_for }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Scheduled
+    );
+
+    assert_eq!(publish_due_issues(&app.pool).await.unwrap(), 0);
+
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::update(
+        newsletter_issues::table.find(Uuid::parse_str(&issue_id).unwrap()),
+    )
+    .set(
+        newsletter_issues::scheduled_for
+            .eq(Some(Utc::now() - Duration::minutes(1))),
+    )
+    .execute(&mut connection)
+    .await
+    .unwrap();
+    assert_eq!(publish_due_issues(&app.pool).await.unwrap(), 1);
+
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Sending
+    );
+    let queued: i64 = issue_delivery_queue::table
+        .count()
+        .get_result(&mut connection)
+        .await
+        .unwrap();
+    assert_eq!(queued, 1);
+}
+
+#[tokio::test]
+async fn scheduled_issues_can_go_back_to_drafts() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let issue_id = create_draft(&app, "Scheduled").await;
+    app.post_issue(
+        &format!("/{}/schedule", issue_id),
+        &serde_json::json!({
+            "scheduled_for": (Utc::now() + Duration::days(1))
+                .format("%Y-%m-%dT%H:%M")
+                .to_string()
+        }),
+    )
+    .await;
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Scheduled
+    );
+
+    app.post_issue(&format!("/{}/unschedule", issue_id), &())
+        .await;
+
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Draft
+    );
+}
+
+#[tokio::test]
+async fn issues_cannot_be_scheduled_in_the_past() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let issue_id = create_draft(&app, "Late").await;
+
+    app.post_issue(
+        &format!("/{}/schedule", issue_id),
+        &serde_json::json!({
+            "scheduled_for": (Utc::now() - Duration::hours(1)).to_rfc3339()
+        }),
+    )
+    .await;
+
+    let html_page = app.get_issue_html(&issue_id).await;
+    assert!(
+        html_page.contains("The delivery time must be a date in the future.")
+    );
+    assert_eq!(
+        issue_status(&app, &issue_id).await,
+        NewsletterIssueStatus::Draft
+    );
+}
+
+#[tokio::test]
+async fn markdown_drafts_keep_their_source() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": "Markdown draft",
+                "content_markdown": "Hello *there*",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response.headers()["Location"].to_str().unwrap();
+    let issue_id = location.strip_prefix("/admin/issues/").unwrap();
+
+    let html_page = app.get_issue_html(issue_id).await;
+    assert!(html_page.contains(">Hello *there*</textarea>"));
+    // The generated versions are not shown as overrides.
+    assert!(!html_page.contains("Hello there"));
+    let mut connection = app.pool.get().await.unwrap();
+    let (text, html): (String, String) = newsletter_issues::table
+        .find(Uuid::parse_str(issue_id).unwrap())
+        .select((
+            newsletter_issues::text_content,
+            newsletter_issues::html_content,
+        ))
+        .first(&mut connection)
+        .await
+        .unwrap();
+    assert_eq!(text, "Hello there");
+    assert_eq!(html, "<p>Hello <em>there</em></p>\n");
+}
+
+#[tokio::test]
+async fn issues_are_personalized_for_every_subscriber() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": "Personal",
+                "content_markdown": "Hi {{ subscriber.name }} \
+                    ({{ subscriber.email }}), \
+                    [unsubscribe]({{ unsubscribe_url }})",
+            }),
+        )
+        .await;
+    let location = response.headers()["Location"].to_str().unwrap();
+    let issue_id = location.strip_prefix("/admin/issues/").unwrap();
+    app.post_issue(&format!("/{}/publish", issue_id), &()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    let text = body[0]["TextBody"].as_str().unwrap();
+    let html = body[0]["HtmlBody"].as_str().unwrap();
+    assert!(text.contains("Hi gabriel aguiar (gabriel.aguiar@gmail.com)"));
+    assert!(text.contains("/subscriptions/unsubscribe?token="));
+    assert!(html.contains("Hi gabriel aguiar"));
+    assert!(html.contains("subscriptions/unsubscribe?token="));
+
+    // The public archive gets neutral values instead.
+    let html_page = app
+        .get_archive("/issues/personal")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("Hi reader"));
+    assert!(!html_page.contains("{{"));
+}
+
+#[tokio::test]
+async fn broken_placeholders_are_rejected_when_saving() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": "Broken",
+                "content_markdown": "Hi {{ subscriber.nmae }}",
+            }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/issues/new");
+    let html_page = app
+        .get_archive("/admin/issues/new")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("The newsletter is not a valid template"));
+    assert!(html_page.contains("subscriber.nmae"));
+    assert!(!app.get_issues_html().await.contains("Broken"));
+}
+
+#[tokio::test]
+async fn drafts_reading_the_server_environment_are_rejected_when_saving() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_issue(
+            "",
+            &serde_json::json!({
+                "title": "Leaky",
+                "content_markdown": "{{ get_env(name=\"APP_DATABASE__PASSWORD\") }}",
+            }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/issues/new");
+    let html_page = app
+        .get_archive("/admin/issues/new")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("cannot be used in a newsletter"));
+    assert!(!app.get_issues_html().await.contains("Leaky"));
+}
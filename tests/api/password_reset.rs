@@ -0,0 +1,313 @@
+use axum_newsletter::schema::{password_reset_tokens, users};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+
+const EMAIL: &str = "admin@example.com";
+const NEW_PASSWORD: &str = "a-brand-new-password";
+
+async fn set_email(app: &TestApp, user_id: Uuid) {
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::update(users::table.find(user_id))
+        .set(users::email.eq(EMAIL))
+        .execute(&mut connection)
+        .await
+        .unwrap();
+}
+
+async fn post_forgot_password(app: &TestApp, email: &str) -> reqwest::Response {
+    app.request_client
+        .post(&format!("{}/login/forgot", &app.address))
+        .form(
+            &app.with_csrf_token(&serde_json::json!({ "email": email }))
+                .await,
+        )
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+async fn get_forgot_password_html(app: &TestApp) -> String {
+    app.request_client
+        .get(&format!("{}/login/forgot", &app.address))
+        .send()
+        .await
+        .expect("Failed to send request")
+        .text()
+        .await
+        .unwrap()
+}
+
+async fn post_reset(
+    app: &TestApp,
+    token: &str,
+    password: &str,
+    password_check: &str,
+) -> reqwest::Response {
+    app.request_client
+        .post(&format!("{}/login/reset", &app.address))
+        .form(
+            &app.with_csrf_token(&serde_json::json!({
+                "token": token,
+                "new_password": password,
+                "new_password_check": password_check
+            }))
+            .await,
+        )
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+/// Requests a reset for the test user, returning the emailed link.
+async fn request_reset_link(app: &TestApp) -> reqwest::Url {
+    set_email(app, app.test_user.user_id).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    post_forgot_password(app, EMAIL).await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(&email_request).await.html
+}
+
+fn token_of(link: &reqwest::Url) -> String {
+    link.query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string()
+}
+
+#[tokio::test]
+async fn known_and_unknown_addresses_get_the_same_answer() {
+    let app = spawn_app(None).await;
+    set_email(&app, app.test_user.user_id).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let known = post_forgot_password(&app, EMAIL).await;
+    let known_html = get_forgot_password_html(&app).await;
+    let unknown = post_forgot_password(&app, "nobody@example.com").await;
+    let unknown_html = get_forgot_password_html(&app).await;
+
+    assert_is_redirect_to(&known, "/login/forgot");
+    assert_is_redirect_to(&unknown, "/login/forgot");
+    assert!(known_html.contains("a reset link has been sent"));
+    assert!(unknown_html.contains("a reset link has been sent"));
+}
+
+#[tokio::test]
+async fn failing_to_send_the_link_gets_the_same_answer() {
+    let app = spawn_app(None).await;
+    set_email(&app, app.test_user.user_id).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = post_forgot_password(&app, EMAIL).await;
+
+    assert_is_redirect_to(&response, "/login/forgot");
+    assert!(get_forgot_password_html(&app)
+        .await
+        .contains("a reset link has been sent"));
+}
+
+#[tokio::test]
+async fn reset_requests_without_a_csrf_token_are_rejected() {
+    let app = spawn_app(None).await;
+    set_email(&app, app.test_user.user_id).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .request_client
+        .post(&format!("{}/login/forgot", &app.address))
+        .form(&serde_json::json!({ "email": EMAIL }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login/forgot");
+    assert!(get_forgot_password_html(&app)
+        .await
+        .contains("Your form has expired"));
+}
+
+#[tokio::test]
+async fn only_the_hash_of_the_token_is_stored() {
+    let app = spawn_app(None).await;
+    let link = request_reset_link(&app).await;
+
+    let mut connection = app.pool.get().await.unwrap();
+    let stored: Vec<String> = password_reset_tokens::table
+        .select(password_reset_tokens::token_hash)
+        .load(&mut connection)
+        .await
+        .unwrap();
+
+    assert_eq!(stored.len(), 1);
+    assert_ne!(stored[0], token_of(&link));
+}
+
+#[tokio::test]
+async fn disabled_users_get_no_reset_link() {
+    let app = spawn_app(None).await;
+    set_email(&app, app.test_user.user_id).await;
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::update(users::table.find(app.test_user.user_id))
+        .set(users::disabled_at.eq(chrono::Utc::now()))
+        .execute(&mut connection)
+        .await
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = post_forgot_password(&app, EMAIL).await;
+
+    assert_is_redirect_to(&response, "/login/forgot");
+}
+
+#[tokio::test]
+async fn reset_link_changes_the_password_and_ends_existing_sessions() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+    let link = request_reset_link(&app).await;
+
+    let form = app.request_client.get(link.clone()).send().await.unwrap();
+    assert_eq!(form.status().as_u16(), 200);
+    let response =
+        post_reset(&app, &token_of(&link), NEW_PASSWORD, NEW_PASSWORD).await;
+    assert_is_redirect_to(&response, "/login");
+
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+    let old = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&old, "/login");
+    let new = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": NEW_PASSWORD
+        }))
+        .await;
+    assert_is_redirect_to(&new, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn reset_links_can_only_be_used_once() {
+    let app = spawn_app(None).await;
+    let link = request_reset_link(&app).await;
+    let token = token_of(&link);
+
+    let first = post_reset(&app, &token, NEW_PASSWORD, NEW_PASSWORD).await;
+    let second =
+        post_reset(&app, &token, "yet-another-one", "yet-another-one").await;
+    let form = app.request_client.get(link).send().await.unwrap();
+
+    assert_is_redirect_to(&first, "/login");
+    assert_eq!(second.status().as_u16(), 404);
+    assert_eq!(form.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn concurrent_resets_with_the_same_link_change_the_password_once() {
+    let app = spawn_app(None).await;
+    let token = token_of(&request_reset_link(&app).await);
+
+    let passwords = ["new-password-one", "new-password-two", "new-password-3"];
+    let responses = futures_util::future::join_all(
+        passwords
+            .iter()
+            .map(|password| post_reset(&app, &token, password, password)),
+    )
+    .await;
+
+    let statuses: Vec<u16> = responses
+        .iter()
+        .map(|response| response.status().as_u16())
+        .collect();
+    assert_eq!(statuses.iter().filter(|&&status| status == 303).count(), 1);
+    assert_eq!(statuses.iter().filter(|&&status| status == 404).count(), 2);
+}
+
+#[tokio::test]
+async fn expired_reset_links_are_rejected() {
+    let app = spawn_app(None).await;
+    let link = request_reset_link(&app).await;
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::update(password_reset_tokens::table)
+        .set(
+            password_reset_tokens::expires_at
+                .eq(chrono::Utc::now() - chrono::Duration::minutes(1)),
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+    let form = app.request_client.get(link.clone()).send().await.unwrap();
+    let response =
+        post_reset(&app, &token_of(&link), NEW_PASSWORD, NEW_PASSWORD).await;
+
+    assert_eq!(form.status().as_u16(), 404);
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn invalid_new_passwords_keep_the_link_usable() {
+    let app = spawn_app(None).await;
+    let link = request_reset_link(&app).await;
+    let token = token_of(&link);
+
+    let mismatch =
+        post_reset(&app, &token, NEW_PASSWORD, "something-else").await;
+    let html = app
+        .request_client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let too_short = post_reset(&app, &token, "short", "short").await;
+    let valid = post_reset(&app, &token, NEW_PASSWORD, NEW_PASSWORD).await;
+
+    assert_eq!(mismatch.status().as_u16(), 303);
+    assert!(html.contains("two different new passwords"));
+    assert_eq!(too_short.status().as_u16(), 303);
+    assert_is_redirect_to(&valid, "/login");
+}
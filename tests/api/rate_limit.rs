@@ -0,0 +1,110 @@
+use crate::helpers::{spawn_app, TestApp, RATE_LIMIT_BURST};
+
+async fn use_up_subscriptions(app: &TestApp) {
+    for _ in 0..RATE_LIMIT_BURST {
+        let response = app.subscribe("name=le%20guin".into()).await.unwrap();
+        assert_ne!(response.status().as_u16(), 429);
+    }
+}
+
+/// Subscribes as `client`, reaching the app through a trusted proxy.
+async fn subscribe_via_proxy(app: &TestApp, client: &str) -> reqwest::Response {
+    app.request_client
+        .post(&format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("X-Forwarded-For", client)
+        .body("name=le%20guin")
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+#[tokio::test]
+async fn subscribing_past_the_burst_is_rejected_with_retry_after() {
+    let app = spawn_app(None).await;
+    use_up_subscriptions(&app).await;
+
+    let response = app.subscribe("name=le%20guin".into()).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+}
+
+#[tokio::test]
+async fn resending_confirmations_shares_the_subscriptions_limit() {
+    let app = spawn_app(None).await;
+    use_up_subscriptions(&app).await;
+
+    let response = app
+        .resend_confirmation("email=ursula_le_guin%40gmail.com".into())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn routes_are_limited_independently() {
+    let app = spawn_app(None).await;
+    use_up_subscriptions(&app).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+#[tokio::test]
+async fn logging_in_past_the_burst_is_rejected() {
+    let app = spawn_app(None).await;
+    let body = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    });
+    for _ in 0..RATE_LIMIT_BURST {
+        let response = app.post_login(&body).await;
+        assert_ne!(response.status().as_u16(), 429);
+    }
+
+    let response = app.post_login(&body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn confirming_past_the_burst_is_rejected() {
+    let app = spawn_app(None).await;
+    let url = format!("{}/subscriptions/confirm", app.address);
+    for _ in 0..RATE_LIMIT_BURST {
+        let response = reqwest::get(&url).await.unwrap();
+        assert_ne!(response.status().as_u16(), 429);
+    }
+
+    let response = reqwest::get(&url).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn clients_behind_the_same_proxy_are_limited_separately() {
+    let app = spawn_app(None).await;
+    for _ in 0..RATE_LIMIT_BURST {
+        let response = subscribe_via_proxy(&app, "198.51.100.1").await;
+        assert_ne!(response.status().as_u16(), 429);
+    }
+
+    let limited = subscribe_via_proxy(&app, "198.51.100.1").await;
+    let other_client = subscribe_via_proxy(&app, "198.51.100.2").await;
+
+    assert_eq!(limited.status().as_u16(), 429);
+    assert_ne!(other_client.status().as_u16(), 429);
+}
@@ -1,3 +1,4 @@
+use axum_newsletter::domain::SubscriptionStatus;
 use diesel_migrations::embed_migrations;
 use diesel_migrations::EmbeddedMigrations;
 use wiremock::{
@@ -58,7 +59,7 @@ async fn subscribe_persists_subcriber_data() {
 
     assert_eq!(&particular.name, "Gabriel Aguiar");
     assert_eq!(&particular.email, "gabriel.masarin.aguiar@gmail.com");
-    assert_eq!(&particular.status, "pending_confirmation");
+    assert_eq!(particular.status, SubscriptionStatus::Pending);
 }
 #[tokio::test]
 async fn subscribe_returns_422_when_data_is_missing() {
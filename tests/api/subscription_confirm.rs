@@ -1,3 +1,4 @@
+use axum_newsletter::domain::SubscriptionStatus;
 use wiremock::{
     matchers::{method, path},
     Mock, ResponseTemplate,
@@ -76,7 +77,7 @@ async fn clicking_on_confirmation_link_sets_subscriber_status_to_confirmed() {
     .await;
     assert_eq!(results.len(), 1);
     let subscriber_data = results.first().unwrap();
-    assert_eq!(subscriber_data.status, "confirmed");
+    assert_eq!(subscriber_data.status, SubscriptionStatus::Confirmed);
 }
 #[tokio::test]
 async fn user_can_click_confirmation_link_twice_with_no_issues() {
@@ -115,7 +116,7 @@ async fn user_can_click_confirmation_link_twice_with_no_issues() {
     .await;
     assert_eq!(results.len(), 1);
     let subscriber_data = results.first().unwrap();
-    assert_eq!(subscriber_data.status, "confirmed");
+    assert_eq!(subscriber_data.status, SubscriptionStatus::Confirmed);
     let response = reqwest::get(confirmation_link.html)
         .await
         .unwrap()
@@ -129,7 +130,7 @@ async fn user_can_click_confirmation_link_twice_with_no_issues() {
     .await;
     assert_eq!(results.len(), 1);
     let subscriber_data = results.first().unwrap();
-    assert_eq!(subscriber_data.status, "confirmed");
+    assert_eq!(subscriber_data.status, SubscriptionStatus::Confirmed);
 }
 #[tokio::test]
 async fn confirmations_with_unexisting_but_well_formated_token_are_rejected_with_unauthorized(
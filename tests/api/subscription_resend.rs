@@ -0,0 +1,164 @@
+use axum_newsletter::domain::SubscriptionStatus;
+use axum_newsletter::schema::subscription_tokens;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{check_subscriber_existance, spawn_app, TestApp};
+
+const SUBSCRIBER_EMAIL: &str = "gabriel.aguiar@gmail.com";
+const SUBSCRIBER_BODY: &str =
+    "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+
+async fn create_pending_subscriber(app: &TestApp) {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(SUBSCRIBER_BODY.into())
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+}
+
+/// Pretends the existing tokens were sent long enough ago to allow a resend.
+async fn age_tokens(app: &TestApp) {
+    let mut connection = app.pool.get().await.unwrap();
+    diesel::update(subscription_tokens::table)
+        .set(
+            subscription_tokens::generated_at
+                .eq(Utc::now() - Duration::hours(1)),
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn resending_sends_a_new_link_and_invalidates_the_old_one() {
+    let app = spawn_app(None).await;
+    create_pending_subscriber(&app).await;
+    age_tokens(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .resend_confirmation("email=gabriel.aguiar%40gmail.com".into())
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    assert_eq!(email_requests.len(), 2);
+    let old_link = app.get_confirmation_links(&email_requests[0]).await;
+    let new_link = app.get_confirmation_links(&email_requests[1]).await;
+    assert_ne!(old_link.html, new_link.html);
+
+    let response = reqwest::get(old_link.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = reqwest::get(new_link.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let mut connection = app.pool.get().await.unwrap();
+    let subscriber =
+        check_subscriber_existance(&mut connection, SUBSCRIBER_EMAIL)
+            .await
+            .pop()
+            .unwrap();
+    assert_eq!(subscriber.status, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn resending_too_soon_sends_nothing_but_still_answers_200() {
+    let app = spawn_app(None).await;
+    create_pending_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .resend_confirmation("email=gabriel.aguiar%40gmail.com".into())
+        .await
+        .unwrap();
+
+    // A 429 here would tell that the address has a pending subscription.
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Retry-After").is_none());
+}
+
+#[tokio::test]
+async fn concurrent_resends_send_a_single_email() {
+    let app = spawn_app(None).await;
+    create_pending_subscriber(&app).await;
+    age_tokens(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(std::time::Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let responses = futures_util::future::join_all((0..5).map(|_| {
+        app.resend_confirmation("email=gabriel.aguiar%40gmail.com".into())
+    }))
+    .await;
+
+    for response in responses {
+        assert_eq!(response.unwrap().status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn resending_to_unknown_or_confirmed_addresses_sends_nothing() {
+    let app = spawn_app(None).await;
+    create_pending_subscriber(&app).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    age_tokens(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    for body in [
+        "email=gabriel.aguiar%40gmail.com",
+        "email=someone.else%40gmail.com",
+    ] {
+        let response = app.resend_confirmation(body.into()).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn resending_to_an_invalid_email_is_rejected_with_a_400() {
+    let app = spawn_app(None).await;
+
+    let response = app
+        .resend_confirmation("email=not-an-email".into())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,163 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::Mock;
+
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app, PostmarkBatchResponder, TestApp,
+};
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(wiremock::ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(body.into())
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Sends an issue linking to an article and returns the delivered HTML.
+async fn send_issue(app: &TestApp, track_engagement: bool) -> String {
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let mut body = serde_json::json!({
+        "title": "Tracked issue",
+        "content_markdown":
+            "Read [the article](https://example.com/article?a=1&b=2).",
+        "idempotency_key": Uuid::now_v7().to_string()
+    });
+    if track_engagement {
+        body["track_engagement"] = "on".into();
+    }
+    let response = app.post_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    body[0]["HtmlBody"].as_str().unwrap().to_string()
+}
+
+/// Finds the one link of the email going to `route`, pointed at the test
+/// server.
+fn tracking_link(app: &TestApp, html: &str, route: &str) -> reqwest::Url {
+    let links: Vec<_> = linkify::LinkFinder::new()
+        .links(html)
+        .filter(|l| *l.kind() == linkify::LinkKind::Url)
+        .filter(|l| l.as_str().contains(route))
+        .collect();
+    assert_eq!(links.len(), 1);
+    let mut link = reqwest::Url::parse(links[0].as_str()).unwrap();
+    assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+    link.set_port(Some(app.server_port)).unwrap();
+    link
+}
+
+#[tokio::test]
+async fn tracked_issues_count_opens_and_clicks() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    let html = send_issue(&app, true).await;
+    assert!(!html.contains("href=\"https://example.com/article"));
+
+    let pixel = tracking_link(&app, &html, "/t/o/");
+    let response = app.request_client.get(pixel).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+    assert_eq!(response.headers()["Cache-Control"], "no-store, max-age=0");
+
+    let click = tracking_link(&app, &html, "/t/c/");
+    let response = app.request_client.get(click).send().await.unwrap();
+    assert_is_redirect_to(&response, "https://example.com/article?a=1&b=2");
+
+    let dashboard = app.get_admin_dashboard_html().await;
+    assert!(dashboard.contains("Tracked issue"));
+    assert!(dashboard.contains("1 (100.0%)"));
+}
+
+#[tokio::test]
+async fn untracked_issues_are_sent_as_written() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    let html = send_issue(&app, false).await;
+
+    assert!(html.contains("href=\"https://example.com/article?a=1&amp;b=2\""));
+    assert!(!html.contains("/t/o/"));
+    assert!(!html.contains("/t/c/"));
+    let dashboard = app.get_admin_dashboard_html().await;
+    assert!(dashboard.contains("Tracking disabled"));
+}
+
+#[tokio::test]
+async fn clicks_with_an_invalid_token_are_redirected_but_not_counted() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+    let html = send_issue(&app, true).await;
+
+    let mut click = tracking_link(&app, &html, "/t/c/");
+    click.set_query(Some("token=forged"));
+    let response = app.request_client.get(click).send().await.unwrap();
+
+    assert_is_redirect_to(&response, "https://example.com/article?a=1&b=2");
+    let dashboard = app.get_admin_dashboard_html().await;
+    assert!(dashboard.contains("0 (0.0%)"));
+}
+
+#[tokio::test]
+async fn unknown_tracking_targets_are_not_found() {
+    let app = spawn_app(None).await;
+
+    let pixel = app
+        .request_client
+        .get(format!("{}/t/o/forged", app.address))
+        .send()
+        .await
+        .unwrap();
+    let click = app
+        .request_client
+        .get(format!(
+            "{}/t/c/{}?token=forged",
+            app.address,
+            Uuid::now_v7()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(pixel.status().as_u16(), 404);
+    assert_eq!(click.status().as_u16(), 404);
+}
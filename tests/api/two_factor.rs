@@ -0,0 +1,215 @@
+use axum_newsletter::domain::TotpSecret;
+use axum_newsletter::schema::{recovery_codes, users};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.request_client
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+async fn post<Body>(app: &TestApp, path: &str, body: &Body) -> reqwest::Response
+where
+    Body: serde::Serialize,
+{
+    app.request_client
+        .post(&format!("{}{}", &app.address, path))
+        .form(&app.with_csrf_token(body).await)
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+/// Values between `<code>` tags, in order.
+fn code_tags(html: &str) -> Vec<String> {
+    html.split("<code>")
+        .skip(1)
+        .map(|part| part.split("</code>").next().unwrap().to_string())
+        .collect()
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Enables two-factor authentication for the logged in test user, returning
+/// the secret and the recovery codes.
+async fn enroll(app: &TestApp) -> (TotpSecret, Vec<String>) {
+    let html = get(app, "/admin/two-factor").await.text().await.unwrap();
+    let secret = TotpSecret::from_base32(&code_tags(&html)[0]).unwrap();
+    let response = post(
+        app,
+        "/admin/two-factor/enable",
+        &serde_json::json!({ "code": secret.code_at(now()) }),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let codes = code_tags(&response.text().await.unwrap());
+    (secret, codes)
+}
+
+async fn login_with_password(app: &TestApp) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    }))
+    .await
+}
+
+async fn post_code(app: &TestApp, code: &str) -> reqwest::Response {
+    post(
+        app,
+        "/login/two-factor",
+        &serde_json::json!({ "code": code }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn enrolling_hands_out_recovery_codes_and_encrypts_the_secret() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+
+    let (secret, codes) = enroll(&app).await;
+
+    assert_eq!(codes.len(), 10);
+    let mut connection = app.pool.get().await.unwrap();
+    let stored: Option<String> = users::table
+        .find(app.test_user.user_id)
+        .select(users::totp_secret)
+        .first(&mut connection)
+        .await
+        .unwrap();
+    let stored = stored.unwrap();
+    assert!(!stored.contains(&secret.to_base32()));
+    let hashes: Vec<String> = recovery_codes::table
+        .select(recovery_codes::code_hash)
+        .load(&mut connection)
+        .await
+        .unwrap();
+    assert_eq!(hashes.len(), 10);
+    assert!(hashes.iter().all(|hash| hash.starts_with("$argon2id$")));
+    assert!(codes.iter().all(|code| !hashes.contains(code)));
+    let html = get(&app, "/admin/two-factor").await.text().await.unwrap();
+    assert!(html.contains("10 recovery codes are left"));
+}
+
+#[tokio::test]
+async fn a_wrong_confirmation_code_does_not_enable_two_factor() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    get(&app, "/admin/two-factor").await;
+
+    let response = post(
+        &app,
+        "/admin/two-factor/enable",
+        &serde_json::json!({ "code": "000000" }),
+    )
+    .await;
+
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html = get(&app, "/admin/two-factor").await.text().await.unwrap();
+    assert!(html.contains("Invalid authentication code."));
+    assert!(html.contains("Two-factor authentication is disabled."));
+}
+
+#[tokio::test]
+async fn login_asks_for_a_code_before_granting_access() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let (secret, _) = enroll(&app).await;
+    app.post_logout().await;
+
+    let response = login_with_password(&app).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+
+    let wrong = post_code(&app, "000000").await;
+    assert_is_redirect_to(&wrong, "/login/two-factor");
+    let html = get(&app, "/login/two-factor").await.text().await.unwrap();
+    assert!(html.contains("Invalid authentication code."));
+
+    // The code of the enrollment step was already used.
+    let response = post_code(&app, &secret.code_at(now() + 30)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn totp_codes_cannot_be_used_twice() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let (secret, _) = enroll(&app).await;
+    let code = secret.code_at(now() + 30);
+    app.post_logout().await;
+    login_with_password(&app).await;
+    assert_is_redirect_to(&post_code(&app, &code).await, "/admin/dashboard");
+    app.post_logout().await;
+
+    login_with_password(&app).await;
+    let response = post_code(&app, &code).await;
+
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn recovery_codes_work_once() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    let (_, codes) = enroll(&app).await;
+    app.post_logout().await;
+
+    login_with_password(&app).await;
+    let first = post_code(&app, &codes[0].to_uppercase()).await;
+    assert_is_redirect_to(&first, "/admin/dashboard");
+    let html = get(&app, "/admin/two-factor").await.text().await.unwrap();
+    assert!(html.contains("9 recovery codes are left"));
+    app.post_logout().await;
+
+    login_with_password(&app).await;
+    let second = post_code(&app, &codes[0]).await;
+    assert_is_redirect_to(&second, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn the_second_step_needs_the_password_first() {
+    let app = spawn_app(None).await;
+
+    let page = get(&app, "/login/two-factor").await;
+    let response = post_code(&app, "123456").await;
+
+    assert_is_redirect_to(&page, "/login");
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn disabling_two_factor_requires_the_password() {
+    let app = spawn_app(None).await;
+    app.login_test_user().await;
+    enroll(&app).await;
+
+    let wrong = post(
+        &app,
+        "/admin/two-factor/disable",
+        &serde_json::json!({ "current_password": "wrong-password" }),
+    )
+    .await;
+    assert_is_redirect_to(&wrong, "/admin/two-factor");
+    let html = get(&app, "/admin/two-factor").await.text().await.unwrap();
+    assert!(html.contains("The current password is incorrect."));
+
+    post(
+        &app,
+        "/admin/two-factor/disable",
+        &serde_json::json!({ "current_password": &app.test_user.password }),
+    )
+    .await;
+    app.post_logout().await;
+    let response = login_with_password(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
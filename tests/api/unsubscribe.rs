@@ -0,0 +1,290 @@
+use crate::helpers::{
+    assert_is_redirect_to, check_subscriber_existance, spawn_app,
+    PostmarkBatchResponder, TestApp,
+};
+use axum_newsletter::domain::{SubscriptionStatus, UnsubscribeToken};
+use secrecy::Secret;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const SUBSCRIBER_EMAIL: &str = "gabriel.aguiar@gmail.com";
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.subscribe(body.into())
+        .await
+        .expect("Request failed.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let body = serde_json::json!({
+        "title":"Newsletter title",
+        "content_text": "Newsletter body as plaintext",
+        "content_html":"<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::now_v7().to_string()
+    });
+    let response = app.post_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+async fn subscriber_status(app: &TestApp) -> SubscriptionStatus {
+    let mut connection = app.pool.get().await.unwrap();
+    check_subscriber_existance(&mut connection, SUBSCRIBER_EMAIL)
+        .await
+        .pop()
+        .unwrap()
+        .status
+}
+
+async fn subscriber_unsubscribe_link(app: &TestApp) -> String {
+    let mut connection = app.pool.get().await.unwrap();
+    let subscriber_id =
+        check_subscriber_existance(&mut connection, SUBSCRIBER_EMAIL)
+            .await
+            .pop()
+            .unwrap()
+            .id;
+    let token = UnsubscribeToken::generate(subscriber_id, &app.hmac_secret.0);
+    format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        token.as_ref()
+    )
+}
+
+#[tokio::test]
+async fn newsletter_emails_carry_one_click_unsubscribe_headers() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkBatchResponder)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let batch_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: Vec<serde_json::Value> =
+        serde_json::from_slice(&batch_request.body).unwrap();
+    let headers = messages[0]["Headers"].as_array().unwrap();
+    assert!(headers.contains(&serde_json::json!({
+        "Name": "List-Unsubscribe-Post",
+        "Value": "List-Unsubscribe=One-Click"
+    })));
+
+    // One-click unsubscribe, as performed by mail clients.
+    let unsubscribe_link = app.get_unsubscribe_link(&messages[0]);
+    let response = reqwest::Client::new()
+        .post(unsubscribe_link)
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        subscriber_status(&app).await,
+        SubscriptionStatus::Unsubscribed
+    );
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletters() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    let unsubscribe_link = subscriber_unsubscribe_link(&app).await;
+    reqwest::Client::new()
+        .post(unsubscribe_link)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn queued_deliveries_are_skipped_after_unsubscribing() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_test_user().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    let unsubscribe_link = subscriber_unsubscribe_link(&app).await;
+    reqwest::Client::new()
+        .post(unsubscribe_link)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn the_unsubscribe_page_asks_for_confirmation() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+
+    let unsubscribe_link = subscriber_unsubscribe_link(&app).await;
+    let response = reqwest::get(&unsubscribe_link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"<button type="submit">Unsubscribe</button>"#));
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn forged_unsubscribe_tokens_are_rejected_with_a_401() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+
+    let mut connection = app.pool.get().await.unwrap();
+    let subscriber_id =
+        check_subscriber_existance(&mut connection, SUBSCRIBER_EMAIL)
+            .await
+            .pop()
+            .unwrap()
+            .id;
+    let token = UnsubscribeToken::generate(
+        subscriber_id,
+        &Secret::new("not-the-application-secret".to_string()),
+    );
+    let response = reqwest::Client::new()
+        .post(&format!(
+            "{}/subscriptions/unsubscribe?token={}",
+            app.address,
+            token.as_ref()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn unsubscribing_without_a_token_is_rejected_with_a_400() {
+    let app = spawn_app(None).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/subscriptions/unsubscribe", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn old_confirmation_links_cannot_resubscribe_with_a_409() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+
+    let unsubscribe_link = subscriber_unsubscribe_link(&app).await;
+    reqwest::Client::new()
+        .post(unsubscribe_link)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let response = reqwest::get(confirmation_link.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        subscriber_status(&app).await,
+        SubscriptionStatus::Unsubscribed
+    );
+}
+
+#[tokio::test]
+async fn subscribing_again_after_unsubscribing_needs_a_new_confirmation() {
+    let app = spawn_app(None).await;
+    create_confirmed_subscriber(&app).await;
+    reqwest::Client::new()
+        .post(subscriber_unsubscribe_link(&app).await)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = "name=gabriel%20aguiar&email=gabriel.aguiar%40gmail.com";
+    app.subscribe(body.into())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Pending);
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).await;
+    let response = reqwest::get(confirmation_link.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, SubscriptionStatus::Confirmed);
+}